@@ -1,22 +1,34 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use crate::graphics::{create_graphics, Graphics};
+use crate::{cli::Options, format::geojson::ExportOptions, graphics::{create_graphics, Graphics}, project};
 
-use winit::{application::ApplicationHandler, dpi::{PhysicalPosition, PhysicalSize}, event::{DeviceEvent, WindowEvent}, event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy}, window::{Window, WindowId}};
+use winit::{application::ApplicationHandler, dpi::{PhysicalPosition, PhysicalSize}, event::{DeviceEvent, ElementState, KeyEvent, WindowEvent}, event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy}, keyboard::{Key, ModifiersState}, window::{Window, WindowId}};
+
+/// The longest edge in radians written to exported GeoJSON, so that map tools draw the same
+/// great-circle curves as the globe.
+const EXPORT_DENSIFY_ANGLE: f64 = 1.0 * std::f64::consts::PI / 180.0;
 
 enum State {
-    Ready(Graphics),
+    Ready(Box<Graphics>),
     Init(Option<EventLoopProxy<Graphics>>),
 }
 
 pub struct App {
     state: State,
+    modifiers: ModifiersState,
+    /// The file the project was last saved to or opened from.
+    project_path: Option<PathBuf>,
+    /// The options the app was started with, which are applied once the graphics are ready.
+    options: Options,
 }
 
 impl App {
-    pub fn new(event_loop: &EventLoop<Graphics>) -> Self {
+    pub fn new(event_loop: &EventLoop<Graphics>, options: Options) -> Self {
         Self {
             state: State::Init(Some(event_loop.create_proxy())),
+            modifiers: ModifiersState::empty(),
+            project_path: None,
+            options,
         }
     }
 
@@ -44,6 +56,157 @@ impl App {
         }
     }
 
+    /// Forwards a window event to the panels, returning `true` if they used it.
+    fn process_ui_event(&mut self, event: &WindowEvent) -> bool {
+        match &mut self.state {
+            State::Ready(gfx) => gfx.process_ui_event(event),
+            State::Init(_) => false,
+        }
+    }
+
+    fn process_keyboard_event(&mut self, event: &KeyEvent) {
+        if let State::Ready(gfx) = &mut self.state {
+            gfx.process_keyboard_event(event);
+        }
+    }
+
+    /// Handles application wide shortcuts that use the control key.
+    ///
+    /// Returns `true` if the event was a shortcut.
+    fn process_shortcut(&mut self, event: &KeyEvent) -> bool {
+        let State::Ready(gfx) = &mut self.state else {
+            return false;
+        };
+        if !self.modifiers.control_key() || event.state != ElementState::Pressed {
+            return false;
+        }
+
+        match event.logical_key.as_ref() {
+            Key::Character("z" | "Z") if self.modifiers.shift_key() => gfx.redo(),
+            Key::Character("z" | "Z") => gfx.undo(),
+            Key::Character("y" | "Y") => gfx.redo(),
+            Key::Character("s" | "S") => self.save_project(self.modifiers.shift_key()),
+            Key::Character("o" | "O") => self.open_project(),
+            Key::Character("i" | "I") => self.import_geojson(),
+            Key::Character("t" | "T") => self.open_texture(),
+            Key::Character("e" | "E") if self.modifiers.shift_key() => self.export_svg(),
+            Key::Character("e" | "E") => self.export_geojson(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Saves the project, asking for a file if it has not been saved before.
+    ///
+    /// Arguments:
+    ///
+    /// * `save_as`: Whether to ask for a new file even if the project has been saved before.
+    fn save_project(&mut self, save_as: bool) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+
+        let path = match &self.project_path {
+            Some(path) if !save_as => Some(path.clone()),
+            _ => project_dialog()
+                .set_file_name(format!("untitled.{}", project::EXTENSION))
+                .save_file()
+                .map(|path| path.with_extension(project::EXTENSION)),
+        };
+        let Some(path) = path else {
+            return;
+        };
+
+        match gfx.save_project(&path) {
+            Ok(()) => self.project_path = Some(path),
+            Err(err) => log::error!("Failed to save the project to {}: {err}", path.display()),
+        }
+    }
+
+    /// Asks for a project file and opens it.
+    fn open_project(&mut self) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let Some(path) = project_dialog().pick_file() else {
+            return;
+        };
+
+        match gfx.open_project(&path) {
+            Ok(()) => self.project_path = Some(path),
+            Err(err) => log::error!("Failed to open the project {}: {err}", path.display()),
+        }
+    }
+
+    /// Asks for an image and wraps it around the globe.
+    fn open_texture(&mut self) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Image", &["png", "jpg", "jpeg", "tif", "tiff", "bmp", "webp"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        if let Err(err) = gfx.set_texture(Some(path.clone())) {
+            log::error!("Failed to open the texture {}: {err}", path.display());
+        }
+    }
+
+    /// Asks for a GeoJSON file and adds its features to the document.
+    fn import_geojson(&mut self) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let Some(path) = geojson_dialog().pick_file() else {
+            return;
+        };
+
+        match gfx.import_geojson(&path) {
+            Ok(issues) => {
+                for issue in issues {
+                    log::warn!("{}: {issue}", path.display());
+                }
+            },
+            Err(err) => log::error!("Failed to import {}: {err}", path.display()),
+        }
+    }
+
+    /// Asks for a file and writes the document to it as GeoJSON.
+    fn export_geojson(&mut self) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let Some(path) = geojson_dialog().set_file_name("untitled.geojson").save_file() else {
+            return;
+        };
+
+        let options = ExportOptions { densify: Some(EXPORT_DENSIFY_ANGLE) };
+        if let Err(err) = gfx.export_geojson(&path, options) {
+            log::error!("Failed to export {}: {err}", path.display());
+        }
+    }
+
+    /// Asks for a file and writes the document to it as an SVG map.
+    fn export_svg(&mut self) {
+        let State::Ready(gfx) = &mut self.state else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("SVG", &["svg"])
+            .set_file_name("untitled.svg")
+            .save_file()
+        else {
+            return;
+        };
+
+        if let Err(err) = gfx.export_svg(&path) {
+            log::error!("Failed to export {}: {err}", path.display());
+        }
+    }
+
     fn update_cursor_position(&mut self, pos: PhysicalPosition<f64>) {
         if let State::Ready(gfx) = &mut self.state {
             gfx.update_cursor_position(pos);
@@ -53,24 +216,35 @@ impl App {
 
 
 
+/// Creates a file dialog that only shows GeoJSON files.
+fn geojson_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new().add_filter("GeoJSON", &["geojson", "json"])
+}
+
+/// Creates a file dialog that only shows project files.
+fn project_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new().add_filter("Sitelen project", &[project::EXTENSION])
+}
+
 impl ApplicationHandler<Graphics> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let State::Init(proxy) = &mut self.state {
-            if let Some(proxy) = proxy.take() {
-                let win_attr = Window::default_attributes().with_title("Sitelen");
+        if let State::Init(proxy) = &mut self.state
+            && let Some(proxy) = proxy.take()
+        {
+            let win_attr = Window::default_attributes().with_title("Sitelen");
 
-                let window = Arc::new(
-                    event_loop
-                        .create_window(win_attr)
-                        .expect("create window err."),
-                );
+            let window = Arc::new(
+                event_loop
+                    .create_window(win_attr)
+                    .expect("create window err."),
+            );
 
-                pollster::block_on(create_graphics(window, proxy));
-            }
+            pollster::block_on(create_graphics(window, proxy, self.options.texture.clone(), self.options.sample_count, self.options.history_depth));
         }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
+        let ui_consumed = self.process_ui_event(&event);
         match event {
             WindowEvent::Resized(size) => self.resized(size),
             WindowEvent::RedrawRequested => {
@@ -81,6 +255,10 @@ impl ApplicationHandler<Graphics> for App {
             WindowEvent::CursorMoved { device_id: _, position } => {
                 self.update_cursor_position(position);
             }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::KeyboardInput { event, .. } if !ui_consumed && !self.process_shortcut(&event) => {
+                self.process_keyboard_event(&event);
+            }
             _ => {}
         }
     }
@@ -95,7 +273,14 @@ impl ApplicationHandler<Graphics> for App {
     }
 
     fn user_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, event: Graphics) {
-        self.state = State::Ready(event);
+        let mut gfx = Box::new(event);
+        if let Some(path) = self.options.project.take() {
+            match gfx.open_project(&path) {
+                Ok(()) => self.project_path = Some(path),
+                Err(err) => log::error!("Failed to open the project {}: {err}", path.display()),
+            }
+        }
+        self.state = State::Ready(gfx);
     }
     
 
@@ -0,0 +1,317 @@
+// Draws every segment of a line as a quad that is expanded on screen, see `render/polyline.rs`.
+//
+// The quads of two segments meeting at a corner are cut along the bisector of the corner, so
+// translucent lines are not blended twice there, and the corner itself is shaped in the fragment
+// shader. Edges fade out over a pixel instead of relying on multisampling.
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// See `LineParams` in `render/polyline.rs`.
+struct LineParams {
+    viewport: vec2<f32>,
+    pixels_per_unit: f32,
+    units_per_kilometre: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> params: LineParams;
+
+// The flags of a segment, see `LineSegment` in `vertex.rs`.
+const KILOMETRES: u32 = 1u;
+const LINE_START: u32 = 2u;
+const LINE_END: u32 = 4u;
+const CAP_SHIFT: u32 = 3u;
+const JOIN_SHIFT: u32 = 5u;
+const ON_GLOBE: u32 = 128u;
+
+// The shapes of the ends of a segment. Caps come first in the order of `LineCap`, followed by the
+// joins in the order of `LineJoin`.
+const BUTT: u32 = 0u;
+const ROUND_CAP: u32 = 1u;
+const SQUARE: u32 = 2u;
+const MITER: u32 = 3u;
+const ROUND_JOIN: u32 = 4u;
+const BEVEL: u32 = 5u;
+
+// The width in pixels added around the quads for the edges to fade out in.
+const FEATHER: f32 = 1.0;
+
+// See `MITER_LIMIT` in `document/style.rs`.
+const MITER_LIMIT: f32 = 4.0;
+
+struct Segment {
+    @location(0) prev: vec3<f32>,
+    @location(1) a: vec3<f32>,
+    @location(2) b: vec3<f32>,
+    @location(3) next: vec3<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) dash: vec4<f32>,
+    @location(6) width: f32,
+    @location(7) along: vec2<f32>,
+    @location(8) flags: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // The position relative to the ends of the segment in pixels.
+    @location(1) @interpolate(linear) from_a: vec2<f32>,
+    @location(2) @interpolate(linear) from_b: vec2<f32>,
+    @location(3) @interpolate(linear) half_width: f32,
+    // The distance along the line in the unit of the dash pattern.
+    @location(4) along: f32,
+    @location(5) @interpolate(flat) dash: vec4<f32>,
+    // The direction of the segment on screen.
+    @location(6) @interpolate(flat) dir: vec2<f32>,
+    // For each end, the normal of the bisector pointing away from the segment and the direction
+    // of the outer side of the corner.
+    @location(7) @interpolate(flat) corner_a: vec4<f32>,
+    @location(8) @interpolate(flat) corner_b: vec4<f32>,
+    // For each end, the cosine of half the angle the line turns by.
+    @location(9) @interpolate(flat) cos_half: vec2<f32>,
+    @location(10) @interpolate(flat) shapes: vec2<u32>,
+    // Negative behind the horizon of the globe.
+    @location(11) facing: f32,
+};
+
+// How one end of a segment is shaped.
+struct End {
+    shape: u32,
+    // The normal of the bisector pointing towards the next segment.
+    cut: vec2<f32>,
+    // The direction of the outer side of the corner.
+    outer: vec2<f32>,
+    cos_half: f32,
+    // How far the quad reaches past the end in pixels.
+    extension: f32,
+};
+
+fn project(position: vec3<f32>) -> vec4<f32> {
+    return camera.view_proj * vec4<f32>(position, 1.0);
+}
+
+// Returns the position in pixels relative to the centre of the image.
+fn to_screen(clip: vec4<f32>) -> vec2<f32> {
+    return clip.xy / clip.w * params.viewport * 0.5;
+}
+
+// Returns the direction on screen between two points, or `fallback` if they cannot be told apart.
+fn screen_direction(p: vec3<f32>, q: vec3<f32>, fallback: vec2<f32>) -> vec2<f32> {
+    let clip_from = project(p);
+    let clip_to = project(q);
+    if clip_from.w <= 0.0 || clip_to.w <= 0.0 {
+        return fallback;
+    }
+    let d = to_screen(clip_to) - to_screen(clip_from);
+    if dot(d, d) < 1e-8 {
+        return fallback;
+    }
+    return normalize(d);
+}
+
+// Returns how far a point faces the camera, which is negative behind the horizon of the globe.
+fn facing(position: vec3<f32>, flags: u32) -> f32 {
+    if (flags & ON_GLOBE) == 0u {
+        return 1.0;
+    }
+    return dot(position, camera.view_pos.xyz - position);
+}
+
+// Returns half the width of a segment in pixels at a point.
+fn half_width(segment: Segment, clip: vec4<f32>) -> f32 {
+    if (segment.flags & KILOMETRES) == 0u {
+        return 0.5 * segment.width;
+    }
+    // The rows of the view matrix have unit length, so the second row of the view projection
+    // matrix is as long as the vertical scale of the projection.
+    let scale = length(vec3<f32>(camera.view_proj[0][1], camera.view_proj[1][1], camera.view_proj[2][1]));
+    let units = segment.width * params.units_per_kilometre;
+    return 0.5 * units * scale * params.viewport.y * 0.5 / clip.w;
+}
+
+// Shapes the end of a segment between the directions of the line before and after it.
+fn end_of(line_end: bool, d_in: vec2<f32>, d_out: vec2<f32>, flags: u32, radius: f32) -> End {
+    var end: End;
+    let reach = radius + FEATHER;
+    if line_end {
+        end.shape = (flags >> CAP_SHIFT) & 3u;
+        end.extension = select(reach, FEATHER, end.shape == BUTT);
+        return end;
+    }
+
+    end.shape = MITER + ((flags >> JOIN_SHIFT) & 3u);
+    let sum = d_in + d_out;
+    end.cos_half = 0.5 * length(sum);
+    // A line turning back onto itself has no bisector, so the segments overlap instead.
+    if end.cos_half > 1e-3 {
+        end.cut = sum / (2.0 * end.cos_half);
+    }
+    let diff = d_in - d_out;
+    if dot(diff, diff) > 1e-12 {
+        end.outer = normalize(diff);
+    }
+    end.extension = reach;
+    if end.shape == MITER {
+        if end.cos_half * MITER_LIMIT < 1.0 {
+            end.shape = BEVEL;
+        } else {
+            // The edges of the quad have to reach the bisector to form the tip.
+            let tan_half = sqrt(max(1.0 - end.cos_half * end.cos_half, 0.0)) / end.cos_half;
+            end.extension = reach * max(tan_half, 1.0);
+        }
+    }
+    return end;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, segment: Segment) -> VertexOutput {
+    var out: VertexOutput;
+    let clip_a = project(segment.a);
+    let clip_b = project(segment.b);
+    if clip_a.w <= 0.0 || clip_b.w <= 0.0 {
+        // Segments reaching behind the camera collapse into a point and are not drawn.
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        return out;
+    }
+
+    let a = to_screen(clip_a);
+    let b = to_screen(clip_b);
+    let dir = screen_direction(segment.a, segment.b, vec2<f32>(1.0, 0.0));
+    let start = (segment.flags & LINE_START) != 0u;
+    let finish = (segment.flags & LINE_END) != 0u;
+    var prev_dir = dir;
+    if !start {
+        prev_dir = screen_direction(segment.prev, segment.a, dir);
+    }
+    var next_dir = dir;
+    if !finish {
+        next_dir = screen_direction(segment.b, segment.next, dir);
+    }
+
+    // Lines thinner than a pixel are drawn a pixel wide and fainter.
+    let at_b = index >= 2u;
+    let clip = select(clip_a, clip_b, at_b);
+    let width = 2.0 * half_width(segment, clip);
+    let radius = max(width, 1.0) * 0.5;
+    let end_a = end_of(start, prev_dir, dir, segment.flags, max(half_width(segment, clip_a), 0.5));
+    let end_b = end_of(finish, dir, next_dir, segment.flags, max(half_width(segment, clip_b), 0.5));
+
+    // The vertices 0 and 1 lie at `a`, 2 and 3 at `b`, on alternating sides of the line.
+    let side = select(-1.0, 1.0, (index & 1u) == 1u);
+    let normal = vec2<f32>(-dir.y, dir.x);
+    let extension = select(-end_a.extension, end_b.extension, at_b);
+    let position = select(a, b, at_b) + normal * side * (radius + FEATHER) + dir * extension;
+    out.clip_position = vec4<f32>(position / (params.viewport * 0.5) * clip.w, clip.z, clip.w);
+
+    out.color = vec4<f32>(segment.color.rgb, segment.color.a * min(width, 1.0));
+    out.from_a = position - a;
+    out.from_b = position - b;
+    out.half_width = radius;
+
+    // Dashes in pixels keep their length where the globe is closest to the camera and shrink
+    // with the distance elsewhere, as if they were painted onto the ground.
+    let scale = select(params.pixels_per_unit, 1.0 / params.units_per_kilometre, (segment.flags & KILOMETRES) != 0u);
+    let along = segment.along * scale;
+    let length_on_screen = distance(a, b);
+    var rate = 0.0;
+    if length_on_screen > 1e-3 {
+        rate = (along.y - along.x) / length_on_screen;
+    }
+    out.along = select(along.x, along.y, at_b) + extension * rate;
+    out.dash = segment.dash;
+
+    // The quads stand out of the curved surface, so the globe is hidden by testing the horizon
+    // instead of the depth.
+    out.facing = facing(select(segment.a, segment.b, at_b), segment.flags);
+
+    out.dir = dir;
+    out.corner_a = vec4<f32>(-end_a.cut, end_a.outer);
+    out.corner_b = vec4<f32>(end_b.cut, end_b.outer);
+    out.cos_half = vec2<f32>(end_a.cos_half, end_b.cos_half);
+    out.shapes = vec2<u32>(end_a.shape, end_b.shape);
+    return out;
+}
+
+// Turns how far the centre of a pixel lies inside an edge into the part of the pixel covered.
+fn edge_coverage(inside: f32) -> f32 {
+    return clamp(inside + 0.5, 0.0, 1.0);
+}
+
+// Returns the part of a pixel covered near one end of a segment.
+//
+// `offset` is the position relative to the end and `outward` the direction leaving the segment
+// through it.
+fn end_coverage(offset: vec2<f32>, outward: vec2<f32>, corner: vec4<f32>, cos_half: f32, shape: u32, radius: f32) -> f32 {
+    let beyond = dot(offset, outward);
+    switch shape {
+        case BUTT: {
+            return edge_coverage(-beyond);
+        }
+        case SQUARE: {
+            return edge_coverage(radius - beyond);
+        }
+        case ROUND_CAP: {
+            return select(1.0, edge_coverage(radius - length(offset)), beyond > 0.0);
+        }
+        default: {}
+    }
+
+    // The other side of the bisector is drawn by the adjacent segment.
+    if dot(offset, corner.xy) > 0.0 {
+        return 0.0;
+    }
+    if beyond <= 0.0 {
+        return 1.0;
+    }
+    switch shape {
+        case ROUND_JOIN: {
+            return edge_coverage(radius - length(offset));
+        }
+        case BEVEL: {
+            return edge_coverage(radius * cos_half - dot(offset, corner.zw));
+        }
+        default: {
+            // The edges of the quad already form the miter.
+            return 1.0;
+        }
+    }
+}
+
+// Returns the part of a pixel that lies on a dash, for up to two dashes and gaps per period.
+fn dash_coverage(along: f32, step: f32, dash: vec4<f32>) -> f32 {
+    let period = dash.x + dash.y + dash.z + dash.w;
+    if period <= 0.0 {
+        return 1.0;
+    }
+    let t = along - floor(along / period) * period;
+    // How far the position lies inside the nearest dash, including the dashes of the
+    // neighbouring periods.
+    let first = min(t, dash.x - t);
+    let second = min(t - dash.x - dash.y, period - dash.w - t);
+    let previous = -dash.w - t;
+    let next = t - period;
+    let inside = max(max(first, second), max(previous, next));
+    return clamp(inside / max(step, 1e-6) + 0.5, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Derivatives must be taken before any branch that differs between pixels.
+    let along_step = fwidth(in.along);
+
+    let normal = vec2<f32>(-in.dir.y, in.dir.x);
+    var coverage = edge_coverage(in.half_width - abs(dot(in.from_a, normal)));
+    coverage = min(coverage, end_coverage(in.from_a, -in.dir, in.corner_a, in.cos_half.x, in.shapes.x, in.half_width));
+    coverage = min(coverage, end_coverage(in.from_b, in.dir, in.corner_b, in.cos_half.y, in.shapes.y, in.half_width));
+    coverage = min(coverage, dash_coverage(in.along, along_step, in.dash));
+    if coverage <= 0.0 || in.facing < 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
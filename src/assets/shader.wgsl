@@ -13,12 +13,14 @@ struct Light {
 @group(2) @binding(0)
 var<uniform> light: Light;
 
-
-struct Mouse {
-    position: vec2<f32>
-}
+// The resolution of the sphere generated by `vs_procedural`.
+struct SphereParams {
+    // The number of rows of cells between the poles. There are twice as many columns.
+    rows: u32,
+    radius: f32,
+};
 @group(3) @binding(0)
-var<uniform> mouse: Mouse;
+var<uniform> sphere: SphereParams;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -48,6 +50,40 @@ fn vs_main(
     return out;
 }
 
+// Generates a UV sphere without a vertex buffer, see `render/procedural.rs`.
+//
+// Every six vertices form the two triangles of a cell, counted along the rows from the north pole
+// and the antimeridian.
+@vertex
+fn vs_procedural(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let pi = 3.14159265;
+    // The corners of both triangles as offsets in columns and rows, counter-clockwise when seen
+    // from outside.
+    var corners = array<vec2<u32>, 6>(
+        vec2<u32>(0u, 0u), vec2<u32>(0u, 1u), vec2<u32>(1u, 1u),
+        vec2<u32>(0u, 0u), vec2<u32>(1u, 1u), vec2<u32>(1u, 0u),
+    );
+    let columns = 2u * sphere.rows;
+    let cell = index / 6u;
+    let corner = corners[index % 6u];
+    let column = cell % columns + corner.x;
+    let row = cell / columns + corner.y;
+
+    let tex_coords = vec2<f32>(f32(column) / f32(columns), f32(row) / f32(sphere.rows));
+    // Both sides of the antimeridian use the same position, so that no gap opens between them.
+    let lon = 2.0 * pi * f32(column % columns) / f32(columns);
+    let colatitude = pi * tex_coords.y;
+    let normal = vec3<f32>(cos(lon) * sin(colatitude), cos(colatitude), -sin(lon) * sin(colatitude));
+
+    var out: VertexOutput;
+    out.tex_coords = tex_coords;
+    out.world_normal = normal;
+    out.world_position = normal * sphere.radius;
+    out.clip_position = camera.view_proj * vec4<f32>(out.world_position, 1.0);
+    out.camera_view_pos = camera.view_pos;
+    return out;
+}
+
 // Fragment shader
 
 @group(0) @binding(0)
@@ -57,8 +93,45 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    
+    return shade(in, textureSample(t_diffuse, s_diffuse, in.tex_coords));
+}
+
+// Draws the globe with a tile pyramid instead of a single texture, see `tiles.wgsl`.
+@fragment
+fn fs_tiled(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in, sample_tiles(in.tex_coords));
+}
+
+// Draws meshes without a seam at the antimeridian, see `SphereMesh::tex_coords_from_normal`.
+@fragment
+fn fs_normal(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tex_coords = tex_coords_from_normal(normalize(in.world_normal));
+    // `u` jumps from 1 to 0 at the antimeridian, which would make the derivatives pick the
+    // smallest mip level along it. There, `u` changes by almost 1 between neighbouring pixels,
+    // far more than the texture moves across a pixel, so `u` wrapped into -0.5..0.5 is used
+    // instead. It has no jump there and the sampler repeats it onto the same texels.
+    let shifted = fract(tex_coords.x + 0.5) - 0.5;
+    let u = select(shifted, tex_coords.x, fwidth(tex_coords.x) < 0.5);
+    return shade(in, textureSample(t_diffuse, s_diffuse, vec2<f32>(u, tex_coords.y)));
+}
+
+// Draws meshes without a seam at the antimeridian with a tile pyramid.
+@fragment
+fn fs_tiled_normal(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in, sample_tiles(tex_coords_from_normal(normalize(in.world_normal))));
+}
+
+// Returns the coordinate of the equirectangular texture at a point of the globe, see
+// `sphere::tex_coords_from_normal`.
+fn tex_coords_from_normal(normal: vec3<f32>) -> vec2<f32> {
+    let pi = 3.14159265;
+    let u = fract(atan2(-normal.z, normal.x) / (2.0 * pi));
+    let v = 0.5 - asin(clamp(normal.y, -1.0, 1.0)) / pi;
+    return vec2<f32>(u, v);
+}
+
+// Lights the color of the globe at a fragment.
+fn shade(in: VertexOutput, object_color: vec4<f32>) -> vec4<f32> {
     let ambient_color = light.color.xyz * light.color.a;
 
     let light_dir = normalize(light.position.xyz - in.world_position);
@@ -0,0 +1,42 @@
+// Samples a tile pyramid streamed into an atlas, see `render/tiles.rs`.
+//
+// This is appended to the shaders drawing the texture of the globe, which declare `s_diffuse`.
+
+struct TileParams {
+    // The finest level of the pyramid, which has one page table texel per tile.
+    max_level: u32,
+    // The size of the part of the map covered by a tile, in pixels.
+    tile_size: f32,
+    // The number of pixels copied from the neighbours around each tile.
+    border: f32,
+    // The width and height of the atlas in pixels.
+    atlas_size: f32,
+};
+
+@group(0) @binding(2)
+var t_atlas: texture_2d<f32>;
+@group(0) @binding(3)
+var t_page_table: texture_2d<u32>;
+@group(0) @binding(4)
+var<uniform> tile_params: TileParams;
+
+// Returns the color of the finest tile in the atlas at a coordinate of the equirectangular map.
+fn sample_tiles(tex_coords: vec2<f32>) -> vec4<f32> {
+    // The map repeats horizontally across the antimeridian.
+    let uv = vec2<f32>(fract(tex_coords.x), clamp(tex_coords.y, 0.0, 1.0));
+    let cells = textureDimensions(t_page_table);
+    let cell = min(vec2<u32>(uv * vec2<f32>(cells)), cells - vec2<u32>(1u));
+    let entry = textureLoad(t_page_table, cell, 0);
+    if entry.w == 0u {
+        // No tile has been read yet.
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let level = entry.z;
+    let tiles = vec2<f32>(f32(2u << level), f32(1u << level));
+    let tile = cell >> vec2<u32>(tile_params.max_level - level);
+    let local = clamp(uv * tiles - vec2<f32>(tile), vec2<f32>(0.0), vec2<f32>(1.0));
+    let slot_size = tile_params.tile_size + 2.0 * tile_params.border;
+    let texel = vec2<f32>(entry.xy) * slot_size + tile_params.border + local * tile_params.tile_size;
+    return textureSampleLevel(t_atlas, s_diffuse, texel / tile_params.atlas_size, 0.0);
+}
@@ -4,8 +4,12 @@ use winit::{
     window::Window,
 };
 
-use super::OrbitCamera;
+use glam::Vec2;
 
+use super::{MapCamera, OrbitCamera};
+
+/// The factor by which one step of the mouse wheel changes the visible part of a map.
+const MAP_ZOOM_FACTOR: f32 = 1.25;
 
 #[derive(Debug)]
 pub struct CameraController {
@@ -34,28 +38,12 @@ impl CameraController {
         camera: &mut OrbitCamera,
     ) {
         match event {
-            DeviceEvent::Button { button: 2, state } => {
-                self.is_drag_rotate = *state == ElementState::Pressed;
-            },
-            DeviceEvent::Button { button: 1, state } => {
-                self.is_left_down = *state == ElementState::Pressed;
-            },
-            DeviceEvent::Button { button: 3, state } => {
-                self.is_right_down = *state == ElementState::Pressed;
-            },
-            DeviceEvent::MouseWheel { delta, .. } => {
-                let scroll_amount = match delta {
-                    // A mouse line is about 1 px.
-                    MouseScrollDelta::LineDelta(_, scroll) => *scroll as f32,
-                    MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => {
-                        *scroll as f32
-                    }
-                };
-
-                if scroll_amount.is_sign_positive() {
-                    camera.add_distance(1.0 * self.zoom_delta);
+            DeviceEvent::Button { button, state } => self.process_button(*button, *state),
+            DeviceEvent::MouseWheel { delta } => {
+                if scroll_amount(delta).is_sign_positive() {
+                    camera.add_distance(self.zoom_delta);
                 } else {
-                    camera.add_distance(-1.0 * self.zoom_delta);
+                    camera.add_distance(-self.zoom_delta);
                 }
 
                 window.request_redraw();
@@ -67,10 +55,70 @@ impl CameraController {
                     window.request_redraw();
                 }
 
+                // Redraw so the stroke under construction follows the cursor.
                 if self.is_left_down {
+                    window.request_redraw();
                 }
             },
             _ => (),
         }
     }
+
+    /// Pans a flat map with the middle mouse button and zooms it with the mouse wheel.
+    ///
+    /// Arguments:
+    ///
+    /// * `event`: The input event.
+    /// * `window`: The window showing the map, redrawn when the view changes.
+    /// * `camera`: The camera looking at the map.
+    pub fn process_map_events(
+        &mut self,
+        event: &DeviceEvent,
+        window: &Window,
+        camera: &mut MapCamera,
+    ) {
+        match event {
+            DeviceEvent::Button { button, state } => self.process_button(*button, *state),
+            DeviceEvent::MouseWheel { delta } => {
+                if scroll_amount(delta).is_sign_positive() {
+                    camera.zoom(MAP_ZOOM_FACTOR);
+                } else {
+                    camera.zoom(1.0 / MAP_ZOOM_FACTOR);
+                }
+                window.request_redraw();
+            },
+            DeviceEvent::MouseMotion { delta } => {
+                if self.is_drag_rotate {
+                    camera.pan(Vec2::new(delta.0 as f32, delta.1 as f32));
+                    window.request_redraw();
+                }
+
+                // Redraw so the stroke under construction follows the cursor.
+                if self.is_left_down {
+                    window.request_redraw();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    /// Tracks which mouse buttons are held down.
+    fn process_button(&mut self, button: u32, state: ElementState) {
+        let pressed = state == ElementState::Pressed;
+        match button {
+            1 => self.is_left_down = pressed,
+            2 => self.is_drag_rotate = pressed,
+            3 => self.is_right_down = pressed,
+            _ => (),
+        }
+    }
+}
+
+/// Returns how far the mouse wheel was turned, positive when scrolling up.
+fn scroll_amount(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        // A mouse line is about 1 px.
+        MouseScrollDelta::LineDelta(_, scroll) => *scroll,
+        MouseScrollDelta::PixelDelta(PhysicalPosition { y: scroll, .. }) => *scroll as f32,
+    }
 }
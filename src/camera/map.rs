@@ -0,0 +1,154 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use super::Camera;
+
+/// A [MapCamera] looks straight down onto a flat map lying in the `z = 0` plane.
+///
+/// It can only be panned across the map and zoomed in and out.
+#[derive(Debug, Clone, Copy)]
+pub struct MapCamera {
+    /// The point of the map in the centre of the view, in world units.
+    pub center: Vec2,
+    /// Half the height of the visible part of the map in world units.
+    pub half_height: f32,
+    /// The smallest `half_height`, which is how far the camera can zoom in.
+    pub min_half_height: f32,
+    /// The largest `half_height`, which is how far the camera can zoom out.
+    pub max_half_height: f32,
+    /// The width and height of the rendered image in pixels.
+    pub viewport: Vec2,
+    /// The height of the eye above the map. Everything drawn between the map and the eye is visible.
+    pub eye_height: f32,
+}
+
+impl Camera for MapCamera {
+    fn build_view_projection_matrix(&self) -> Mat4 {
+        let half_size = Vec2::new(self.half_height * self.aspect(), self.half_height);
+        let view = Mat4::look_at_rh(self.eye_position(), self.center.extend(0.0), Vec3::Y);
+        let proj = Mat4::orthographic_rh(-half_size.x, half_size.x, -half_size.y, half_size.y, 0.0, 2.0 * self.eye_height);
+        proj * view
+    }
+
+    fn eye_position(&self) -> Vec3 {
+        self.center.extend(self.eye_height)
+    }
+}
+
+impl MapCamera {
+    /// Creates a new [MapCamera] showing the centre of the map.
+    ///
+    /// Arguments:
+    ///
+    /// * `half_height`: Half the height of the visible part of the map in world units.
+    /// * `viewport`: The width and height of the rendered image in pixels.
+    /// * `eye_height`: The height of the eye above the map.
+    pub fn new(half_height: f32, viewport: Vec2, eye_height: f32) -> Self {
+        Self {
+            center: Vec2::ZERO,
+            half_height,
+            min_half_height: f32::EPSILON,
+            max_half_height: f32::MAX,
+            viewport: viewport.max(Vec2::ONE),
+            eye_height,
+        }
+    }
+
+    /// The width of the view divided by its height.
+    pub fn aspect(&self) -> f32 {
+        self.viewport.x / self.viewport.y
+    }
+
+    /// The size of one pixel on the map in world units.
+    pub fn pixel_size(&self) -> f32 {
+        2.0 * self.half_height / self.viewport.y
+    }
+
+    /// Sets how much of the map is visible, respecting the zoom bounds.
+    ///
+    /// Arguments:
+    ///
+    /// * `half_height`: Half the height of the visible part of the map in world units.
+    pub fn set_half_height(&mut self, half_height: f32) {
+        self.half_height = half_height.clamp(self.min_half_height, self.max_half_height);
+    }
+
+    /// Zooms in or out, keeping the centre of the view in place.
+    ///
+    /// Arguments:
+    ///
+    /// * `factor`: The factor by which the visible part of the map grows.
+    pub fn zoom(&mut self, factor: f32) {
+        self.set_half_height(self.half_height * factor);
+    }
+
+    /// Moves the view across the map.
+    ///
+    /// Arguments:
+    ///
+    /// * `pixels`: How far the map is dragged in pixels, with `y` pointing down as on screen.
+    pub fn pan(&mut self, pixels: Vec2) {
+        self.center += Vec2::new(-pixels.x, pixels.y) * self.pixel_size();
+    }
+
+    /// Returns the point of the map under a pixel of the rendered image, in world units.
+    ///
+    /// Arguments:
+    ///
+    /// * `pixel`: The pixel position with the origin in the top left corner.
+    pub fn map_point(&self, pixel: Vec2) -> Vec2 {
+        let offset = pixel - self.viewport / 2.0;
+        self.center + Vec2::new(offset.x, -offset.y) * self.pixel_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> MapCamera {
+        let mut camera = MapCamera::new(10.0, Vec2::new(200.0, 100.0), 5.0);
+        camera.min_half_height = 1.0;
+        camera.max_half_height = 40.0;
+        camera
+    }
+
+    #[test]
+    fn zooms_within_its_bounds() {
+        let mut camera = camera();
+        camera.zoom(2.0);
+        assert_eq!(camera.half_height, 20.0);
+        for _ in 0..10 {
+            camera.zoom(2.0);
+            assert!(camera.half_height <= camera.max_half_height);
+        }
+        assert_eq!(camera.half_height, camera.max_half_height);
+        for _ in 0..20 {
+            camera.zoom(0.5);
+            assert!(camera.half_height >= camera.min_half_height);
+        }
+        assert_eq!(camera.half_height, camera.min_half_height);
+
+        camera.set_half_height(f32::INFINITY);
+        assert_eq!(camera.half_height, camera.max_half_height);
+    }
+
+    #[test]
+    fn pans_the_map_with_the_cursor() {
+        let mut camera = camera();
+        let grabbed = camera.map_point(Vec2::new(50.0, 20.0));
+        camera.pan(Vec2::new(30.0, -10.0));
+        assert!(camera.map_point(Vec2::new(80.0, 10.0)).distance(grabbed) < 1e-5);
+        assert_eq!(camera.map_point(camera.viewport / 2.0), camera.center);
+    }
+
+    #[test]
+    fn rays_hit_the_map_under_the_pixel() {
+        let mut camera = camera();
+        camera.center = Vec2::new(3.0, -2.0);
+        for pixel in [Vec2::ZERO, Vec2::new(100.0, 50.0), Vec2::new(170.0, 90.0)] {
+            let ray = camera.ray_through_pixel(pixel, camera.viewport);
+            let hit = ray.origin - ray.direction * (ray.origin.z / ray.direction.z);
+            assert!(hit.truncate().distance(camera.map_point(pixel)) < 1e-4, "{pixel}");
+        }
+    }
+}
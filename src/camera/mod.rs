@@ -1,13 +1,34 @@
 pub mod controller;
+pub mod map;
 pub mod orbit;
+pub mod ray;
 
-use glam::Mat4;
+use glam::{Mat4, Vec2, Vec3};
 
+pub use map::MapCamera;
 pub use orbit::OrbitCamera;
+pub use ray::Ray;
 
 /// A camera is used for rendering specific parts of the scene.
 pub trait Camera: Sized {
     fn build_view_projection_matrix(&self) -> Mat4;
+
+    /// The position of the eye in world space.
+    fn eye_position(&self) -> Vec3;
+
+    /// Builds the ray that passes through a pixel of the rendered image.
+    ///
+    /// Arguments:
+    ///
+    /// * `pixel`: The pixel position with the origin in the top left corner.
+    /// * `viewport`: The width and height of the rendered image in pixels.
+    fn ray_through_pixel(&self, pixel: Vec2, viewport: Vec2) -> Ray {
+        let ndc = Vec2::new(
+            2.0 * pixel.x / viewport.x - 1.0,
+            1.0 - 2.0 * pixel.y / viewport.y,
+        );
+        Ray::from_ndc(self.build_view_projection_matrix(), ndc)
+    }
 }
 
 /// The camera uniform contains the data linked to the camera that is passed to the shader.
@@ -27,9 +48,10 @@ impl CameraUniform {
     /// Updates the view projection matrix of this [CameraUniform].
     ///
     /// Arguments:
-    /// * `camera`: The [OrbitCamera] from which the matrix will be computed.
-    pub fn update_view_proj(&mut self, camera: &OrbitCamera) {
-        self.view_position = [camera.eye.x, camera.eye.y, camera.eye.z, 1.0];
+    /// * `camera`: The camera from which the matrix will be computed.
+    pub fn update_view_proj(&mut self, camera: &impl Camera) {
+        let eye = camera.eye_position();
+        self.view_position = [eye.x, eye.y, eye.z, 1.0];
         self.view_proj = camera.build_view_projection_matrix().to_cols_array_2d();
     }
 }
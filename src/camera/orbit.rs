@@ -1,6 +1,7 @@
 use glam::{Mat4, Vec3};
 
 use super::Camera;
+use crate::geo::LatLon;
 
 /// An [OrbitCamera] only permits rotation of the eye on a spherical shell around a target.
 #[derive(Debug, Clone, Copy)]
@@ -35,6 +36,10 @@ impl Camera for OrbitCamera {
         let proj = Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar);
         proj * view
     }
+
+    fn eye_position(&self) -> Vec3 {
+        self.eye
+    }
 }
 
 impl OrbitCamera {
@@ -135,6 +140,24 @@ impl OrbitCamera {
         self.set_yaw(self.yaw + (delta * perc_zoom.max(0.02) * perc_pitch));
     }
 
+    /// Returns the coordinate on the globe straight below the eye, which is shown in the centre
+    /// of the view when the target is the centre of the globe.
+    pub fn center(&self) -> LatLon {
+        // The eye lies on the prime meridian when the yaw is -90°, see [LatLon::to_unit_vector].
+        LatLon::new(self.pitch.to_degrees() as f64, self.yaw.to_degrees() as f64 + 90.0)
+    }
+
+    /// Rotates the eye above a coordinate on the globe, keeping its distance.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The coordinate that will be shown in the centre of the view.
+    pub fn look_at(&mut self, center: LatLon) {
+        self.pitch = (center.lat.to_radians() as f32).clamp(self.bounds.min_pitch, self.bounds.max_pitch);
+        self.yaw = (center.lon - 90.0).to_radians() as f32;
+        self.update();
+    }
+
     // function easeInOutCubic(x: number): number {
     // return x < 0.5 ? 4 * x * x * x : 1 - Math.pow(-2 * x + 2, 3) / 2;
     // }
@@ -0,0 +1,97 @@
+use glam::{Mat4, Vec2, Vec3};
+
+/// A half-line in world space, used to find what lies under the cursor.
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    /// The point where the ray starts.
+    pub origin: Vec3,
+    /// The normalised direction of the ray.
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Creates a ray through a point given in normalised device coordinates.
+    ///
+    /// Arguments:
+    ///
+    /// * `view_proj`: The view projection matrix of the camera that renders the scene.
+    /// * `ndc`: The point on screen, with `[-1, -1]` in the bottom left and `[1, 1]` in the top right corner.
+    pub fn from_ndc(view_proj: Mat4, ndc: Vec2) -> Self {
+        // The near plane is very close to the eye, which makes the inverse too imprecise in `f32`.
+        let inverse = view_proj.as_dmat4().inverse();
+        let ndc = ndc.as_dvec2();
+        // wgpu uses a depth range of `[0, 1]`, so these are points on the near and far plane.
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+
+        Self {
+            origin: near.as_vec3(),
+            direction: (far - near).normalize().as_vec3(),
+        }
+    }
+
+    /// Returns the closest point in front of the origin where the ray enters a sphere.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The centre of the sphere.
+    /// * `radius`: The radius of the sphere.
+    pub fn intersect_sphere(&self, center: Vec3, radius: f32) -> Option<Vec3> {
+        let oc = self.origin - center;
+        let b = oc.dot(self.direction);
+        let c = oc.length_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrt_d = discriminant.sqrt();
+        let t = if -b - sqrt_d >= 0.0 { -b - sqrt_d } else { -b + sqrt_d };
+        (t >= 0.0).then(|| self.origin + self.direction * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Camera, OrbitCamera},
+        geo::LatLon,
+        sphere::GLOBE_RADIUS,
+    };
+
+    const VIEWPORT: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn pick(camera: &OrbitCamera, pixel: Vec2) -> Option<LatLon> {
+        let hit = camera.ray_through_pixel(pixel, VIEWPORT).intersect_sphere(Vec3::ZERO, GLOBE_RADIUS)?;
+        Some(LatLon::from_unit_vector(hit.as_dvec3()))
+    }
+
+    #[test]
+    fn centre_pixel_picks_the_camera_centre() {
+        let mut camera = OrbitCamera::new(15.0, 0.0, 0.0, Vec3::ZERO, VIEWPORT.x / VIEWPORT.y);
+        for (lat, lon) in [(0.0, 0.0), (45.0, 120.0), (-60.0, -179.5), (20.0, 180.0), (80.0, -90.0)] {
+            camera.look_at(LatLon::new(lat, lon));
+            let picked = pick(&camera, VIEWPORT / 2.0).expect("the centre of the view shows the globe");
+            assert!(picked.angular_distance(camera.center()) < 1e-4, "{picked:?} != {:?}", camera.center());
+        }
+    }
+
+    #[test]
+    fn pixels_beside_the_globe_pick_nothing() {
+        let camera = OrbitCamera::new(15.0, 0.0, 0.0, Vec3::ZERO, VIEWPORT.x / VIEWPORT.y);
+        assert_eq!(pick(&camera, Vec2::ZERO), None);
+        assert_eq!(pick(&camera, VIEWPORT), None);
+    }
+
+    #[test]
+    fn intersects_the_near_side_of_a_sphere() {
+        let ray = Ray { origin: Vec3::new(0.0, 0.0, 5.0), direction: Vec3::NEG_Z };
+        assert_eq!(ray.intersect_sphere(Vec3::ZERO, 1.0), Some(Vec3::new(0.0, 0.0, 1.0)));
+        // From inside, the ray leaves through the far side.
+        let ray = Ray { origin: Vec3::ZERO, direction: Vec3::NEG_Z };
+        assert_eq!(ray.intersect_sphere(Vec3::ZERO, 1.0), Some(Vec3::new(0.0, 0.0, -1.0)));
+        // Spheres behind the origin are not hit.
+        assert_eq!(ray.intersect_sphere(Vec3::new(0.0, 0.0, 5.0), 1.0), None);
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use crate::renderer::SAMPLE_COUNTS;
+
+/// The size of screenshots in pixels when no other one is given.
+const DEFAULT_SCREENSHOT_SIZE: (u32, u32) = (1920, 1080);
+
+/// The options the app was started with.
+///
+/// The command line looks like
+/// `[--texture <image>] [--msaa <samples>] [--history-depth <steps>] [--screenshot <out.png> [--size <width>x<height>] [--map] [--software]] [project]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    /// The image wrapped around the globe, or `None` for the built-in texture or the one of the project.
+    pub texture: Option<PathBuf>,
+    /// The project that is opened at start.
+    pub project: Option<PathBuf>,
+    /// The number of samples per pixel used for anti-aliasing, or `None` for the default.
+    pub sample_count: Option<u32>,
+    /// The number of edits that can be undone, or `None` for the default.
+    pub history_depth: Option<usize>,
+    /// Whether to render a screenshot instead of opening a window.
+    pub screenshot: Option<Screenshot>,
+}
+
+/// How a screenshot is rendered, see [crate::screenshot::take].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screenshot {
+    /// The PNG file the screenshot is written to.
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Whether the flat map is rendered instead of the globe.
+    pub map: bool,
+    /// Whether to render in software, for machines without a GPU.
+    pub software: bool,
+}
+
+impl Options {
+    /// Parses the command line arguments, not including the name of the program.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut options = Options::default();
+        let mut output = None;
+        let (mut width, mut height) = DEFAULT_SCREENSHOT_SIZE;
+        let (mut map, mut software) = (false, false);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--texture" => match args.next() {
+                    Some(path) => options.texture = Some(PathBuf::from(path)),
+                    None => return Err("--texture needs the path of an image".to_string()),
+                },
+                "--msaa" => match args.next().and_then(|count| count.parse().ok()) {
+                    Some(count) if SAMPLE_COUNTS.contains(&count) => options.sample_count = Some(count),
+                    _ => return Err("--msaa needs 1, 2, 4 or 8 samples".to_string()),
+                },
+                "--history-depth" => match args.next().and_then(|depth| depth.parse().ok()) {
+                    Some(depth) if depth > 0 => options.history_depth = Some(depth),
+                    _ => return Err("--history-depth needs a positive number of steps".to_string()),
+                },
+                "--screenshot" => match args.next() {
+                    Some(path) => output = Some(PathBuf::from(path)),
+                    None => return Err("--screenshot needs the path of the PNG file".to_string()),
+                },
+                "--size" => match args.next().as_deref().and_then(parse_size) {
+                    Some(size) => (width, height) = size,
+                    None => return Err("--size needs a size like 1920x1080".to_string()),
+                },
+                "--map" => map = true,
+                "--software" => software = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => options.project = Some(PathBuf::from(arg)),
+            }
+        }
+
+        options.screenshot = output.map(|output| Screenshot { output, width, height, map, software });
+        Ok(options)
+    }
+}
+
+/// Parses a size like `1920x1080`.
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_screenshot_without_the_option() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+        assert_eq!(parse(&["--size", "10x10"]).unwrap().screenshot, None);
+    }
+
+    #[test]
+    fn parses_all_options() {
+        let options = parse(&["--texture", "earth.png", "--msaa", "8", "--history-depth", "32", "--screenshot", "out.png", "--size", "640x480", "--map", "--software", "world.sitelen"]);
+
+        assert_eq!(options, Ok(Options {
+            texture: Some(PathBuf::from("earth.png")),
+            project: Some(PathBuf::from("world.sitelen")),
+            sample_count: Some(8),
+            history_depth: Some(32),
+            screenshot: Some(Screenshot {
+                output: PathBuf::from("out.png"),
+                width: 640,
+                height: 480,
+                map: true,
+                software: true,
+            }),
+        }));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse(&["--texture"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--msaa", "3"]).is_err());
+        assert!(parse(&["--history-depth", "0"]).is_err());
+        assert!(parse(&["--history-depth", "many"]).is_err());
+        for size in ["640", "0x480", "ax480", "640x"] {
+            assert!(parse(&["--screenshot", "out.png", "--size", size]).is_err(), "{size}");
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use std::collections::VecDeque;
+
+use super::{Attributes, Document, Feature, FeatureId, Geometry, Layer, LayerId, Style};
+
+/// A reversible change to a [Document].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Inserts `feature` at `index` in the drawing order.
+    AddFeature { index: usize, feature: Feature },
+    /// Removes `feature`, which was found at `index` in the drawing order.
+    RemoveFeature { index: usize, feature: Feature },
+    /// Replaces the geometry of a feature, for example to move one of its vertices.
+    SetGeometry { id: FeatureId, before: Geometry, after: Geometry },
+    /// Replaces the style of a feature, where `None` draws it with the style of its layer.
+    SetStyle { id: FeatureId, before: Option<Style>, after: Option<Style> },
+    /// Inserts `layer` at `index` in the drawing order.
+    AddLayer { index: usize, layer: Layer },
+    /// Removes `layer`, which was found at `index` in the drawing order and has no features.
+    RemoveLayer { index: usize, layer: Layer },
+    /// Moves the layer at `from` in the drawing order to `to`.
+    MoveLayer { from: usize, to: usize },
+    /// Replaces the name, visibility, lock, opacity or style of a layer.
+    SetLayer { before: Layer, after: Layer },
+    /// Several changes that are undone together, applied in order.
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Applies the change to a document.
+    pub fn apply(&self, document: &mut Document) {
+        match self {
+            Command::AddFeature { index, feature } => document.insert(*index, feature.clone()),
+            Command::RemoveFeature { feature, .. } => {
+                document.remove(feature.id());
+            },
+            Command::SetGeometry { id, after, .. } => set_geometry(document, *id, after),
+            Command::SetStyle { id, after, .. } => set_style(document, *id, after),
+            Command::AddLayer { index, layer } => document.insert_layer(*index, layer.clone()),
+            Command::RemoveLayer { layer, .. } => {
+                document.remove_layer(layer.id());
+            },
+            Command::MoveLayer { from, to } => document.move_layer(*from, *to),
+            Command::SetLayer { after, .. } => document.set_layer(after.clone()),
+            Command::Batch(commands) => commands.iter().for_each(|command| command.apply(document)),
+        }
+    }
+
+    /// Reverts a change previously made by [Command::apply].
+    pub fn revert(&self, document: &mut Document) {
+        match self {
+            Command::AddFeature { feature, .. } => {
+                document.remove(feature.id());
+            },
+            Command::RemoveFeature { index, feature } => document.insert(*index, feature.clone()),
+            Command::SetGeometry { id, before, .. } => set_geometry(document, *id, before),
+            Command::SetStyle { id, before, .. } => set_style(document, *id, before),
+            Command::AddLayer { layer, .. } => {
+                document.remove_layer(layer.id());
+            },
+            Command::RemoveLayer { index, layer } => document.insert_layer(*index, layer.clone()),
+            Command::MoveLayer { from, to } => document.move_layer(*to, *from),
+            Command::SetLayer { before, .. } => document.set_layer(before.clone()),
+            Command::Batch(commands) => commands.iter().rev().for_each(|command| command.revert(document)),
+        }
+    }
+
+    /// Tries to combine a command that directly follows this one into it.
+    ///
+    /// Returns `true` if `next` was merged, so that both are undone in a single step.
+    fn merge(&mut self, next: &Command) -> bool {
+        match (self, next) {
+            (
+                Command::SetGeometry { id, after, .. },
+                Command::SetGeometry { id: next_id, after: next_after, .. },
+            ) if id == next_id => {
+                *after = next_after.clone();
+                true
+            },
+            (
+                Command::SetStyle { id, after, .. },
+                Command::SetStyle { id: next_id, after: next_after, .. },
+            ) if id == next_id => {
+                *after = next_after.clone();
+                true
+            },
+            (Command::SetLayer { after, .. }, Command::SetLayer { after: next_after, .. }) if after.id() == next_after.id() => {
+                *after = next_after.clone();
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Creates the commands removing all features of a layer, from the last to the first, so that
+/// each index stays valid until its feature is removed.
+fn remove_features_of(document: &Document, layer: LayerId) -> Vec<Command> {
+    let mut commands: Vec<_> = document
+        .features()
+        .enumerate()
+        .filter(|(_, feature)| feature.layer() == layer)
+        .map(|(index, feature)| Command::RemoveFeature { index, feature: feature.clone() })
+        .collect();
+    commands.reverse();
+    commands
+}
+
+fn set_geometry(document: &mut Document, id: FeatureId, geometry: &Geometry) {
+    if let Some(feature) = document.get_mut(id) {
+        feature.geometry = geometry.clone();
+    }
+}
+
+fn set_style(document: &mut Document, id: FeatureId, style: &Option<Style>) {
+    if let Some(feature) = document.get_mut(id) {
+        feature.style = style.clone();
+    }
+}
+
+/// Records the changes made to a [Document] so that they can be undone and redone.
+#[derive(Debug)]
+pub struct History {
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    /// The most steps that can be undone. Older steps are forgotten.
+    max_depth: usize,
+    /// Whether the last recorded command may absorb the next mergeable one.
+    open: bool,
+}
+
+impl History {
+    /// Creates a new empty [History].
+    ///
+    /// Arguments:
+    ///
+    /// * `max_depth`: The most steps that can be undone.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            max_depth,
+            open: false,
+        }
+    }
+
+    /// Applies a command to the document and records it.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document to change.
+    /// * `command`: The change to make.
+    /// * `merge`: Whether the command may be combined with the previous one into a single
+    ///   undo step, for example while dragging. Call [History::seal] to end the step.
+    pub fn execute(&mut self, document: &mut Document, command: Command, merge: bool) {
+        command.apply(document);
+        self.redo.clear();
+
+        if merge && self.open && self.undo.back_mut().is_some_and(|last| last.merge(&command)) {
+            return;
+        }
+
+        self.undo.push_back(command);
+        self.open = merge;
+        self.truncate();
+    }
+
+    /// Adds a new feature to the document as an undoable step.
+    ///
+    /// Returns the identifier of the new feature.
+    pub fn add_feature(&mut self, document: &mut Document, geometry: Geometry) -> FeatureId {
+        let feature = document.new_feature(geometry);
+        let id = feature.id();
+        let index = document.len();
+        self.execute(document, Command::AddFeature { index, feature }, false);
+        id
+    }
+
+    /// Adds several features to the end of the document as a single undoable step.
+    ///
+    /// Returns the identifiers of the new features.
+    pub fn add_features(&mut self, document: &mut Document, features: impl IntoIterator<Item = (Geometry, Attributes)>) -> Vec<FeatureId> {
+        let mut ids = vec![];
+        let mut commands = vec![];
+        for (geometry, attributes) in features {
+            let mut feature = document.new_feature(geometry);
+            feature.attributes = attributes;
+            ids.push(feature.id());
+            commands.push(Command::AddFeature { index: document.len() + commands.len(), feature });
+        }
+        if !commands.is_empty() {
+            self.execute(document, Command::Batch(commands), false);
+        }
+        ids
+    }
+
+    /// Removes a feature from the document as an undoable step.
+    ///
+    /// Returns `false` if the feature does not exist.
+    pub fn remove_feature(&mut self, document: &mut Document, id: FeatureId) -> bool {
+        let (Some(index), Some(feature)) = (document.index_of(id), document.get(id).cloned()) else {
+            return false;
+        };
+        self.execute(document, Command::RemoveFeature { index, feature }, false);
+        true
+    }
+
+    /// Replaces the style of a feature as an undoable step.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document to change.
+    /// * `id`: The feature to restyle.
+    /// * `style`: The new style, or `None` to draw the feature with the style of its layer.
+    /// * `merge`: Whether the change may be combined with the previous one, for example while
+    ///   dragging a color picker. Call [History::seal] to end the step.
+    pub fn set_style(&mut self, document: &mut Document, id: FeatureId, style: Option<Style>, merge: bool) {
+        let Some(before) = document.get(id).map(|feature| feature.style.clone()) else {
+            return;
+        };
+        if before != style {
+            self.execute(document, Command::SetStyle { id, before, after: style }, merge);
+        }
+    }
+
+    /// Adds an empty layer above the active one and makes it active, as an undoable step.
+    ///
+    /// Returns the identifier of the new layer.
+    pub fn add_layer(&mut self, document: &mut Document, name: String) -> LayerId {
+        let layer = document.new_layer(name);
+        let id = layer.id();
+        let index = document.layer_index(document.active_layer().id()).map_or(0, |index| index + 1);
+        self.execute(document, Command::AddLayer { index, layer }, false);
+        document.set_active_layer(id);
+        id
+    }
+
+    /// Removes a layer together with its features as an undoable step.
+    ///
+    /// Returns `false` if the layer does not exist or is the only one.
+    pub fn remove_layer(&mut self, document: &mut Document, id: LayerId) -> bool {
+        let (Some(index), Some(layer)) = (document.layer_index(id), document.layer(id).cloned()) else {
+            return false;
+        };
+        if document.layers.len() == 1 {
+            return false;
+        }
+        let mut commands = remove_features_of(document, id);
+        commands.push(Command::RemoveLayer { index, layer });
+        self.execute(document, Command::Batch(commands), false);
+        true
+    }
+
+    /// Moves a layer to another position in the drawing order as an undoable step.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document to change.
+    /// * `id`: The layer to move.
+    /// * `to`: The new position of the layer, with `0` being the bottom.
+    ///
+    /// Returns `false` if the layer does not exist or is already at that position.
+    pub fn move_layer(&mut self, document: &mut Document, id: LayerId, to: usize) -> bool {
+        let Some(from) = document.layer_index(id) else {
+            return false;
+        };
+        let to = to.min(document.layers.len() - 1);
+        if from == to {
+            return false;
+        }
+        self.execute(document, Command::MoveLayer { from, to }, false);
+        true
+    }
+
+    /// Replaces the properties of a layer as an undoable step.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document to change.
+    /// * `layer`: The new properties of the layer with the same identifier.
+    /// * `merge`: Whether the change may be combined with the previous one, for example while
+    ///   dragging the opacity slider. Call [History::seal] to end the step.
+    pub fn set_layer(&mut self, document: &mut Document, layer: Layer, merge: bool) {
+        let Some(before) = document.layer(layer.id()).cloned() else {
+            return;
+        };
+        if before != layer {
+            self.execute(document, Command::SetLayer { before, after: layer }, merge);
+        }
+    }
+
+    /// Moves the features of a layer onto the layer below it and removes the emptied layer, as an
+    /// undoable step.
+    ///
+    /// The moved features are drawn on top of the features of the layer below, as before.
+    ///
+    /// Returns `false` if the layer does not exist or is the bottom one.
+    pub fn merge_layer_down(&mut self, document: &mut Document, id: LayerId) -> bool {
+        let Some(index) = document.layer_index(id).filter(|&index| index > 0) else {
+            return false;
+        };
+        let below = document.layers[index - 1].id();
+        let layer = document.layers[index].clone();
+
+        let mut commands = remove_features_of(document, id);
+        let remaining = document.len() - commands.len();
+        let moved = commands.iter().rev().filter_map(|command| match command {
+            Command::RemoveFeature { feature, .. } => Some(Feature { layer: below, ..feature.clone() }),
+            _ => None,
+        });
+        let added: Vec<_> = moved.enumerate().map(|(i, feature)| Command::AddFeature { index: remaining + i, feature }).collect();
+        commands.extend(added);
+        commands.push(Command::RemoveLayer { index, layer });
+        self.execute(document, Command::Batch(commands), false);
+        true
+    }
+
+    /// Adds a copy of a layer and its features right above it, as an undoable step.
+    ///
+    /// Returns the identifier of the copy, or `None` if the layer does not exist.
+    pub fn duplicate_layer(&mut self, document: &mut Document, id: LayerId) -> Option<LayerId> {
+        let index = document.layer_index(id)?;
+        let original = document.layers[index].clone();
+        let mut copy = document.new_layer(format!("{} copy", original.name));
+        (copy.visible, copy.locked, copy.opacity) = (original.visible, original.locked, original.opacity);
+        copy.style = original.style;
+        let copy_id = copy.id();
+
+        let originals: Vec<_> = document.features_in(id).cloned().collect();
+        let mut commands = vec![Command::AddLayer { index: index + 1, layer: copy }];
+        for (i, original) in originals.into_iter().enumerate() {
+            let mut feature = document.new_feature(original.geometry);
+            feature.layer = copy_id;
+            feature.attributes = original.attributes;
+            feature.style = original.style;
+            commands.push(Command::AddFeature { index: document.len() + i, feature });
+        }
+        self.execute(document, Command::Batch(commands), false);
+        Some(copy_id)
+    }
+
+    /// Stops merging commands into the last undo step.
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    /// Reverts the last recorded step.
+    ///
+    /// Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self, document: &mut Document) -> bool {
+        self.open = false;
+        let Some(command) = self.undo.pop_back() else {
+            return false;
+        };
+        command.revert(document);
+        self.redo.push(command);
+        true
+    }
+
+    /// Applies the last undone step again.
+    ///
+    /// Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self, document: &mut Document) -> bool {
+        self.open = false;
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(document);
+        self.undo.push_back(command);
+        true
+    }
+
+    /// Forgets all recorded steps, for example after opening a different document.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+
+    fn truncate(&mut self) {
+        while self.undo.len() > self.max_depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document::testing::DocumentExt, geo::LatLon};
+
+    fn point(lat: f64) -> Geometry {
+        Geometry::Point(LatLon::new(lat, 0.0))
+    }
+
+    /// Returns the latitudes of the points of each layer, from the bottom to the top.
+    fn layer_contents(document: &Document) -> Vec<Vec<f64>> {
+        document
+            .layers()
+            .map(|layer| {
+                document
+                    .features_in(layer.id())
+                    .map(|f| match f.geometry {
+                        Geometry::Point(p) => p.lat,
+                        _ => f64::NAN,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the latitudes of the points of a document in drawing order.
+    fn latitudes(document: &Document) -> Vec<f64> {
+        layer_contents(document).concat()
+    }
+
+    fn move_to(document: &Document, id: FeatureId, lat: f64) -> Command {
+        let before = document.get(id).unwrap().geometry.clone();
+        Command::SetGeometry { id, before, after: point(lat) }
+    }
+
+    #[test]
+    fn undoes_and_redoes_edits() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let first = history.add_feature(&mut document, point(1.0));
+        let second = history.add_feature(&mut document, point(2.0));
+        history.remove_feature(&mut document, first);
+        let command = move_to(&document, second, 5.0);
+        history.execute(&mut document, command, false);
+        assert_eq!(latitudes(&document), vec![5.0]);
+
+        assert!(history.undo(&mut document));
+        assert_eq!(latitudes(&document), vec![2.0]);
+        assert!(history.undo(&mut document));
+        // The removed feature comes back at its old place in the drawing order.
+        assert_eq!(latitudes(&document), vec![1.0, 2.0]);
+        assert!(history.undo(&mut document));
+        assert!(history.undo(&mut document));
+        assert!(document.is_empty());
+        assert!(!history.undo(&mut document));
+
+        while history.redo(&mut document) {}
+        assert_eq!(latitudes(&document), vec![5.0]);
+        assert_eq!(document.get(second).map(|f| f.geometry.clone()), Some(point(5.0)));
+    }
+
+    #[test]
+    fn merges_drags_into_one_step() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let id = history.add_feature(&mut document, point(1.0));
+        for lat in [2.0, 3.0, 4.0] {
+            let command = move_to(&document, id, lat);
+            history.execute(&mut document, command, true);
+        }
+        assert_eq!(latitudes(&document), vec![4.0]);
+
+        history.undo(&mut document);
+        assert_eq!(latitudes(&document), vec![1.0]);
+        history.redo(&mut document);
+        assert_eq!(latitudes(&document), vec![4.0]);
+    }
+
+    #[test]
+    fn sealing_ends_a_step() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let id = history.add_feature(&mut document, point(1.0));
+        let command = move_to(&document, id, 2.0);
+        history.execute(&mut document, command, true);
+        history.seal();
+        let command = move_to(&document, id, 3.0);
+        history.execute(&mut document, command, true);
+
+        history.undo(&mut document);
+        assert_eq!(latitudes(&document), vec![2.0]);
+        history.undo(&mut document);
+        assert_eq!(latitudes(&document), vec![1.0]);
+    }
+
+    #[test]
+    fn new_edits_clear_the_redo_steps() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        history.add_feature(&mut document, point(1.0));
+        history.add_feature(&mut document, point(2.0));
+        history.undo(&mut document);
+
+        history.add_feature(&mut document, point(3.0));
+        assert!(!history.redo(&mut document));
+        assert_eq!(latitudes(&document), vec![1.0, 3.0]);
+    }
+
+    #[test]
+    fn forgets_the_oldest_steps() {
+        let mut document = Document::new();
+        let mut history = History::new(2);
+        for lat in [1.0, 2.0, 3.0] {
+            history.add_feature(&mut document, point(lat));
+        }
+
+        assert!(history.undo(&mut document));
+        assert!(history.undo(&mut document));
+        assert!(!history.undo(&mut document));
+        assert_eq!(latitudes(&document), vec![1.0]);
+    }
+
+    #[test]
+    fn new_features_go_to_the_active_layer() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let bottom = document.active_layer().id();
+        history.add_feature(&mut document, point(1.0));
+        let top = history.add_layer(&mut document, "Rivers".to_string());
+        history.add_feature(&mut document, point(2.0));
+        document.set_active_layer(bottom);
+        history.add_feature(&mut document, point(3.0));
+
+        assert_eq!(document.active_layer().id(), bottom);
+        assert_eq!(document.layer_index(top), Some(1));
+        assert_eq!(layer_contents(&document), vec![vec![1.0, 3.0], vec![2.0]]);
+    }
+
+    #[test]
+    fn merging_down_keeps_the_drawing_order() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let bottom = document.active_layer().id();
+        history.add_feature(&mut document, point(1.0));
+        let top = history.add_layer(&mut document, "Top".to_string());
+        history.add_feature(&mut document, point(2.0));
+        document.set_active_layer(bottom);
+        history.add_feature(&mut document, point(3.0));
+        let before = layer_contents(&document);
+
+        assert!(history.merge_layer_down(&mut document, top));
+        assert_eq!(layer_contents(&document), vec![vec![1.0, 3.0, 2.0]]);
+        assert!(!history.merge_layer_down(&mut document, bottom));
+
+        history.undo(&mut document);
+        assert_eq!(layer_contents(&document), before);
+        history.redo(&mut document);
+        assert_eq!(layer_contents(&document), vec![vec![1.0, 3.0, 2.0]]);
+    }
+
+    #[test]
+    fn duplicating_copies_the_features() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let original = document.active_layer().id();
+        let id = history.add_feature(&mut document, point(1.0));
+        let mut layer = document.active_layer().clone();
+        layer.opacity = 0.5;
+        history.set_layer(&mut document, layer, false);
+
+        let copy = history.duplicate_layer(&mut document, original).unwrap();
+
+        let copied = document.features_in(copy).next().unwrap();
+        assert_ne!(copied.id(), id);
+        assert_eq!(copied.geometry, point(1.0));
+        assert_eq!(document.layer(copy).unwrap().name, "Layer 1 copy");
+        assert_eq!(document.layer(copy).unwrap().opacity, 0.5);
+        history.undo(&mut document);
+        assert_eq!(document.layers().count(), 1);
+        assert_eq!(document.len(), 1);
+    }
+
+    #[test]
+    fn reorders_and_removes_layers() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let bottom = document.active_layer().id();
+        let top = history.add_layer(&mut document, "Top".to_string());
+        history.add_feature(&mut document, point(2.0));
+
+        assert!(history.move_layer(&mut document, top, 0));
+        assert_eq!(document.layer_index(bottom), Some(1));
+        assert!(history.remove_layer(&mut document, top));
+        assert!(document.is_empty());
+        assert!(!history.remove_layer(&mut document, bottom));
+
+        history.undo(&mut document);
+        history.undo(&mut document);
+        assert_eq!(layer_contents(&document), vec![vec![], vec![2.0]]);
+    }
+
+    #[test]
+    fn opacity_drags_are_undone_at_once() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let mut layer = document.active_layer().clone();
+        for opacity in [0.8, 0.6, 0.4] {
+            layer.opacity = opacity;
+            history.set_layer(&mut document, layer.clone(), true);
+        }
+
+        history.undo(&mut document);
+
+        assert_eq!(document.active_layer().opacity, 1.0);
+    }
+
+    #[test]
+    fn locked_layers_are_not_editable() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        history.add_feature(&mut document, point(1.0));
+        history.add_layer(&mut document, "Top".to_string());
+        history.add_feature(&mut document, point(2.0));
+        let mut layer = document.active_layer().clone();
+        layer.locked = true;
+        history.set_layer(&mut document, layer, false);
+
+        let editable: Vec<_> = document.editable_features().map(|f| f.geometry.clone()).collect();
+
+        assert!(!document.active_layer().is_editable());
+        assert_eq!(editable, vec![point(1.0)]);
+    }
+
+    #[test]
+    fn feature_styles_override_the_layer() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let id = history.add_feature(&mut document, point(1.0));
+        let mut style = Style { fill: None, ..Style::default() };
+
+        history.set_style(&mut document, id, Some(style.clone()), true);
+        style.stroke = None;
+        history.set_style(&mut document, id, Some(style.clone()), true);
+        let feature = document.get(id).unwrap();
+        assert_eq!(document.style_of(feature), &style);
+
+        history.undo(&mut document);
+        let feature = document.get(id).unwrap();
+        assert_eq!(feature.style, None);
+        assert_eq!(document.style_of(feature), &document.active_layer().style);
+    }
+}
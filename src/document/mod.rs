@@ -0,0 +1,534 @@
+pub mod history;
+pub mod style;
+#[cfg(test)]
+pub mod testing;
+
+use std::f64::consts::PI;
+
+use glam::{DQuat, DVec3};
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{enclosed_area, flatten_cubic, triangulate, LatLon};
+
+pub use style::{Fill, LineCap, LineJoin, Stroke, StrokeWidth, Style};
+
+/// A stable identifier of a [Feature] within a [Document].
+///
+/// Identifiers are never reused, so they stay valid references even after the feature they
+/// pointed to has been removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FeatureId(u64);
+
+/// A stable identifier of a [Layer] within a [Document], never reused like [FeatureId].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LayerId(u64);
+
+/// A group of features that are shown, locked and stacked together, like the coastlines or the
+/// rivers of a map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    id: LayerId,
+    pub name: String,
+    /// Whether the features are drawn and exported.
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    /// Whether the features are protected from being drawn into or edited.
+    #[serde(default)]
+    pub locked: bool,
+    /// How opaque the features are drawn, from `0` for invisible to `1` for their own colors.
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// How the features are drawn unless they have a style of their own.
+    #[serde(default)]
+    pub style: Style,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+impl Layer {
+    /// The identifier of this layer within its [Document].
+    pub fn id(&self) -> LayerId {
+        self.id
+    }
+
+    /// Whether features can be added to the layer or changed, which needs it to be visible and
+    /// unlocked.
+    pub fn is_editable(&self) -> bool {
+        self.visible && !self.locked
+    }
+}
+
+/// A closed area on the globe.
+///
+/// On a sphere every ring divides the surface into two areas, so the orientation of the rings
+/// decides which one is meant: the polygon lies to the left of every ring, so `exterior` runs
+/// counter-clockwise when seen from outside the globe and each hole runs clockwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Polygon {
+    /// The outer boundary. The ring is implicitly closed, so the last coordinate should not
+    /// repeat the first one.
+    pub exterior: Vec<LatLon>,
+    /// Rings cut out of the area enclosed by `exterior`.
+    #[serde(default)]
+    pub holes: Vec<Vec<LatLon>>,
+}
+
+impl Polygon {
+    /// Creates a new [Polygon] without holes.
+    pub fn new(exterior: Vec<LatLon>) -> Self {
+        Self { exterior, holes: vec![] }
+    }
+
+    /// Orients the rings so that the polygon covers the smaller of the two areas each ring
+    /// divides the globe into.
+    ///
+    /// Use this for rings whose orientation carries no meaning, such as drawn or imported ones.
+    pub fn normalized(mut self) -> Self {
+        if enclosed_area(&to_vectors(&self.exterior)) > 2.0 * PI {
+            self.exterior.reverse();
+        }
+        for hole in &mut self.holes {
+            if enclosed_area(&to_vectors(hole)) < 2.0 * PI {
+                hole.reverse();
+            }
+        }
+        self
+    }
+
+    /// Splits the polygon into triangles on the unit sphere, see [triangulate].
+    ///
+    /// Arguments:
+    ///
+    /// * `max_angle`: The largest angle in radians spanned by any edge of a triangle.
+    pub fn triangulate(&self, max_angle: f64) -> (Vec<DVec3>, Vec<u32>) {
+        let holes: Vec<_> = self.holes.iter().map(|hole| to_vectors(hole)).collect();
+        triangulate(&to_vectors(&self.exterior), &holes, max_angle)
+    }
+}
+
+fn to_vectors(ring: &[LatLon]) -> Vec<DVec3> {
+    ring.iter().map(|p| p.to_unit_vector()).collect()
+}
+
+/// A point of a [BezierPath] together with the handles of the curves meeting at it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PathAnchor {
+    /// The point the path passes through.
+    pub point: LatLon,
+    /// The handle controlling the curve that ends at this anchor.
+    pub in_handle: LatLon,
+    /// The handle controlling the curve that starts at this anchor.
+    pub out_handle: LatLon,
+}
+
+impl PathAnchor {
+    /// Creates a new [PathAnchor] with both handles collapsed onto its point, making the
+    /// adjacent curves straight geodesics.
+    pub fn corner(point: LatLon) -> Self {
+        Self { point, in_handle: point, out_handle: point }
+    }
+}
+
+/// A smooth path made of cubic Bezier curves whose control points lie on the globe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BezierPath {
+    pub anchors: Vec<PathAnchor>,
+    /// Whether the last anchor is connected back to the first one.
+    #[serde(default)]
+    pub closed: bool,
+}
+
+impl BezierPath {
+    /// Iterates over the control points of each cubic curve of the path.
+    pub fn segments(&self) -> impl Iterator<Item = [LatLon; 4]> + '_ {
+        let wrap = if self.closed && self.anchors.len() > 1 { self.anchors.first() } else { None };
+        self.anchors
+            .iter()
+            .zip(self.anchors.iter().skip(1).chain(wrap))
+            .map(|(a, b)| [a.point, a.out_handle, b.in_handle, b.point])
+    }
+
+    /// Approximates the path with a line.
+    ///
+    /// Arguments:
+    ///
+    /// * `max_angle`: The largest angle in radians between two consecutive points of the result.
+    pub fn flatten(&self, max_angle: f64) -> Vec<LatLon> {
+        let mut line: Vec<LatLon> = self.anchors.first().map(|a| a.point).into_iter().collect();
+        for segment in self.segments() {
+            line.extend(flatten_cubic(segment, max_angle).into_iter().skip(1));
+        }
+        line
+    }
+}
+
+/// The shape of a [Feature].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Geometry {
+    Point(LatLon),
+    Polyline(Vec<LatLon>),
+    Polygon(Polygon),
+    Path(BezierPath),
+}
+
+impl Geometry {
+    /// Returns the points that can be moved individually.
+    ///
+    /// These are the corners of lines and polygons, with the exterior ring of a polygon followed
+    /// by its holes, and the anchors of paths.
+    pub fn vertices(&self) -> Vec<LatLon> {
+        match self {
+            Geometry::Point(p) => vec![*p],
+            Geometry::Polyline(line) => line.clone(),
+            Geometry::Polygon(polygon) => std::iter::once(&polygon.exterior)
+                .chain(&polygon.holes)
+                .flatten()
+                .copied()
+                .collect(),
+            Geometry::Path(path) => path.anchors.iter().map(|a| a.point).collect(),
+        }
+    }
+
+    /// Moves one of the points returned by [Geometry::vertices].
+    ///
+    /// The handles of a path anchor are rotated along with it, keeping the shape of the curve.
+    ///
+    /// Arguments:
+    ///
+    /// * `index`: The index of the vertex in [Geometry::vertices].
+    /// * `point`: The new position of the vertex.
+    pub fn set_vertex(&mut self, index: usize, point: LatLon) {
+        match self {
+            Geometry::Point(p) => *p = point,
+            Geometry::Polyline(line) => line[index] = point,
+            Geometry::Polygon(polygon) => {
+                let ring = std::iter::once(&mut polygon.exterior)
+                    .chain(&mut polygon.holes)
+                    .scan(0, |start, ring| {
+                        let ring_start = *start;
+                        *start += ring.len();
+                        Some((ring_start, ring))
+                    })
+                    .find(|(start, ring)| index < start + ring.len());
+                if let Some((start, ring)) = ring {
+                    ring[index - start] = point;
+                }
+            },
+            Geometry::Path(path) => {
+                let anchor = &mut path.anchors[index];
+                let rotation = DQuat::from_rotation_arc(anchor.point.to_unit_vector(), point.to_unit_vector());
+                let rotate = |p: LatLon| LatLon::from_unit_vector(rotation * p.to_unit_vector());
+                anchor.in_handle = rotate(anchor.in_handle);
+                anchor.out_handle = rotate(anchor.out_handle);
+                anchor.point = point;
+            },
+        }
+    }
+}
+
+/// Free-form data attached to a [Feature], such as the properties of an imported GeoJSON feature.
+pub type Attributes = serde_json::Map<String, serde_json::Value>;
+
+/// A single drawn element of a [Document].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feature {
+    id: FeatureId,
+    /// The layer the feature belongs to. Files without layers put every feature on the first one.
+    #[serde(default)]
+    layer: LayerId,
+    pub geometry: Geometry,
+    #[serde(default, skip_serializing_if = "Attributes::is_empty")]
+    pub attributes: Attributes,
+    /// Overrides the style of the layer for this feature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<Style>,
+}
+
+impl Feature {
+    /// The identifier of this feature within its [Document].
+    pub fn id(&self) -> FeatureId {
+        self.id
+    }
+
+    /// The layer this feature belongs to.
+    pub fn layer(&self) -> LayerId {
+        self.layer
+    }
+}
+
+/// The vector data drawn onto the globe.
+///
+/// All coordinates are stored as [LatLon] so that they are independent of the mesh, the camera
+/// and any projection used for display or export.
+///
+/// Every feature belongs to one of the layers, of which there is always at least one. Layers are
+/// drawn from the first to the last, and the features of a layer in the order they were added.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "DocumentData")]
+pub struct Document {
+    features: Vec<Feature>,
+    next_id: u64,
+    /// The layers from the bottom to the top of the drawing order.
+    layers: Vec<Layer>,
+    next_layer_id: u64,
+    /// The layer new features are added to.
+    active_layer: LayerId,
+    /// Incremented on every change so that consumers know when to rebuild derived data.
+    revision: u64,
+}
+
+/// The serialized form of a [Document], which leaves out the bookkeeping that can be rebuilt.
+#[derive(Deserialize)]
+struct DocumentData {
+    #[serde(default)]
+    layers: Vec<Layer>,
+    #[serde(default)]
+    features: Vec<Feature>,
+}
+
+impl Serialize for Document {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct DocumentRef<'a> {
+            layers: &'a [Layer],
+            features: &'a [Feature],
+        }
+
+        DocumentRef { layers: &self.layers, features: &self.features }.serialize(serializer)
+    }
+}
+
+impl From<DocumentData> for Document {
+    fn from(data: DocumentData) -> Self {
+        let mut document = Document::new();
+        let mut layers = data.layers.into_iter();
+        if let Some(first) = layers.next() {
+            document.layers = vec![first];
+        }
+        for layer in layers {
+            if document.layer(layer.id).is_none() {
+                document.insert_layer(document.layers.len(), layer);
+            }
+        }
+        document.next_layer_id = document.layers.iter().map(|layer| layer.id.0 + 1).max().unwrap_or(0);
+        document.active_layer = document.layers[document.layers.len() - 1].id;
+
+        let first_layer = document.layers[0].id;
+        for mut feature in data.features {
+            // Keep the first of any features sharing an identifier, as a command history would
+            // otherwise not be able to tell them apart.
+            if document.get(feature.id).is_none() {
+                if document.layer(feature.layer).is_none() {
+                    feature.layer = first_layer;
+                }
+                document.insert(document.len(), feature);
+            }
+        }
+        document
+    }
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        let layer = Layer {
+            id: LayerId(0),
+            name: "Layer 1".to_string(),
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            style: Style::default(),
+        };
+        Self {
+            features: vec![],
+            next_id: 0,
+            active_layer: layer.id,
+            layers: vec![layer],
+            next_layer_id: 1,
+            revision: 0,
+        }
+    }
+}
+
+
+impl Document {
+    /// Creates a new empty [Document].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a feature on the active layer with a fresh identifier without adding it to the
+    /// document.
+    ///
+    /// This allows the addition to be recorded as a [history::Command] before it happens.
+    pub fn new_feature(&mut self, geometry: Geometry) -> Feature {
+        let id = FeatureId(self.next_id);
+        self.next_id += 1;
+        Feature { id, layer: self.active_layer, geometry, attributes: Attributes::new(), style: None }
+    }
+
+    /// Inserts a feature at a position in the list of features, which is the drawing order within
+    /// its layer.
+    ///
+    /// Arguments:
+    ///
+    /// * `index`: The position of the feature, clamped to the number of features.
+    /// * `feature`: The feature, keeping its identifier and its layer, which has to exist.
+    pub fn insert(&mut self, index: usize, feature: Feature) {
+        self.next_id = self.next_id.max(feature.id.0 + 1);
+        self.features.insert(index.min(self.features.len()), feature);
+        self.revision += 1;
+    }
+
+    /// Removes a feature from the document, returning it if it existed.
+    pub fn remove(&mut self, id: FeatureId) -> Option<Feature> {
+        let index = self.index_of(id)?;
+        self.revision += 1;
+        Some(self.features.remove(index))
+    }
+
+    /// Returns the position of a feature in the list of features.
+    pub fn index_of(&self, id: FeatureId) -> Option<usize> {
+        self.features.iter().position(|f| f.id == id)
+    }
+
+    /// Returns the feature with the given identifier.
+    pub fn get(&self, id: FeatureId) -> Option<&Feature> {
+        self.features.iter().find(|f| f.id == id)
+    }
+
+    /// Returns the feature with the given identifier for modification.
+    ///
+    /// The document is considered changed as soon as this is called.
+    pub fn get_mut(&mut self, id: FeatureId) -> Option<&mut Feature> {
+        let feature = self.features.iter_mut().find(|f| f.id == id)?;
+        self.revision += 1;
+        Some(feature)
+    }
+
+    /// Iterates over all features in the order they were added.
+    pub fn features(&self) -> impl Iterator<Item = &Feature> {
+        self.features.iter()
+    }
+
+    /// The number of features in the document.
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Iterates over the features of a layer in the order they are drawn.
+    pub fn features_in(&self, layer: LayerId) -> impl Iterator<Item = &Feature> {
+        self.features.iter().filter(move |f| f.layer == layer)
+    }
+
+    /// Iterates over the features that can be edited, see [Layer::is_editable].
+    pub fn editable_features(&self) -> impl Iterator<Item = &Feature> {
+        self.features.iter().filter(|f| self.layer(f.layer).is_some_and(Layer::is_editable))
+    }
+
+    /// Returns the style a feature is drawn with, which is its own or that of its layer.
+    pub fn style_of<'a>(&'a self, feature: &'a Feature) -> &'a Style {
+        feature.style.as_ref().unwrap_or_else(|| &self.layer(feature.layer).unwrap_or(&self.layers[0]).style)
+    }
+
+    /// Iterates over the layers from the bottom to the top of the drawing order.
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter()
+    }
+
+    /// Returns the layer with the given identifier.
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    /// Returns the position of a layer in the drawing order, with `0` being the bottom.
+    pub fn layer_index(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.id == id)
+    }
+
+    /// Creates a layer with a fresh identifier without adding it to the document.
+    ///
+    /// Arguments:
+    ///
+    /// * `name`: The name of the layer as shown to the user.
+    pub fn new_layer(&mut self, name: String) -> Layer {
+        let id = LayerId(self.next_layer_id);
+        self.next_layer_id += 1;
+        Layer { id, name, visible: true, locked: false, opacity: 1.0, style: Style::default() }
+    }
+
+    /// Inserts a layer at a position in the drawing order.
+    ///
+    /// Arguments:
+    ///
+    /// * `index`: The position of the layer, clamped to the number of layers.
+    /// * `layer`: The layer, keeping its identifier.
+    pub fn insert_layer(&mut self, index: usize, layer: Layer) {
+        self.next_layer_id = self.next_layer_id.max(layer.id.0 + 1);
+        self.layers.insert(index.min(self.layers.len()), layer);
+        self.revision += 1;
+    }
+
+    /// Removes a layer that contains no features, returning it.
+    ///
+    /// Returns `None` if the layer does not exist, still contains features or is the only one.
+    /// If it was the active layer, the layer below it becomes active.
+    pub fn remove_layer(&mut self, id: LayerId) -> Option<Layer> {
+        let index = self.layer_index(id)?;
+        if self.layers.len() == 1 || self.features.iter().any(|f| f.layer == id) {
+            return None;
+        }
+        let layer = self.layers.remove(index);
+        if self.active_layer == id {
+            self.active_layer = self.layers[index.saturating_sub(1)].id;
+        }
+        self.revision += 1;
+        Some(layer)
+    }
+
+    /// Moves a layer to another position in the drawing order.
+    ///
+    /// Arguments:
+    ///
+    /// * `from`: The current position of the layer.
+    /// * `to`: The new position of the layer, clamped to the last one.
+    pub fn move_layer(&mut self, from: usize, to: usize) {
+        if from >= self.layers.len() {
+            return;
+        }
+        let layer = self.layers.remove(from);
+        self.layers.insert(to.min(self.layers.len()), layer);
+        self.revision += 1;
+    }
+
+    /// Replaces the properties of a layer, identified by the identifier of `layer`.
+    pub fn set_layer(&mut self, layer: Layer) {
+        if let Some(existing) = self.layers.iter_mut().find(|l| l.id == layer.id) {
+            *existing = layer;
+            self.revision += 1;
+        }
+    }
+
+    /// The layer new features are added to.
+    pub fn active_layer(&self) -> &Layer {
+        self.layer(self.active_layer).unwrap_or(&self.layers[0])
+    }
+
+    /// Picks the layer new features are added to, if it exists.
+    pub fn set_active_layer(&mut self, id: LayerId) {
+        if self.layer(id).is_some() {
+            self.active_layer = id;
+        }
+    }
+
+    /// A counter that changes whenever the document is modified.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+/// The color of lines, outlines and point markers unless styled otherwise.
+pub const LINE_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 1.0];
+
+/// The color of filled areas unless styled otherwise.
+pub const FILL_COLOR: [f32; 4] = [0.8, 0.1, 0.1, 0.35];
+
+/// The width of lines in pixels unless styled otherwise.
+pub const LINE_WIDTH: f32 = 1.5;
+
+/// How the features of a [super::Layer] or a single [super::Feature] are drawn.
+///
+/// Colors are linear RGB with the opacity as the fourth component, like the colors the renderer
+/// blends with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    /// How lines, the outlines of polygons and point markers are drawn, or `None` to leave them out.
+    #[serde(default)]
+    pub stroke: Option<Stroke>,
+    /// How polygons are filled, or `None` to leave them hollow.
+    #[serde(default)]
+    pub fill: Option<Fill>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            stroke: Some(Stroke::default()),
+            fill: Some(Fill::default()),
+        }
+    }
+}
+
+/// The outline of a line, polygon or point marker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stroke {
+    pub color: [f32; 4],
+    pub width: StrokeWidth,
+    /// The lengths of alternating dashes and gaps in the unit of `width`, starting with a dash.
+    /// A pattern with an odd number of lengths is repeated once to make it even, like in SVG. An
+    /// empty pattern draws a solid line.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dash: Vec<f32>,
+    #[serde(default)]
+    pub cap: LineCap,
+    #[serde(default)]
+    pub join: LineJoin,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            color: LINE_COLOR,
+            width: StrokeWidth::Pixels(LINE_WIDTH),
+            dash: vec![],
+            cap: LineCap::Round,
+            join: LineJoin::Round,
+        }
+    }
+}
+
+impl Stroke {
+    /// Returns the dash pattern with an even number of lengths, or an empty one if it would not
+    /// draw any dashes.
+    pub fn dash_pattern(&self) -> Vec<f32> {
+        if self.dash.iter().any(|&length| !length.is_finite() || length < 0.0) || self.dash.iter().sum::<f32>() <= 0.0 {
+            return vec![];
+        }
+        let mut pattern = self.dash.clone();
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_within(..);
+        }
+        pattern
+    }
+}
+
+/// The width of a [Stroke].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokeWidth {
+    /// A width on screen that stays the same while zooming.
+    Pixels(f32),
+    /// A width on the ground that grows while zooming in, like the width of a river.
+    Kilometres(f32),
+}
+
+impl StrokeWidth {
+    /// The number in the unit of the width.
+    pub fn value(self) -> f32 {
+        match self {
+            StrokeWidth::Pixels(value) | StrokeWidth::Kilometres(value) => value,
+        }
+    }
+}
+
+/// The shape of the ends of an open line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineCap {
+    /// Ends exactly at the end point.
+    Butt,
+    /// Ends with a half circle around the end point.
+    #[default]
+    Round,
+    /// Ends with a half square around the end point.
+    Square,
+}
+
+impl LineCap {
+    pub const ALL: [LineCap; 3] = [LineCap::Butt, LineCap::Round, LineCap::Square];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineCap::Butt => "Butt",
+            LineCap::Round => "Round",
+            LineCap::Square => "Square",
+        }
+    }
+}
+
+/// The shape of the corners of a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, or bevels corners that would get sharper than
+    /// [MITER_LIMIT].
+    Miter,
+    /// Rounds the corner with a circle around it.
+    #[default]
+    Round,
+    /// Cuts the corner off straight.
+    Bevel,
+}
+
+impl LineJoin {
+    pub const ALL: [LineJoin; 3] = [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel];
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineJoin::Miter => "Miter",
+            LineJoin::Round => "Round",
+            LineJoin::Bevel => "Bevel",
+        }
+    }
+}
+
+/// The longest a miter may get relative to the width of the line before it is beveled, as in SVG.
+pub const MITER_LIMIT: f32 = 4.0;
+
+/// The fill of a polygon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+    pub color: [f32; 4],
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Self { color: FILL_COLOR }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_odd_dash_patterns() {
+        let stroke = |dash: Vec<f32>| Stroke { dash, ..Stroke::default() };
+
+        assert_eq!(stroke(vec![4.0, 2.0]).dash_pattern(), vec![4.0, 2.0]);
+        assert_eq!(stroke(vec![3.0]).dash_pattern(), vec![3.0, 3.0]);
+        assert_eq!(stroke(vec![0.0, 0.0]).dash_pattern(), Vec::<f32>::new());
+        assert_eq!(stroke(vec![2.0, -1.0]).dash_pattern(), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn reads_partial_strokes() {
+        let style: Style = serde_json::from_str(r#"{"stroke": {"color": [0, 0, 1, 1], "width": {"kilometres": 5}}}"#).unwrap();
+
+        let stroke = style.stroke.unwrap();
+        assert_eq!(stroke.width, StrokeWidth::Kilometres(5.0));
+        assert_eq!((stroke.cap, stroke.join), (LineCap::Round, LineJoin::Round));
+        assert!(stroke.dash.is_empty());
+        assert_eq!(style.fill, None);
+    }
+}
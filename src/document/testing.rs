@@ -0,0 +1,30 @@
+use super::{Document, FeatureId, Geometry};
+
+/// Shortcuts for setting up documents in tests.
+///
+/// Edits made by the tools go through [super::history::History] instead, so that they can be
+/// undone.
+pub trait DocumentExt {
+    /// Adds a new feature to the active layer and returns its identifier.
+    ///
+    /// Arguments:
+    ///
+    /// * `geometry`: The shape of the new feature.
+    fn add(&mut self, geometry: Geometry) -> FeatureId;
+
+    /// Whether the document contains no features.
+    fn is_empty(&self) -> bool;
+}
+
+impl DocumentExt for Document {
+    fn add(&mut self, geometry: Geometry) -> FeatureId {
+        let feature = self.new_feature(geometry);
+        let id = feature.id();
+        self.insert(self.len(), feature);
+        id
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,606 @@
+use std::{fmt, fs, io, path::Path};
+
+use glam::{DVec2, DVec3};
+use geojson::{FeatureCollection, JsonObject, JsonValue, Position, Value};
+
+use crate::{document::{Attributes, Document, Geometry, Polygon}, geo::{densify, split_line, split_polygon, LatLon}};
+
+/// The largest angle between two points of a flattened Bezier path when exporting without
+/// densifying.
+const PATH_FLATTEN_ANGLE: f64 = 1.0 * std::f64::consts::PI / 180.0;
+
+/// The features read from a GeoJSON file, see [import].
+#[derive(Debug, Default)]
+pub struct Import {
+    /// The geometries that were read together with the properties of their feature.
+    pub features: Vec<(Geometry, Attributes)>,
+    /// Everything that had to be skipped or repaired.
+    pub issues: Vec<ImportIssue>,
+}
+
+/// A problem with a single feature of a GeoJSON file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportIssue {
+    /// The position of the feature in the file.
+    pub feature: usize,
+    pub problem: Problem,
+}
+
+/// What was wrong with a feature and how the import dealt with it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The feature is not valid GeoJSON and was skipped.
+    Malformed(String),
+    /// The feature has no geometry and was skipped.
+    MissingGeometry,
+    /// A position is not a finite longitude and latitude. The line or polygon containing it was
+    /// skipped, or only the ring if it was a hole.
+    InvalidPosition(Position),
+    /// A line or ring has fewer distinct positions than it needs and was skipped.
+    TooFewPositions { kind: &'static str, count: usize },
+    /// The last position of a ring does not repeat the first one. The ring was closed.
+    UnclosedRing,
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "feature {}: ", self.feature)?;
+        match &self.problem {
+            Problem::Malformed(err) => write!(f, "malformed feature skipped: {err}"),
+            Problem::MissingGeometry => write!(f, "feature without geometry skipped"),
+            Problem::InvalidPosition(position) => write!(f, "invalid position {position:?} skipped"),
+            Problem::TooFewPositions { kind, count } => write!(f, "{kind} with only {count} distinct positions skipped"),
+            Problem::UnclosedRing => write!(f, "unclosed ring was closed"),
+        }
+    }
+}
+
+/// An error that prevented a GeoJSON file from being read or written at all.
+#[derive(Debug)]
+pub enum GeoJsonError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    GeoJson(Box<geojson::Error>),
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoJsonError::Io(err) => write!(f, "{err}"),
+            GeoJsonError::Json(err) => write!(f, "invalid JSON: {err}"),
+            GeoJsonError::GeoJson(err) => write!(f, "invalid GeoJSON: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+impl From<io::Error> for GeoJsonError {
+    fn from(err: io::Error) -> Self {
+        GeoJsonError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for GeoJsonError {
+    fn from(err: serde_json::Error) -> Self {
+        GeoJsonError::Json(err)
+    }
+}
+
+impl From<geojson::Error> for GeoJsonError {
+    fn from(err: geojson::Error) -> Self {
+        GeoJsonError::GeoJson(Box::new(err))
+    }
+}
+
+/// Reads the features of a GeoJSON file.
+///
+/// Accepts a FeatureCollection, a single Feature or a bare geometry. Multi-geometries and
+/// geometry collections are split into one feature per part, each with a copy of the
+/// `properties`. Invalid features and parts are skipped and reported in [Import::issues], only a
+/// file that is not GeoJSON at all fails as a whole.
+///
+/// Arguments:
+///
+/// * `json`: The contents of the file.
+pub fn import(json: &str) -> Result<Import, GeoJsonError> {
+    let mut value: JsonValue = serde_json::from_str(json)?;
+    let mut import = Import::default();
+
+    let kind = value.get("type").and_then(JsonValue::as_str).map(str::to_string);
+    match kind.as_deref() {
+        Some("FeatureCollection") => {
+            let JsonValue::Array(features) = value["features"].take() else {
+                return Err(geojson::Error::ExpectedArrayValue("features".to_string()).into());
+            };
+            for (index, feature) in features.into_iter().enumerate() {
+                import.read_feature(index, feature);
+            }
+        },
+        Some("Feature") => import.read_feature(0, value),
+        _ => {
+            let geometry = geojson::Geometry::from_json_value(value)?;
+            import.read_geometry(0, geometry.value, &Attributes::new());
+        },
+    }
+
+    Ok(import)
+}
+
+/// Reads the features of a GeoJSON file, see [import].
+pub fn import_file(path: &Path) -> Result<Import, GeoJsonError> {
+    import(&fs::read_to_string(path)?)
+}
+
+impl Import {
+    fn read_feature(&mut self, index: usize, value: JsonValue) {
+        let feature = match geojson::Feature::from_json_value(value) {
+            Ok(feature) => feature,
+            Err(err) => return self.report(index, Problem::Malformed(err.to_string())),
+        };
+        let Some(geometry) = feature.geometry else {
+            return self.report(index, Problem::MissingGeometry);
+        };
+        let attributes = feature.properties.unwrap_or_else(JsonObject::new);
+        self.read_geometry(index, geometry.value, &attributes);
+    }
+
+    fn read_geometry(&mut self, index: usize, value: Value, attributes: &Attributes) {
+        match value {
+            Value::Point(position) => self.read_point(index, &position, attributes),
+            Value::MultiPoint(positions) => {
+                for position in &positions {
+                    self.read_point(index, position, attributes);
+                }
+            },
+            Value::LineString(line) => self.read_line(index, &line, attributes),
+            Value::MultiLineString(lines) => {
+                for line in &lines {
+                    self.read_line(index, line, attributes);
+                }
+            },
+            Value::Polygon(rings) => self.read_polygon(index, &rings, attributes),
+            Value::MultiPolygon(polygons) => {
+                for rings in &polygons {
+                    self.read_polygon(index, rings, attributes);
+                }
+            },
+            Value::GeometryCollection(geometries) => {
+                for geometry in geometries {
+                    self.read_geometry(index, geometry.value, attributes);
+                }
+            },
+        }
+    }
+
+    fn read_point(&mut self, index: usize, position: &Position, attributes: &Attributes) {
+        match to_lat_lon(position) {
+            Ok(point) => self.features.push((Geometry::Point(point), attributes.clone())),
+            Err(problem) => self.report(index, problem),
+        }
+    }
+
+    fn read_line(&mut self, index: usize, line: &[Position], attributes: &Attributes) {
+        let line = to_points(line).and_then(|line| at_least(line, 2, "line"));
+        match line {
+            Ok(line) => self.features.push((Geometry::Polyline(line), attributes.clone())),
+            Err(problem) => self.report(index, problem),
+        }
+    }
+
+    fn read_polygon(&mut self, index: usize, rings: &[Vec<Position>], attributes: &Attributes) {
+        let Some((exterior, holes)) = rings.split_first() else {
+            return self.report(index, Problem::TooFewPositions { kind: "polygon", count: 0 });
+        };
+        let exterior = match self.read_ring(index, exterior) {
+            Ok(exterior) => exterior,
+            Err(problem) => return self.report(index, problem),
+        };
+
+        let mut polygon = Polygon::new(exterior);
+        for hole in holes {
+            match self.read_ring(index, hole) {
+                Ok(hole) => polygon.holes.push(hole),
+                Err(problem) => self.report(index, problem),
+            }
+        }
+        // Plenty of data ignores the winding order RFC 7946 asks for, so do not rely on it.
+        self.features.push((Geometry::Polygon(polygon.normalized()), attributes.clone()));
+    }
+
+    fn read_ring(&mut self, index: usize, ring: &[Position]) -> Result<Vec<LatLon>, Problem> {
+        let mut ring = to_points(ring)?;
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        } else if !ring.is_empty() {
+            self.report(index, Problem::UnclosedRing);
+        }
+        at_least(ring, 3, "ring")
+    }
+
+    fn report(&mut self, feature: usize, problem: Problem) {
+        self.issues.push(ImportIssue { feature, problem });
+    }
+}
+
+/// Converts a GeoJSON position, which lists the longitude first.
+fn to_lat_lon(position: &Position) -> Result<LatLon, Problem> {
+    match position[..] {
+        [lon, lat, ..] if (-180.0..=180.0).contains(&lon) && (-90.0..=90.0).contains(&lat) => Ok(LatLon::new(lat, lon)),
+        _ => Err(Problem::InvalidPosition(position.clone())),
+    }
+}
+
+/// Converts a list of positions, dropping positions that repeat the previous one.
+fn to_points(positions: &[Position]) -> Result<Vec<LatLon>, Problem> {
+    let mut points: Vec<LatLon> = vec![];
+    for position in positions {
+        let point = to_lat_lon(position)?;
+        if points.last() != Some(&point) {
+            points.push(point);
+        }
+    }
+    Ok(points)
+}
+
+fn at_least(points: Vec<LatLon>, count: usize, kind: &'static str) -> Result<Vec<LatLon>, Problem> {
+    if points.len() < count {
+        return Err(Problem::TooFewPositions { kind, count: points.len() });
+    }
+    Ok(points)
+}
+
+/// How a [Document] is written as GeoJSON, see [export].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// The largest angle in radians an edge may span.
+    ///
+    /// Sitelen connects points along great circles, while map tools draw straight lines between
+    /// longitudes and latitudes. Densifying inserts points along the great circles so that both
+    /// show the same curves. `None` keeps only the original points.
+    pub densify: Option<f64>,
+}
+
+/// Converts a document to a GeoJSON FeatureCollection following RFC 7946.
+///
+/// Lines and polygons crossing the antimeridian are cut in two, polygons around a pole are
+/// closed along its latitude, exterior rings run counter-clockwise and holes clockwise. Bezier
+/// paths are flattened into lines. The attributes of each feature become its `properties`.
+///
+/// Features are written in drawing order, from the bottom layer to the top one. Hidden layers
+/// are left out.
+///
+/// Arguments:
+///
+/// * `document`: The document to export.
+/// * `options`: How the geometry is written.
+pub fn export(document: &Document, options: ExportOptions) -> FeatureCollection {
+    let features = document
+        .layers()
+        .filter(|layer| layer.visible)
+        .flat_map(|layer| document.features_in(layer.id()))
+        .filter_map(|feature| {
+            let geometry = export_geometry(&feature.geometry, options)?;
+            Some(geojson::Feature {
+                bbox: None,
+                geometry: Some(geojson::Geometry::new(geometry)),
+                id: None,
+                properties: Some(feature.attributes.clone()),
+                foreign_members: None,
+            })
+        })
+        .collect();
+
+    FeatureCollection { bbox: None, features, foreign_members: None }
+}
+
+/// Writes a document to a GeoJSON file, see [export].
+pub fn export_file(document: &Document, options: ExportOptions, path: &Path) -> Result<(), GeoJsonError> {
+    fs::write(path, serde_json::to_string(&export(document, options))?)?;
+    Ok(())
+}
+
+fn export_geometry(geometry: &Geometry, options: ExportOptions) -> Option<Value> {
+    match geometry {
+        Geometry::Point(p) => Some(Value::Point(vec![p.lon, p.lat])),
+        Geometry::Polyline(line) => export_line(line, options),
+        Geometry::Path(path) => export_line(&path.flatten(options.densify.unwrap_or(PATH_FLATTEN_ANGLE)), options),
+        Geometry::Polygon(polygon) => {
+            let exterior = export_ring(&polygon.exterior, options);
+            let holes: Vec<_> = polygon.holes.iter().map(|hole| export_ring(hole, options)).collect();
+            let mut polygons: Vec<Vec<Vec<Position>>> = split_polygon(&exterior, &holes)
+                .into_iter()
+                .map(|rings| rings.into_iter().map(|ring| to_positions(ring.iter().chain(ring.first()))).collect())
+                .collect();
+            match polygons.len() {
+                0 => None,
+                1 => polygons.pop().map(Value::Polygon),
+                _ => Some(Value::MultiPolygon(polygons)),
+            }
+        },
+    }
+}
+
+fn export_line(line: &[LatLon], options: ExportOptions) -> Option<Value> {
+    let mut points: Vec<DVec3> = line.iter().map(|p| p.to_unit_vector()).collect();
+    if let Some(max_angle) = options.densify {
+        points = densify(&points, max_angle);
+    }
+    let mut lines: Vec<Vec<Position>> = split_line(&points).iter().map(to_positions).collect();
+    match lines.len() {
+        0 => None,
+        1 => lines.pop().map(Value::LineString),
+        _ => Some(Value::MultiLineString(lines)),
+    }
+}
+
+/// Converts a ring to unit vectors, densifying it including the edge that closes it.
+fn export_ring(ring: &[LatLon], options: ExportOptions) -> Vec<DVec3> {
+    let mut points: Vec<DVec3> = ring.iter().map(|p| p.to_unit_vector()).collect();
+    if let Some(max_angle) = options.densify && !points.is_empty() {
+        points.push(points[0]);
+        points = densify(&points, max_angle);
+        points.pop();
+    }
+    points
+}
+
+fn to_positions<'a>(points: impl IntoIterator<Item = &'a DVec2>) -> Vec<Position> {
+    points.into_iter().map(|p| vec![p.x, p.y]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{history::History, testing::DocumentExt};
+
+    #[test]
+    fn splits_multi_geometries_and_keeps_properties() {
+        let json = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": { "name": "islands", "population": 12 },
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": [
+                            [[[0, 0], [10, 0], [5, 10], [0, 0]]],
+                            [[[20, 0], [30, 0], [25, 10], [20, 0]]]
+                        ]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": null,
+                    "geometry": { "type": "MultiPoint", "coordinates": [[13.4, 52.5], [2.35, 48.86]] }
+                }
+            ]
+        }"#;
+        let import = import(json).unwrap();
+
+        assert!(import.issues.is_empty());
+        assert_eq!(import.features.len(), 4);
+        assert!(matches!(import.features[0].0, Geometry::Polygon(_)));
+        assert_eq!(import.features[1].1["name"], "islands");
+        assert_eq!(import.features[1].1["population"], 12);
+        assert_eq!(import.features[2].0, Geometry::Point(LatLon::new(52.5, 13.4)));
+        assert!(import.features[3].1.is_empty());
+    }
+
+    #[test]
+    fn polygons_cover_the_smaller_area() {
+        // A clockwise ring, which RFC 7946 would read as everything but the triangle.
+        let json = r#"{ "type": "Polygon", "coordinates": [[[0, 0], [5, 10], [10, 0], [0, 0]]] }"#;
+        let import = import(json).unwrap();
+
+        let Geometry::Polygon(polygon) = &import.features[0].0 else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(polygon.exterior, vec![LatLon::new(0.0, 10.0), LatLon::new(10.0, 5.0), LatLon::new(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn reports_invalid_geometry_and_imports_the_rest() {
+        let json = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                { "type": "Feature", "properties": {}, "geometry": { "type": "LineString", "coordinates": [[0, 0], [0, 0]] } },
+                { "type": "Feature", "properties": {}, "geometry": { "type": "Point", "coordinates": [0, 91] } },
+                { "type": "Feature", "properties": {}, "geometry": null },
+                { "type": "Feature", "properties": {}, "geometry": { "type": "Circle", "coordinates": [0, 0] } },
+                {
+                    "type": "Feature",
+                    "properties": {},
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [
+                            [[0, 0], [10, 0], [10, 10], [0, 10]],
+                            [[2, 2], [3, 3], [2, 2]]
+                        ]
+                    }
+                },
+                { "type": "Feature", "properties": {}, "geometry": { "type": "LineString", "coordinates": [[0, 0], [1, 1]] } }
+            ]
+        }"#;
+        let import = import(json).unwrap();
+
+        let problems: Vec<_> = import.issues.iter().map(|issue| (issue.feature, issue.problem.clone())).collect();
+        assert!(matches!(problems[0], (0, Problem::TooFewPositions { kind: "line", count: 1 })));
+        assert!(matches!(problems[1], (1, Problem::InvalidPosition(_))));
+        assert!(matches!(problems[2], (2, Problem::MissingGeometry)));
+        assert!(matches!(problems[3], (3, Problem::Malformed(_))));
+        assert!(matches!(problems[4], (4, Problem::UnclosedRing)));
+        assert!(matches!(problems[5], (4, Problem::TooFewPositions { kind: "ring", count: 2 })));
+        assert_eq!(problems.len(), 6);
+
+        assert_eq!(import.features.len(), 2);
+        let Geometry::Polygon(polygon) = &import.features[0].0 else {
+            panic!("expected a polygon");
+        };
+        assert_eq!(polygon.exterior.len(), 4);
+        assert!(polygon.holes.is_empty());
+    }
+
+    /// The planar area of a closed GeoJSON ring, positive if it runs counter-clockwise.
+    fn signed_area(ring: &[Position]) -> f64 {
+        ring.windows(2).map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1]).sum::<f64>() / 2.0
+    }
+
+    fn export_one(geometry: Geometry, options: ExportOptions) -> Value {
+        let mut document = Document::new();
+        document.add(geometry);
+        let mut collection = export(&document, options);
+        collection.features.remove(0).geometry.unwrap().value
+    }
+
+    fn lat_lon_ring(coords: &[(f64, f64)]) -> Vec<LatLon> {
+        coords.iter().map(|&(lat, lon)| LatLon::new(lat, lon)).collect()
+    }
+
+    #[test]
+    fn splits_lines_at_the_antimeridian() {
+        let line = Geometry::Polyline(lat_lon_ring(&[(10.0, 170.0), (10.0, -170.0)]));
+        let Value::MultiLineString(parts) = export_one(line, ExportOptions::default()) else {
+            panic!("expected a multi line");
+        };
+
+        assert_eq!(parts.len(), 2);
+        assert!((parts[0][0][0] - 170.0).abs() < 1e-9 && (parts[0][0][1] - 10.0).abs() < 1e-9);
+        assert_eq!(parts[0][1][0], 180.0);
+        assert_eq!(parts[1][0][0], -180.0);
+        assert_eq!(parts[0][1][1], parts[1][0][1]);
+        // The great circle bends towards the pole between the two points.
+        assert!(parts[0][1][1] > 10.0);
+        assert!((parts[1][1][0] + 170.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn splits_polygons_at_the_antimeridian() {
+        let square = Polygon::new(lat_lon_ring(&[(-10.0, 170.0), (-10.0, -170.0), (10.0, -170.0), (10.0, 170.0)]));
+        let Value::MultiPolygon(polygons) = export_one(Geometry::Polygon(square), ExportOptions::default()) else {
+            panic!("expected a multi polygon");
+        };
+
+        assert_eq!(polygons.len(), 2);
+        for polygon in &polygons {
+            assert_eq!(polygon.len(), 1);
+            assert_eq!(polygon[0].first(), polygon[0].last());
+            assert!(signed_area(&polygon[0]) > 0.0);
+            assert!(polygon[0].iter().all(|p| (-180.0..=180.0).contains(&p[0])));
+        }
+        let total: f64 = polygons.iter().map(|polygon| signed_area(&polygon[0])).sum();
+        assert!((total - 400.0).abs() < 5.0, "{total}");
+    }
+
+    #[test]
+    fn splits_holes_crossing_the_antimeridian() {
+        let mut square = Polygon::new(lat_lon_ring(&[(-10.0, 170.0), (-10.0, -170.0), (10.0, -170.0), (10.0, 170.0)]));
+        square.holes.push(lat_lon_ring(&[(-5.0, 175.0), (5.0, 175.0), (5.0, -175.0), (-5.0, -175.0)]));
+        let Value::MultiPolygon(polygons) = export_one(Geometry::Polygon(square), ExportOptions::default()) else {
+            panic!("expected a multi polygon");
+        };
+
+        // The hole opens up both halves into rings shaped like a "C".
+        assert_eq!(polygons.len(), 2);
+        assert!(polygons.iter().all(|polygon| polygon.len() == 1));
+        let total: f64 = polygons.iter().map(|polygon| signed_area(&polygon[0])).sum();
+        assert!((total - 300.0).abs() < 5.0, "{total}");
+    }
+
+    #[test]
+    fn closes_polar_caps_along_the_pole() {
+        let ring: Vec<_> = (0..8).map(|i| LatLon::new(80.0, -180.0 + 45.0 * i as f64)).collect();
+        let Value::Polygon(rings) = export_one(Geometry::Polygon(Polygon::new(ring)), ExportOptions::default()) else {
+            panic!("expected a polygon");
+        };
+
+        assert_eq!(rings.len(), 1);
+        assert!(signed_area(&rings[0]) > 0.0);
+        assert!(rings[0].iter().any(|p| p[1] == 90.0));
+        assert!(rings[0].iter().all(|p| (-180.0..=180.0).contains(&p[0])));
+    }
+
+    #[test]
+    fn uses_right_hand_winding() {
+        let mut polygon = Polygon::new(lat_lon_ring(&[(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]));
+        polygon.holes.push(lat_lon_ring(&[(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)]));
+        let Value::Polygon(rings) = export_one(Geometry::Polygon(polygon.normalized()), ExportOptions::default()) else {
+            panic!("expected a polygon");
+        };
+
+        assert_eq!(rings.len(), 2);
+        assert!(signed_area(&rings[0]) > 0.0);
+        assert!(signed_area(&rings[1]) < 0.0);
+    }
+
+    #[test]
+    fn polygons_covering_both_poles_become_holes_in_the_map() {
+        // Everything but a small triangle.
+        let polygon = Polygon::new(lat_lon_ring(&[(0.0, 0.0), (10.0, 5.0), (0.0, 10.0)]));
+        let Value::Polygon(rings) = export_one(Geometry::Polygon(polygon), ExportOptions::default()) else {
+            panic!("expected a polygon");
+        };
+
+        assert_eq!(rings.len(), 2);
+        assert!((signed_area(&rings[0]) - 360.0 * 180.0).abs() < 1e-6);
+        assert!(signed_area(&rings[1]) < 0.0);
+    }
+
+    #[test]
+    fn densifies_along_great_circles() {
+        let line = Geometry::Polyline(lat_lon_ring(&[(45.0, -45.0), (45.0, 45.0)]));
+        let options = ExportOptions { densify: Some(1f64.to_radians()) };
+        let Value::LineString(positions) = export_one(line, options) else {
+            panic!("expected a line");
+        };
+
+        assert!(positions.len() > 60);
+        let middle = &positions[positions.len() / 2];
+        assert!(middle[1] > 50.0);
+    }
+
+    #[test]
+    fn export_keeps_properties() {
+        let mut document = Document::new();
+        let id = document.add(Geometry::Point(LatLon::new(52.5, 13.4)));
+        document.get_mut(id).unwrap().attributes.insert("name".to_string(), "Berlin".into());
+
+        let json = serde_json::to_string(&export(&document, ExportOptions::default())).unwrap();
+        let import = import(&json).unwrap();
+
+        assert_eq!(import.features[0].0, Geometry::Point(LatLon::new(52.5, 13.4)));
+        assert_eq!(import.features[0].1["name"], "Berlin");
+    }
+
+    #[test]
+    fn exports_visible_layers_in_drawing_order() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let bottom = document.active_layer().id();
+        let hidden = history.add_layer(&mut document, "Hidden".to_string());
+        history.add_feature(&mut document, Geometry::Point(LatLon::new(30.0, 30.0)));
+        history.add_layer(&mut document, "Top".to_string());
+        history.add_feature(&mut document, Geometry::Point(LatLon::new(20.0, 20.0)));
+        // Added last, but drawn below the others.
+        document.set_active_layer(bottom);
+        history.add_feature(&mut document, Geometry::Point(LatLon::new(10.0, 10.0)));
+        let mut layer = document.layer(hidden).unwrap().clone();
+        layer.visible = false;
+        history.set_layer(&mut document, layer, false);
+
+        let points: Vec<_> = export(&document, ExportOptions::default())
+            .features
+            .into_iter()
+            .map(|feature| feature.geometry.unwrap().value)
+            .collect();
+
+        assert_eq!(points, vec![Value::Point(vec![10.0, 10.0]), Value::Point(vec![20.0, 20.0])]);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_geojson() {
+        assert!(import("[1, 2, 3]").is_err());
+        assert!(import(r#"{ "type": "FeatureCollection" }"#).is_err());
+    }
+}
@@ -0,0 +1,655 @@
+use std::{f64::consts::PI, fmt::Write, fs, io, path::Path};
+
+use glam::{DQuat, DVec2};
+
+use crate::{document::{style::MITER_LIMIT, Document, Geometry, LineCap, LineJoin, Stroke, StrokeWidth, Style}, geo::{densify, split_line, split_polygon, LatLon, EARTH_RADIUS_KM}, graticule::{self, Extent, Graticule}, projection::{Projection, ProjectionKind, WEB_MERCATOR_MAX_LAT}};
+
+/// The longest great-circle arc written as a single straight segment.
+const SEGMENT_ANGLE: f64 = 0.5 * PI / 180.0;
+
+/// The longest edge in degrees along the border of the map.
+///
+/// Edges added while clipping follow the border of the map instead of great circles, so they
+/// are subdivided separately.
+const BORDER_STEP: f64 = 0.5;
+
+/// How far in degrees the clip region stays inside the domain of a projection, so that points
+/// on its border are not rounded to the outside or to the opposite side of the map.
+const CLIP_INSET: f64 = 1e-7;
+
+/// The radius of the circles marking point features in pixels.
+const MARKER_RADIUS: f64 = 3.0;
+
+/// The size of the labels of the graticule in pixels.
+const LABEL_SIZE: f64 = 10.0;
+
+/// The color of the labels of the graticule.
+const LABEL_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+
+/// Settings for [export].
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// The projection of the map.
+    pub projection: ProjectionKind,
+    /// The coordinate in the centre of the projection, see [ProjectionKind::create].
+    pub center: LatLon,
+    /// The part of the map that is exported, as its minimum and maximum corner in map
+    /// coordinates, or `None` for the whole map.
+    pub extent: Option<(DVec2, DVec2)>,
+    /// The width of the image in pixels. The height follows from the extent.
+    pub width: f64,
+    /// The latitude and longitude grid drawn below the layers, if it is visible.
+    pub graticule: Graticule,
+}
+
+/// Converts a document into an SVG image of a map.
+///
+/// Lines and polygons are cut where they cross the border of the map, such as the antimeridian
+/// of a world map or the horizon of an orthographic view, and clipped to the parts the
+/// projection can show. Each layer of the document is written into a group that editors like
+/// Inkscape treat as a layer, keeping its name, visibility and opacity, and the features are drawn
+/// with their styles. Widths and dashes given in kilometres are scaled like the map. A visible
+/// graticule is written into a layer of its own below them.
+///
+/// Arguments:
+///
+/// * `document`: The document to export.
+/// * `options`: The projection and extent of the map.
+pub fn export(document: &Document, options: SvgOptions) -> String {
+    let frame = MapFrame::new(options.projection, options.center);
+    let (min, max) = options.extent.unwrap_or_else(|| frame.projection.bounds());
+    let scale = options.width / (max.x - min.x);
+    let size = (max - min) * scale;
+    let to_pixels = |q: DVec2| DVec2::new(q.x - min.x, max.y - q.y) * scale;
+    // Map coordinates are about as large as angles on the unit sphere.
+    let pixels_per_km = scale / EARTH_RADIUS_KM;
+
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{w:.2}" height="{h:.2}" viewBox="0 0 {w:.2} {h:.2}">"#,
+        w = size.x,
+        h = size.y,
+    );
+    let _ = writeln!(svg, r#"  <defs><clipPath id="extent"><rect width="{:.2}" height="{:.2}"/></clipPath></defs>"#, size.x, size.y);
+
+    if options.graticule.visible {
+        write_graticule(&mut svg, &options, &frame, (min, max), &to_pixels, pixels_per_km);
+    }
+
+    let mut count = 0;
+    for (index, layer) in document.layers().enumerate() {
+        let display = if layer.visible { "" } else { r#" style="display:none""# };
+        let _ = writeln!(
+            svg,
+            r#"  <g id="layer-{}" inkscape:groupmode="layer" inkscape:label="{}" opacity="{}" clip-path="url(#extent)"{display}>"#,
+            index + 1,
+            escape(&layer.name),
+            layer.opacity,
+        );
+        for feature in document.features_in(layer.id()) {
+            count += 1;
+            let style = document.style_of(feature);
+            write_feature(&mut svg, &format!("feature-{count}"), &feature.geometry, style, &frame, &to_pixels, pixels_per_km);
+        }
+        let _ = writeln!(svg, "  </g>");
+    }
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// Writes the parallels and meridians of a graticule, and their labels if it has any, into a
+/// layer group.
+///
+/// The spacing adapts to the height of the exported part of the map like it does to the view.
+fn write_graticule(
+    svg: &mut String,
+    options: &SvgOptions,
+    frame: &MapFrame,
+    (min, max): (DVec2, DVec2),
+    to_pixels: &impl Fn(DVec2) -> DVec2,
+    pixels_per_km: f64,
+) {
+    // Map coordinates are about as large as angles on the unit sphere.
+    let view_angle = (max.y - min.y).to_degrees();
+    let spacing = options.graticule.spacing_for(view_angle);
+    let (center, extent) = match options.extent {
+        Some(_) => match frame.projection.inverse((min + max) / 2.0) {
+            Some(center) => (center, Extent::around(center, view_angle, spacing)),
+            None => (options.center, Extent::WORLD),
+        },
+        None => (options.center, Extent::WORLD),
+    };
+
+    let _ = writeln!(
+        svg,
+        r#"  <g id="graticule" inkscape:groupmode="layer" inkscape:label="Graticule" clip-path="url(#extent)">"#,
+    );
+    for (index, line) in graticule::lines(spacing, &extent).into_iter().enumerate() {
+        let mut points = line.points;
+        if line.closed {
+            points.extend(points.first().copied());
+        }
+        let style = stroke_attributes(Some(&line.kind.stroke()), pixels_per_km);
+        write_line(svg, &format!("graticule-{}", index + 1), &frame.line(&points), to_pixels, &style);
+    }
+    if options.graticule.labels {
+        let (color, opacity) = svg_color(LABEL_COLOR);
+        for label in graticule::labels(spacing, &extent, center) {
+            let Some(q) = frame.point(label.position) else {
+                continue;
+            };
+            let q = to_pixels(q);
+            let _ = writeln!(
+                svg,
+                r#"    <text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="{LABEL_SIZE}" text-anchor="middle" dominant-baseline="middle" fill="{color}" fill-opacity="{opacity}">{}</text>"#,
+                q.x,
+                q.y,
+                escape(&label.text),
+            );
+        }
+    }
+    let _ = writeln!(svg, "  </g>");
+}
+
+/// Writes a feature as an SVG element, leaving it out if no part of it can be shown on the map.
+fn write_feature(
+    svg: &mut String,
+    id: &str,
+    geometry: &Geometry,
+    style: &Style,
+    frame: &MapFrame,
+    to_pixels: &impl Fn(DVec2) -> DVec2,
+    pixels_per_km: f64,
+) {
+    let line_style = stroke_attributes(style.stroke.as_ref(), pixels_per_km);
+    let line_style = line_style.as_str();
+    match geometry {
+        Geometry::Point(p) => {
+            // Markers are filled with the color of the stroke, like on the globe.
+            let (Some(q), Some(stroke)) = (frame.point(*p), &style.stroke) else {
+                return;
+            };
+            let q = to_pixels(q);
+            let (color, opacity) = svg_color(stroke.color);
+            let _ = writeln!(
+                svg,
+                r#"    <circle id="{id}" cx="{:.2}" cy="{:.2}" r="{MARKER_RADIUS}" fill="{color}" fill-opacity="{opacity}"/>"#,
+                q.x, q.y,
+            );
+        },
+        Geometry::Polyline(line) => write_line(svg, id, &frame.line(line), to_pixels, line_style),
+        Geometry::Path(path) => write_line(svg, id, &frame.line(&path.flatten(SEGMENT_ANGLE)), to_pixels, line_style),
+        Geometry::Polygon(polygon) => {
+            let rings: Vec<_> = frame
+                .polygon(&polygon.exterior, &polygon.holes)
+                .into_iter()
+                .flatten()
+                .collect();
+            if rings.is_empty() {
+                return;
+            }
+            let fill = match &style.fill {
+                Some(fill) => {
+                    let (color, opacity) = svg_color(fill.color);
+                    format!(r#"fill="{color}" fill-opacity="{opacity}" fill-rule="evenodd""#)
+                },
+                None => r#"fill="none""#.to_string(),
+            };
+            let _ = writeln!(svg, r#"    <path id="{id}" d="{}" {fill} {line_style}/>"#, path_data(&rings, true, to_pixels));
+        },
+    }
+}
+
+/// Writes a document to an SVG file, see [export].
+pub fn export_file(document: &Document, options: SvgOptions, path: &Path) -> io::Result<()> {
+    fs::write(path, export(document, options))
+}
+
+fn write_line(svg: &mut String, id: &str, parts: &[Vec<DVec2>], to_pixels: &impl Fn(DVec2) -> DVec2, style: &str) {
+    if parts.is_empty() {
+        return;
+    }
+    let _ = writeln!(svg, r#"    <path id="{id}" d="{}" fill="none" {style}/>"#, path_data(parts, false, to_pixels));
+}
+
+/// Formats the attributes drawing the outline of an element with a stroke, if any.
+///
+/// Arguments:
+///
+/// * `stroke`: The stroke, or `None` for no outline.
+/// * `pixels_per_km`: The scale of widths and dashes given in kilometres.
+fn stroke_attributes(stroke: Option<&Stroke>, pixels_per_km: f64) -> String {
+    let Some(stroke) = stroke else {
+        return r#"stroke="none""#.to_string();
+    };
+    let (color, opacity) = svg_color(stroke.color);
+    let scale = match stroke.width {
+        StrokeWidth::Pixels(_) => 1.0,
+        StrokeWidth::Kilometres(_) => pixels_per_km,
+    };
+    let width = stroke.width.value() as f64 * scale;
+    let cap = match stroke.cap {
+        LineCap::Butt => "butt",
+        LineCap::Round => "round",
+        LineCap::Square => "square",
+    };
+    let join = match stroke.join {
+        LineJoin::Miter => "miter",
+        LineJoin::Round => "round",
+        LineJoin::Bevel => "bevel",
+    };
+    let mut attributes = format!(
+        r#"stroke="{color}" stroke-opacity="{opacity}" stroke-width="{width:.2}" stroke-linejoin="{join}" stroke-linecap="{cap}""#
+    );
+    if stroke.join == LineJoin::Miter {
+        let _ = write!(attributes, r#" stroke-miterlimit="{MITER_LIMIT}""#);
+    }
+    let dash = stroke.dash_pattern();
+    if !dash.is_empty() {
+        let lengths: Vec<_> = dash.iter().map(|&length| format!("{:.2}", length as f64 * scale)).collect();
+        let _ = write!(attributes, r#" stroke-dasharray="{}""#, lengths.join(" "));
+    }
+    attributes
+}
+
+/// Escapes the characters of a text that have a meaning in XML attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Builds the `d` attribute of a path with one subpath for each part.
+fn path_data(parts: &[Vec<DVec2>], closed: bool, to_pixels: &impl Fn(DVec2) -> DVec2) -> String {
+    let mut data = String::new();
+    for part in parts {
+        for (i, q) in part.iter().enumerate() {
+            let q = to_pixels(*q);
+            let command = if i == 0 { 'M' } else { 'L' };
+            let _ = write!(data, "{command}{:.2} {:.2}", q.x, q.y);
+        }
+        if closed {
+            data.push('Z');
+        }
+    }
+    data
+}
+
+/// Formats a linear color as used by the renderer as an sRGB color and its opacity.
+fn svg_color(color: [f32; 4]) -> (String, f32) {
+    let encode = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        let srgb = if c <= 0.003_130_8 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        (srgb * 255.0).round() as u8
+    };
+    let [r, g, b, a] = color;
+    (format!("#{:02x}{:02x}{:02x}", encode(r), encode(g), encode(b)), a)
+}
+
+/// A projection together with a rotated frame of longitude and latitude in which the domain of
+/// the projection is a rectangle.
+///
+/// Geometry is cut at the antimeridian of the frame, which runs through the point opposite the
+/// centre of the map, and clipped to the rectangle before it is projected.
+struct MapFrame {
+    projection: Box<dyn Projection>,
+    /// Turns the globe so that the centre of the map lies at latitude and longitude `0`.
+    rotation: DQuat,
+    /// The domain of the projection in the rotated frame, as longitude and latitude in degrees.
+    clip: (DVec2, DVec2),
+}
+
+impl MapFrame {
+    fn new(kind: ProjectionKind, center: LatLon) -> Self {
+        let spin = DQuat::from_rotation_y(-center.lon.to_radians());
+        let tilt = DQuat::from_rotation_z(center.lat.to_radians());
+        let (rotation, max) = match kind {
+            // Cylindrical and pseudo-cylindrical maps keep their parallels horizontal.
+            ProjectionKind::Equirectangular
+            | ProjectionKind::Robinson
+            | ProjectionKind::Mollweide
+            | ProjectionKind::EqualEarth => (spin, DVec2::new(180.0, 90.0)),
+            ProjectionKind::Mercator => (spin, DVec2::new(180.0, WEB_MERCATOR_MAX_LAT)),
+            ProjectionKind::LambertAzimuthalEqualArea => (tilt * spin, DVec2::new(180.0, 90.0)),
+            // Only the hemisphere facing the viewer is visible.
+            ProjectionKind::Orthographic => (tilt * spin, DVec2::new(90.0, 90.0)),
+        };
+        let max = max - CLIP_INSET;
+        Self { projection: kind.create(center), rotation, clip: (-max, max) }
+    }
+
+    /// Projects a coordinate given in the rotated frame.
+    fn project(&self, q: DVec2) -> Option<DVec2> {
+        let p = self.rotation.inverse() * LatLon::new(q.y, q.x).to_unit_vector();
+        self.projection.forward(LatLon::from_unit_vector(p))
+    }
+
+    /// Projects a point, returning `None` if it lies outside the map.
+    fn point(&self, p: LatLon) -> Option<DVec2> {
+        let p = self.rotation * p.to_unit_vector();
+        let q = LatLon::from_unit_vector(p);
+        let q = DVec2::new(q.lon, q.lat);
+        let (min, max) = self.clip;
+        (q.cmpge(min).all() && q.cmple(max).all()).then(|| self.project(q)).flatten()
+    }
+
+    /// Projects a line, splitting it into the parts that lie on the map.
+    fn line(&self, line: &[LatLon]) -> Vec<Vec<DVec2>> {
+        let points: Vec<_> = line.iter().map(|p| self.rotation * p.to_unit_vector()).collect();
+        split_line(&densify(&points, SEGMENT_ANGLE))
+            .iter()
+            .flat_map(|part| clip_line(part, self.clip))
+            .flat_map(|part| self.project_line(&densify_planar(&part, false)))
+            .collect()
+    }
+
+    /// Projects a polygon, clipping it to the map.
+    ///
+    /// Returns the polygons on the map, each given by its exterior followed by its holes.
+    fn polygon(&self, exterior: &[LatLon], holes: &[Vec<LatLon>]) -> Vec<Vec<Vec<DVec2>>> {
+        let ring = |ring: &[LatLon]| {
+            let mut points: Vec<_> = ring.iter().map(|p| self.rotation * p.to_unit_vector()).collect();
+            if let Some(&first) = points.first() {
+                points.push(first);
+                points = densify(&points, SEGMENT_ANGLE);
+                points.pop();
+            }
+            points
+        };
+        let holes: Vec<_> = holes.iter().map(|hole| ring(hole)).collect();
+
+        let mut polygons = vec![];
+        for rings in split_polygon(&ring(exterior), &holes) {
+            let mut rings = rings.into_iter().map(|ring| clip_ring(&ring, self.clip));
+            // Holes do not matter when nothing of the exterior is left.
+            let Some(exterior) = rings.next().filter(|ring| ring.len() >= 3) else {
+                continue;
+            };
+            let polygon: Vec<_> = std::iter::once(exterior)
+                .chain(rings.filter(|ring| ring.len() >= 3))
+                .map(|ring| densify_planar(&ring, true).into_iter().filter_map(|q| self.project(q)).collect())
+                .collect();
+            polygons.push(polygon);
+        }
+        polygons
+    }
+
+    /// Projects a line given in the rotated frame, splitting it where points cannot be projected.
+    fn project_line(&self, line: &[DVec2]) -> Vec<Vec<DVec2>> {
+        let mut parts = vec![];
+        let mut part = vec![];
+        for &q in line {
+            match self.project(q) {
+                Some(q) => part.push(q),
+                None if part.len() >= 2 => parts.push(std::mem::take(&mut part)),
+                None => part.clear(),
+            }
+        }
+        if part.len() >= 2 {
+            parts.push(part);
+        }
+        parts
+    }
+}
+
+/// Clips a line in the plane to a rectangle, returning the parts inside it.
+fn clip_line(line: &[DVec2], (min, max): (DVec2, DVec2)) -> Vec<Vec<DVec2>> {
+    let mut parts = vec![];
+    let mut part: Vec<DVec2> = vec![];
+    for pair in line.windows(2) {
+        let Some((a, b)) = clip_segment(pair[0], pair[1], min, max) else {
+            continue;
+        };
+        if part.last() != Some(&a) {
+            if part.len() >= 2 {
+                parts.push(std::mem::take(&mut part));
+            }
+            part = vec![a];
+        }
+        part.push(b);
+    }
+    if part.len() >= 2 {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Clips a segment to a rectangle with the Liang-Barsky algorithm.
+fn clip_segment(a: DVec2, b: DVec2, min: DVec2, max: DVec2) -> Option<(DVec2, DVec2)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-d.x, a.x - min.x), (d.x, max.x - a.x), (-d.y, a.y - min.y), (d.y, max.y - a.y)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    (t0 <= t1).then(|| (if t0 > 0.0 { a + d * t0 } else { a }, if t1 < 1.0 { a + d * t1 } else { b }))
+}
+
+/// Clips a closed ring in the plane to a rectangle with the Sutherland-Hodgman algorithm.
+///
+/// Parts of the ring outside the rectangle are replaced by its border, so a concave ring may
+/// come back with edges running along the border twice.
+fn clip_ring(ring: &[DVec2], (min, max): (DVec2, DVec2)) -> Vec<DVec2> {
+    let mut ring = ring.to_vec();
+    // Each edge of the rectangle as the axis it bounds, its value and which side is inside.
+    for (axis, bound, keep_below) in [(0, min.x, false), (0, max.x, true), (1, min.y, false), (1, max.y, true)] {
+        let inside = |p: DVec2| if keep_below { p[axis] <= bound } else { p[axis] >= bound };
+        let mut clipped = Vec::with_capacity(ring.len() + 4);
+        for (i, &a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+            if inside(a) {
+                clipped.push(a);
+            }
+            if inside(a) != inside(b) {
+                let t = (bound - a[axis]) / (b[axis] - a[axis]);
+                let mut p = a + (b - a) * t;
+                p[axis] = bound;
+                clipped.push(p);
+            }
+        }
+        ring = clipped;
+        if ring.is_empty() {
+            break;
+        }
+    }
+    ring
+}
+
+/// Subdivides the edges of a line or ring given as longitude and latitude in degrees, so that
+/// straight edges along the border of the map follow its shape once projected.
+fn densify_planar(points: &[DVec2], closed: bool) -> Vec<DVec2> {
+    let mut result = Vec::with_capacity(points.len());
+    let count = if closed { points.len() } else { points.len().saturating_sub(1) };
+    for i in 0..count {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let steps = ((b - a).length() / BORDER_STEP).ceil().max(1.0) as usize;
+        result.extend((0..steps).map(|j| a.lerp(b, j as f64 / steps as f64)));
+    }
+    if !closed && let Some(&last) = points.last() {
+        result.push(last);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::{history::History, testing::DocumentExt, Polygon};
+
+    use super::*;
+
+    fn options(projection: ProjectionKind, center: LatLon) -> SvgOptions {
+        SvgOptions { projection, center, extent: None, width: 1000.0, graticule: Graticule::default() }
+    }
+
+    fn lat_lon_ring(coords: &[(f64, f64)]) -> Vec<LatLon> {
+        coords.iter().map(|&(lat, lon)| LatLon::new(lat, lon)).collect()
+    }
+
+    fn within(q: DVec2, (min, max): (DVec2, DVec2)) -> bool {
+        q.cmpge(min - 1e-9).all() && q.cmple(max + 1e-9).all()
+    }
+
+    #[test]
+    fn splits_lines_at_the_border_of_the_map() {
+        let frame = MapFrame::new(ProjectionKind::Robinson, LatLon::new(0.0, 0.0));
+        let parts = frame.line(&lat_lon_ring(&[(10.0, 170.0), (10.0, -170.0)]));
+
+        assert_eq!(parts.len(), 2);
+        // Both parts end on the border of the map, at the same latitude.
+        let (end, start) = (*parts[0].last().unwrap(), parts[1][0]);
+        assert!((end + DVec2::new(start.x, -start.y)).length() < 1e-6);
+        assert!(frame.projection.inverse(end * 1.001).is_none());
+
+        // The same line crosses the middle of a map centred on the antimeridian.
+        let frame = MapFrame::new(ProjectionKind::Robinson, LatLon::new(0.0, 180.0));
+        assert_eq!(frame.line(&lat_lon_ring(&[(10.0, 170.0), (10.0, -170.0)])).len(), 1);
+    }
+
+    #[test]
+    fn clips_polygons_to_the_visible_hemisphere() {
+        let frame = MapFrame::new(ProjectionKind::Orthographic, LatLon::new(0.0, 0.0));
+        let square = lat_lon_ring(&[(-20.0, 60.0), (-20.0, 120.0), (20.0, 120.0), (20.0, 60.0)]);
+        let polygons = frame.polygon(&square, &[]);
+
+        assert_eq!(polygons.len(), 1);
+        let exterior = &polygons[0][0];
+        assert!(exterior.iter().all(|q| q.length() <= 1.0 + 1e-9));
+        // The clipped polygon runs along the horizon.
+        assert!(exterior.iter().any(|q| q.length() > 1.0 - 1e-6));
+        assert!(frame.polygon(&lat_lon_ring(&[(-20.0, 120.0), (-20.0, 150.0), (20.0, 150.0)]), &[]).is_empty());
+    }
+
+    #[test]
+    fn clips_polar_caps_to_the_mercator_domain() {
+        let frame = MapFrame::new(ProjectionKind::Mercator, LatLon::new(0.0, 30.0));
+        let cap = lat_lon_ring(&[(70.0, 0.0), (70.0, 90.0), (70.0, 180.0), (70.0, -90.0)]);
+        let polygons = frame.polygon(&cap, &[]);
+
+        let bounds = frame.projection.bounds();
+        assert!(!polygons.is_empty());
+        for q in polygons.iter().flatten().flatten() {
+            assert!(within(*q, bounds), "{q} outside {bounds:?}");
+        }
+        let top = polygons.iter().flatten().flatten().map(|q| q.y).fold(f64::MIN, f64::max);
+        assert!((top - bounds.1.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn keeps_holes() {
+        let frame = MapFrame::new(ProjectionKind::EqualEarth, LatLon::new(0.0, 0.0));
+        let mut polygon = Polygon::new(lat_lon_ring(&[(-20.0, -20.0), (-20.0, 20.0), (20.0, 20.0), (20.0, -20.0)]));
+        polygon.holes.push(lat_lon_ring(&[(-5.0, -5.0), (5.0, -5.0), (5.0, 5.0), (-5.0, 5.0)]));
+
+        let polygons = frame.polygon(&polygon.exterior, &polygon.holes);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].len(), 2);
+    }
+
+    #[test]
+    fn writes_features_into_a_layer_group() {
+        let mut document = Document::new();
+        document.add(Geometry::Point(LatLon::new(52.5, 13.4)));
+        document.add(Geometry::Polyline(lat_lon_ring(&[(0.0, 170.0), (0.0, -170.0)])));
+        document.add(Geometry::Polygon(Polygon::new(lat_lon_ring(&[(0.0, 0.0), (0.0, 10.0), (10.0, 5.0)]))));
+
+        let svg = export(&document, options(ProjectionKind::Equirectangular, LatLon::new(0.0, 0.0)));
+
+        assert!(svg.contains(r#"width="1000.00" height="500.00""#));
+        assert!(svg.contains(r#"inkscape:groupmode="layer""#));
+        assert!(svg.contains(r#"<circle id="feature-1""#));
+        let line = svg.lines().find(|line| line.contains(r#"id="feature-2""#)).unwrap();
+        assert_eq!(line.matches('M').count(), 2);
+        let polygon = svg.lines().find(|line| line.contains(r#"id="feature-3""#)).unwrap();
+        assert!(polygon.contains(r#"fill-opacity="0.35""#));
+    }
+
+    #[test]
+    fn writes_one_group_per_layer() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        document.add(Geometry::Point(LatLon::new(10.0, 10.0)));
+        let rivers = history.add_layer(&mut document, "Rivers & lakes".to_string());
+        document.add(Geometry::Point(LatLon::new(20.0, 20.0)));
+        let mut layer = document.layer(rivers).unwrap().clone();
+        (layer.visible, layer.opacity) = (false, 0.5);
+        history.set_layer(&mut document, layer, false);
+
+        let svg = export(&document, options(ProjectionKind::Equirectangular, LatLon::new(0.0, 0.0)));
+
+        let groups: Vec<_> = svg.lines().filter(|line| line.contains(r#"inkscape:groupmode="layer""#)).collect();
+        assert_eq!(groups.len(), 2);
+        assert!(groups[0].contains(r#"inkscape:label="Layer 1" opacity="1""#));
+        assert!(groups[1].contains(r#"inkscape:label="Rivers &amp; lakes" opacity="0.5""#));
+        assert!(groups[1].contains("display:none"));
+        assert_eq!(svg.matches("</g>").count(), 2);
+    }
+
+    #[test]
+    fn writes_the_styles_of_features() {
+        let mut document = Document::new();
+        let mut history = History::new(8);
+        let line = history.add_feature(&mut document, Geometry::Polyline(lat_lon_ring(&[(0.0, 0.0), (0.0, 10.0)])));
+        history.add_feature(&mut document, Geometry::Polygon(Polygon::new(lat_lon_ring(&[(0.0, 0.0), (0.0, 10.0), (10.0, 5.0)]))));
+        let stroke = Stroke {
+            width: StrokeWidth::Kilometres(100.0),
+            dash: vec![200.0, 100.0],
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            ..Stroke::default()
+        };
+        history.set_style(&mut document, line, Some(Style { stroke: Some(stroke), fill: None }), false);
+        let mut layer = document.active_layer().clone();
+        layer.style.stroke = None;
+        history.set_layer(&mut document, layer, false);
+
+        let svg = export(&document, options(ProjectionKind::Equirectangular, LatLon::new(0.0, 0.0)));
+
+        // The map is 1000 pixels wide for the circumference of the earth.
+        let line = svg.lines().find(|line| line.contains(r#"id="feature-1""#)).unwrap();
+        let pixels_per_km = 1000.0 / (2.0 * PI * EARTH_RADIUS_KM);
+        assert!(line.contains(&format!(r#"stroke-width="{:.2}""#, 100.0 * pixels_per_km)), "{line}");
+        assert!(line.contains(&format!(r#"stroke-dasharray="{:.2} {:.2}""#, 200.0 * pixels_per_km, 100.0 * pixels_per_km)), "{line}");
+        assert!(line.contains(r#"stroke-linejoin="miter" stroke-linecap="butt""#), "{line}");
+        let polygon = svg.lines().find(|line| line.contains(r#"id="feature-2""#)).unwrap();
+        assert!(polygon.contains(r#"fill-opacity="0.35""#));
+        assert!(polygon.contains(r#"stroke="none""#));
+    }
+
+    #[test]
+    fn writes_the_graticule_below_the_layers() {
+        let mut document = Document::new();
+        document.add(Geometry::Point(LatLon::new(10.0, 10.0)));
+        let mut options = options(ProjectionKind::Robinson, LatLon::new(0.0, 0.0));
+        options.graticule = Graticule { visible: true, labels: true, spacing: Some(30.0) };
+
+        let svg = export(&document, options);
+
+        let group = svg.find(r#"id="graticule""#).unwrap();
+        assert!(group < svg.find(r#"id="layer-1""#).unwrap());
+        assert!(svg.contains(r#"id="graticule-1""#));
+        assert!(svg.contains(">30°N</text>"));
+        assert!(svg.contains(">0°</text>"));
+        assert!(svg.contains(">150°W</text>"));
+
+        options.graticule.labels = false;
+        assert!(!export(&document, options).contains("<text"));
+        options.graticule.visible = false;
+        assert!(!export(&document, options).contains("graticule"));
+    }
+
+    #[test]
+    fn leaves_out_points_outside_the_map() {
+        let mut document = Document::new();
+        document.add(Geometry::Point(LatLon::new(0.0, 180.0)));
+
+        let svg = export(&document, options(ProjectionKind::Orthographic, LatLon::new(0.0, 0.0)));
+        assert!(!svg.contains("<circle"));
+    }
+}
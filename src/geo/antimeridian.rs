@@ -0,0 +1,302 @@
+use std::iter;
+
+use glam::{DVec2, DVec3};
+
+use super::LatLon;
+
+/// Splits a line on the unit sphere where it crosses the antimeridian.
+///
+/// Arguments:
+///
+/// * `line`: The points of the line, connected by great-circle arcs.
+///
+/// Returns the parts of the line as longitude and latitude in degrees. The longitudes of every
+/// part stay within `[-180, 180]`, and parts end on the antimeridian where they were split.
+pub fn split_line(line: &[DVec3]) -> Vec<Vec<DVec2>> {
+    let line = unwrap(line);
+    if line.len() < 2 {
+        return vec![];
+    }
+
+    let mut parts: Vec<(i64, Vec<DVec2>)> = vec![];
+    for pair in line.windows(2) {
+        let strip = strip_of((pair[0].x + pair[1].x) / 2.0);
+        match parts.last_mut() {
+            Some((current, part)) if *current == strip => part.push(shift(pair[1], strip)),
+            _ => parts.push((strip, vec![shift(pair[0], strip), shift(pair[1], strip)])),
+        }
+    }
+    parts.into_iter().map(|(_, part)| part).collect()
+}
+
+/// Splits a polygon on the unit sphere into polygons that do not cross the antimeridian.
+///
+/// The interior lies to the left of every ring, see [super::enclosed_area]. Polygons that
+/// contain a pole are closed along the line of that pole's latitude, which is how such areas
+/// are represented on a map.
+///
+/// Arguments:
+///
+/// * `exterior`: The outer ring, without repeating the first point.
+/// * `holes`: Rings cut out of the polygon.
+///
+/// Returns polygons whose rings are given as longitude and latitude in degrees, without
+/// repeating their first point. The first ring of each polygon is its exterior, which runs
+/// counter-clockwise on the map, followed by its holes, which run clockwise.
+pub fn split_polygon(exterior: &[DVec3], holes: &[Vec<DVec3>]) -> Vec<Vec<Vec<DVec2>>> {
+    let mut rings = vec![];
+    for (i, ring) in iter::once(exterior).chain(holes.iter().map(Vec::as_slice)).enumerate() {
+        if ring.len() < 3 {
+            continue;
+        }
+        let is_hole = i > 0;
+        let closed: Vec<_> = ring.iter().chain(ring.first()).copied().collect();
+        let mut planar = unwrap(&closed);
+        let (first, last) = (planar[0], planar[planar.len() - 1]);
+        planar.pop();
+
+        // A ring that ends a full turn away from where it started winds around a pole.
+        let turns = ((last.x - first.x) / 360.0).round();
+        if turns.abs() > 1.0 {
+            log::warn!("Skipping a ring that winds around a pole {turns} times");
+            continue;
+        }
+        if turns != 0.0 {
+            // Heading east, the area on the left contains the north pole. The area of a hole
+            // lies on the right instead.
+            let pole = if is_hole { -90.0 * turns } else { 90.0 * turns };
+            planar.extend([last, DVec2::new(last.x, pole), DVec2::new(first.x, pole)]);
+        } else if !is_hole && signed_area(&planar) < 0.0 {
+            // A clockwise exterior surrounds both poles, so it becomes a hole in the whole map.
+            let (min, max) = x_range(iter::once(&planar));
+            let center = if min >= -180.0 && max <= 180.0 { 0.0 } else { (min + max) / 2.0 };
+            rings.push(vec![
+                DVec2::new(center - 180.0, -90.0),
+                DVec2::new(center + 180.0, -90.0),
+                DVec2::new(center + 180.0, 90.0),
+                DVec2::new(center - 180.0, 90.0),
+            ]);
+        }
+        rings.push(planar);
+    }
+    if rings.is_empty() {
+        return vec![];
+    }
+
+    let (min, max) = x_range(&rings);
+    let mut polygons = vec![];
+    let mut strip = strip_of((min + max) / 2.0);
+    let first_cut = ((min - 180.0) / 360.0).floor() as i64;
+    for k in first_cut..=strip_of(max) {
+        let cut = 180.0 + 360.0 * k as f64;
+        if cut <= min || cut >= max {
+            continue;
+        }
+        let [left, right] = split_rings(rings, cut);
+        polygons.extend(assemble(left, k));
+        rings = right;
+        strip = k + 1;
+    }
+    polygons.extend(assemble(rings, strip));
+    polygons
+}
+
+/// Converts points to longitude and latitude, letting the longitude run past `±180` instead of
+/// jumping, and inserts a point wherever a line crosses the antimeridian or one of its copies.
+fn unwrap(points: &[DVec3]) -> Vec<DVec2> {
+    let mut result: Vec<DVec2> = Vec::with_capacity(points.len());
+    let mut previous: Option<(DVec3, DVec2)> = None;
+    for &p in points {
+        let coord = LatLon::from_unit_vector(p);
+        let mut q = DVec2::new(coord.lon, coord.lat);
+        if let Some((a, qa)) = previous {
+            // Great-circle arcs are shorter than half a turn, so their longitude never changes
+            // by more than 180°.
+            q.x += ((qa.x - q.x) / 360.0).round() * 360.0;
+
+            let (lo, hi) = (qa.x.min(q.x), qa.x.max(q.x));
+            let mut cuts: Vec<f64> = (strip_of(lo)..=strip_of(hi))
+                .map(|k| 180.0 + 360.0 * k as f64)
+                .filter(|&cut| lo < cut && cut < hi)
+                .collect();
+            if q.x < qa.x {
+                cuts.reverse();
+            }
+            result.extend(cuts.into_iter().map(|cut| DVec2::new(cut, meridian_crossing(a, p, cut))));
+        }
+        result.push(q);
+        previous = Some((p, q));
+    }
+    result
+}
+
+/// Returns the latitude in degrees at which the arc from `a` to `b` crosses the meridian `lon`.
+fn meridian_crossing(a: DVec3, b: DVec3, lon: f64) -> f64 {
+    let lon = lon.to_radians();
+    let normal = DVec3::new(lon.sin(), 0.0, lon.cos());
+    let (da, db) = (normal.dot(a), normal.dot(b));
+    let t = if da == db { 0.0 } else { da / (da - db) };
+    LatLon::from_unit_vector(a.lerp(b, t)).lat
+}
+
+/// Returns the index of the copy of the map `[-180, 180]` that contains the longitude `x`.
+fn strip_of(x: f64) -> i64 {
+    ((x + 180.0) / 360.0).floor() as i64
+}
+
+fn shift(p: DVec2, strip: i64) -> DVec2 {
+    DVec2::new(p.x - 360.0 * strip as f64, p.y)
+}
+
+fn x_range<'a>(rings: impl IntoIterator<Item = &'a Vec<DVec2>>) -> (f64, f64) {
+    rings
+        .into_iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| (min.min(p.x), max.max(p.x)))
+}
+
+/// Cuts closed rings along the vertical line at `x = cut`.
+///
+/// Rings are cut into chains that start and end on the line. The chains on each side are then
+/// joined along the line into new rings, keeping the interior of the rings on their left.
+///
+/// Returns the rings left and right of the line.
+fn split_rings(rings: Vec<Vec<DVec2>>, cut: f64) -> [Vec<Vec<DVec2>>; 2] {
+    let mut result = [vec![], vec![]];
+    let mut chains = [vec![], vec![]];
+
+    for ring in rings {
+        let has_left = ring.iter().any(|p| p.x < cut);
+        let has_right = ring.iter().any(|p| p.x > cut);
+        if !has_left || !has_right {
+            // Rings lying on the line itself enclose nothing and are dropped.
+            if has_left || has_right {
+                result[has_right as usize].push(ring);
+            }
+            continue;
+        }
+
+        let mut points = Vec::with_capacity(ring.len() + 2);
+        for (i, &a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+            points.push(a);
+            if (a.x - cut) * (b.x - cut) < 0.0 {
+                let t = (cut - a.x) / (b.x - a.x);
+                points.push(DVec2::new(cut, a.y + t * (b.y - a.y)));
+            }
+        }
+
+        for (side, sign) in [(0, -1.0), (1, 1.0)] {
+            let on_side = |p: DVec2| (p.x - cut) * sign > 0.0;
+            let on_other_side = |p: DVec2| (p.x - cut) * sign < 0.0;
+            // Start on the other side so that every chain is complete when the loop ends there.
+            let start = points.iter().position(|&p| on_other_side(p)).unwrap();
+            let mut chain: Vec<DVec2> = vec![];
+            for j in 1..=points.len() {
+                let p = points[(start + j) % points.len()];
+                if on_other_side(p) {
+                    // Chains that only touch the line enclose nothing.
+                    if chain.iter().any(|&p| on_side(p)) {
+                        chains[side].push(std::mem::take(&mut chain));
+                    } else {
+                        chain.clear();
+                    }
+                } else {
+                    chain.push(p);
+                }
+            }
+        }
+    }
+
+    let [left, right] = chains;
+    // Walking with the interior on the left means walking north along the line on its left
+    // side and south on its right side.
+    result[0].extend(stitch(left, 1.0));
+    result[1].extend(stitch(right, -1.0));
+    result
+}
+
+/// Joins chains that start and end on a vertical line into closed rings.
+///
+/// Each chain is continued with the chain that starts closest after its end when walking along
+/// the line in `direction`, which is `1` for north and `-1` for south.
+fn stitch(chains: Vec<Vec<DVec2>>, direction: f64) -> Vec<Vec<DVec2>> {
+    let mut used = vec![false; chains.len()];
+    let mut rings = vec![];
+    for first in 0..chains.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let mut ring = chains[first].clone();
+        loop {
+            let end = ring[ring.len() - 1].y;
+            let next = (0..chains.len())
+                .filter(|&j| !used[j] || j == first)
+                .map(|j| (j, (chains[j][0].y - end) * direction))
+                .filter(|(_, distance)| *distance >= 0.0)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match next {
+                Some((j, _)) if j != first => {
+                    used[j] = true;
+                    ring.extend(&chains[j]);
+                },
+                _ => break,
+            }
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+/// Groups the rings of one copy of the map into polygons and moves them into `[-180, 180]`.
+///
+/// Counter-clockwise rings become exteriors, and each clockwise ring becomes a hole of the
+/// smallest exterior containing it.
+fn assemble(rings: Vec<Vec<DVec2>>, strip: i64) -> Vec<Vec<Vec<DVec2>>> {
+    let mut exteriors = vec![];
+    let mut holes = vec![];
+    for ring in rings {
+        let ring: Vec<_> = ring.into_iter().map(|p| shift(p, strip)).collect();
+        let area = signed_area(&ring);
+        if area > 1e-12 {
+            exteriors.push((area, vec![ring]));
+        } else if area < -1e-12 {
+            holes.push(ring);
+        }
+    }
+
+    for hole in holes {
+        // Prefer a point away from the edges of the map, where the hole may touch its exterior.
+        let inner = hole.iter().find(|p| p.x.abs() < 180.0).unwrap_or(&hole[0]);
+        let container = exteriors
+            .iter_mut()
+            .filter(|(_, polygon)| ring_contains(&polygon[0], *inner))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, polygon)) = container {
+            polygon.push(hole);
+        }
+    }
+    exteriors.into_iter().map(|(_, polygon)| polygon).collect()
+}
+
+/// The area enclosed by a ring in the plane, positive if it runs counter-clockwise.
+fn signed_area(ring: &[DVec2]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in ring.iter().enumerate() {
+        area += a.perp_dot(ring[(i + 1) % ring.len()]);
+    }
+    area / 2.0
+}
+
+/// Tests whether a point lies inside a ring in the plane.
+fn ring_contains(ring: &[DVec2], p: DVec2) -> bool {
+    let mut inside = false;
+    for (i, a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
@@ -0,0 +1,128 @@
+use bezier_rs::{Bezier, TValue};
+use glam::DVec3;
+
+use super::{angle_between, slerp, LatLon};
+
+/// The smallest cosine between a control point and the centre of its tangent frame.
+///
+/// The gnomonic projection used for the tangent frame distorts strongly towards 90° from its
+/// centre, so curves spanning more than this fall back to spherical interpolation.
+const MIN_FRAME_COSINE: f64 = 0.5;
+
+/// The most points a single curve is flattened into.
+const MAX_STEPS: usize = 4096;
+
+/// Flattens a cubic Bezier curve with control points on the globe into a line.
+///
+/// The curve is evaluated in a gnomonic tangent frame centred on its control points. Great circles
+/// are straight lines in that projection, so a curve whose handles lie on the arc between its end
+/// points is exactly a geodesic. Curves too large for the frame are evaluated with spherical
+/// linear interpolation instead.
+///
+/// Arguments:
+///
+/// * `control`: The start point, the two handles and the end point of the curve.
+/// * `max_angle`: The largest angle in radians between two consecutive points of the result.
+pub fn flatten_cubic(control: [LatLon; 4], max_angle: f64) -> Vec<LatLon> {
+    let points = control.map(|p| p.to_unit_vector());
+    let center = points.iter().sum::<DVec3>();
+
+    let flattened = if center.length_squared() > 1e-12
+        && points.iter().all(|p| p.dot(center.normalize()) >= MIN_FRAME_COSINE)
+    {
+        flatten_in_tangent_frame(points, center.normalize(), max_angle)
+    } else {
+        flatten_spherical(points, max_angle)
+    };
+
+    flattened.into_iter().map(LatLon::from_unit_vector).collect()
+}
+
+/// Evaluates the curve in the plane touching the sphere at `center`.
+fn flatten_in_tangent_frame(points: [DVec3; 4], center: DVec3, max_angle: f64) -> Vec<DVec3> {
+    let e1 = center.any_orthonormal_vector();
+    let e2 = center.cross(e1);
+    let [p0, p1, p2, p3] = points.map(|p| {
+        let p = p / p.dot(center);
+        (p.dot(e1), p.dot(e2))
+    });
+
+    let bezier = Bezier::from_cubic_coordinates(p0.0, p0.1, p1.0, p1.1, p2.0, p2.1, p3.0, p3.1);
+    // Distances in the tangent plane are never shorter than the angles they cover.
+    let steps = step_count(bezier.length(Some(64)), max_angle);
+    (0..=steps)
+        .map(|i| {
+            let p = bezier.evaluate(TValue::Parametric(i as f64 / steps as f64));
+            (center + e1 * p.x + e2 * p.y).normalize()
+        })
+        .collect()
+}
+
+/// Evaluates the curve with de Casteljau's algorithm, using great-circle arcs instead of lines.
+fn flatten_spherical(points: [DVec3; 4], max_angle: f64) -> Vec<DVec3> {
+    let control_length: f64 = points.windows(2).map(|p| angle_between(p[0], p[1])).sum();
+    let steps = step_count(control_length, max_angle);
+    (0..=steps)
+        .map(|i| {
+            let t = i as f64 / steps as f64;
+            let [a, b, c, d] = points;
+            let (ab, bc, cd) = (slerp(a, b, t), slerp(b, c, t), slerp(c, d, t));
+            slerp(slerp(ab, bc, t), slerp(bc, cd, t), t)
+        })
+        .collect()
+}
+
+fn step_count(length: f64, max_angle: f64) -> usize {
+    ((length / max_angle).ceil() as usize).clamp(1, MAX_STEPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::simplify::distance_to_arc;
+
+    /// The largest angle between a dense sampling of `reference` and the line through `points`.
+    fn max_deviation(points: &[LatLon], reference: &[DVec3]) -> f64 {
+        let line: Vec<_> = points.iter().map(|p| p.to_unit_vector()).collect();
+        reference
+            .iter()
+            .map(|&r| line.windows(2).map(|s| distance_to_arc(r, s[0], s[1])).fold(f64::INFINITY, f64::min))
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn goes_through_the_anchors() {
+        let small = [(10.0, 10.0), (20.0, 15.0), (15.0, 30.0), (5.0, 35.0)].map(|(lat, lon)| LatLon::new(lat, lon));
+        // Too large for the tangent frame, so this one is interpolated on the sphere.
+        let large = [(0.0, 0.0), (60.0, 60.0), (0.0, 120.0), (-30.0, 170.0)].map(|(lat, lon)| LatLon::new(lat, lon));
+
+        for control in [small, large] {
+            let line = flatten_cubic(control, 0.5f64.to_radians());
+            assert!(line[0].angular_distance(control[0]) < 1e-9);
+            assert!(line[line.len() - 1].angular_distance(control[3]) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn stays_close_to_the_curve_in_the_tangent_frame() {
+        let control = [(10.0, 10.0), (30.0, 15.0), (-5.0, 30.0), (5.0, 40.0)].map(|(lat, lon)| LatLon::new(lat, lon));
+        let center = control.iter().map(|p| p.to_unit_vector()).sum::<DVec3>().normalize();
+        let reference = flatten_in_tangent_frame(control.map(LatLon::to_unit_vector), center, 1e-4);
+
+        for degrees in [0.1, 1.0, 5.0] {
+            let max_angle = f64::to_radians(degrees);
+            let line = flatten_cubic(control, max_angle);
+            assert!(max_deviation(&line, &reference) < max_angle / 4.0, "{degrees}°");
+        }
+    }
+
+    #[test]
+    fn handles_on_the_arc_give_a_geodesic() {
+        let (a, b) = (LatLon::new(-20.0, 10.0), LatLon::new(30.0, 50.0));
+        let handles = [1.0 / 3.0, 2.0 / 3.0].map(|t| LatLon::from_unit_vector(slerp(a.to_unit_vector(), b.to_unit_vector(), t)));
+        let line = flatten_cubic([a, handles[0], handles[1], b], 1f64.to_radians());
+        for p in line {
+            assert!(distance_to_arc(p.to_unit_vector(), a.to_unit_vector(), b.to_unit_vector()) < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use glam::DVec3;
+
+use super::angle_between;
+
+/// Interpolates along the great circle between two unit vectors.
+///
+/// Arguments:
+///
+/// * `a`: The start of the arc, returned for `t = 0`.
+/// * `b`: The end of the arc, returned for `t = 1`.
+/// * `t`: The fraction of the arc to travel.
+pub fn slerp(a: DVec3, b: DVec3, t: f64) -> DVec3 {
+    let angle = angle_between(a, b);
+    if angle < 1e-9 {
+        return a.lerp(b, t).normalize();
+    }
+    let sin = angle.sin();
+    if sin < 1e-9 {
+        // Antipodal points have no unique great circle, so go around any perpendicular axis.
+        let axis = a.any_orthonormal_vector();
+        let theta = angle * t;
+        return a * theta.cos() + axis * theta.sin();
+    }
+    (a * ((1.0 - t) * angle).sin() + b * (t * angle).sin()) / sin
+}
+
+/// Subdivides a line of unit vectors so that no segment spans more than `max_angle` radians.
+///
+/// The inserted points lie on the great circles between the original points, so the result
+/// follows the same geodesics when its segments are drawn as straight chords.
+///
+/// Arguments:
+///
+/// * `line`: The points of the line on the unit sphere.
+/// * `max_angle`: The largest angle in radians a segment may span.
+pub fn densify(line: &[DVec3], max_angle: f64) -> Vec<DVec3> {
+    let mut result = Vec::with_capacity(line.len());
+    for pair in line.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let steps = (angle_between(a, b) / max_angle).ceil().max(1.0) as usize;
+        // Push the original point itself, which slerp would only reproduce up to rounding.
+        result.push(a);
+        result.extend((1..steps).map(|i| slerp(a, b, i as f64 / steps as f64)));
+    }
+    result.extend(line.last());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::LatLon;
+
+    fn assert_finite_unit(v: DVec3) {
+        assert!(v.is_finite() && (v.length() - 1.0).abs() < 1e-9, "{v}");
+    }
+
+    #[test]
+    fn densified_steps_stay_below_the_limit() {
+        let line = [LatLon::new(0.0, 0.0), LatLon::new(45.0, 90.0), LatLon::new(-30.0, 170.0), LatLon::new(-30.0, -170.0)]
+            .map(LatLon::to_unit_vector);
+        let max_angle = 2f64.to_radians();
+        let dense = densify(&line, max_angle);
+
+        assert!(dense.len() > 100);
+        for pair in dense.windows(2) {
+            assert_finite_unit(pair[1]);
+            assert!(angle_between(pair[0], pair[1]) <= max_angle + 1e-12);
+        }
+        // The original points are kept exactly, including the endpoints.
+        for point in line {
+            assert!(dense.contains(&point));
+        }
+        assert_eq!(dense.first(), line.first());
+        assert_eq!(dense.last(), line.last());
+    }
+
+    #[test]
+    fn slerp_keeps_the_endpoints() {
+        let (a, b) = (LatLon::new(10.0, 20.0).to_unit_vector(), LatLon::new(-40.0, 100.0).to_unit_vector());
+        assert!(slerp(a, b, 0.0).distance(a) < 1e-12);
+        assert!(slerp(a, b, 1.0).distance(b) < 1e-12);
+        let middle = slerp(a, b, 0.5);
+        assert!((angle_between(a, middle) - angle_between(middle, b)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degenerate_arcs_give_no_nan() {
+        let a = LatLon::new(30.0, 40.0).to_unit_vector();
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            assert_finite_unit(slerp(a, a, t));
+            assert_finite_unit(slerp(a, -a, t));
+        }
+        assert!((angle_between(a, slerp(a, -a, 0.5)) - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+        let dense = densify(&[a, a, -a], 0.1);
+        assert_eq!(dense.first(), Some(&a));
+        assert_eq!(dense.last(), Some(&-a));
+        for pair in dense.windows(2) {
+            assert_finite_unit(pair[1]);
+            assert!(angle_between(pair[0], pair[1]) <= 0.1 + 1e-12);
+        }
+        assert_eq!(densify(&[], 0.1), vec![]);
+    }
+}
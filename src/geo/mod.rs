@@ -0,0 +1,188 @@
+mod antimeridian;
+mod bezier;
+mod geodesic;
+mod polygon;
+mod simplify;
+
+pub use antimeridian::{split_line, split_polygon};
+pub use bezier::flatten_cubic;
+pub use geodesic::{densify, slerp};
+pub use polygon::{enclosed_area, triangulate};
+pub use simplify::simplify;
+
+use std::fmt;
+
+use glam::{DVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// The mean radius of the earth in kilometres, used to turn distances on the ground into angles.
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// A position on the globe given in geographic coordinates.
+///
+/// Coordinates are stored in degrees, latitude first. The latitude is clamped to `[-90, 90]`
+/// and the longitude is wrapped into `[-180, 180]` when created through [LatLon::new], which is
+/// also used when deserializing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "LatLonData")]
+pub struct LatLon {
+    /// The latitude in degrees, positive towards the north pole.
+    pub lat: f64,
+    /// The longitude in degrees, positive towards the east.
+    pub lon: f64,
+}
+
+impl LatLon {
+    /// Creates a new [LatLon], clamping the latitude and wrapping the longitude.
+    ///
+    /// Arguments:
+    ///
+    /// * `lat`: The latitude in degrees.
+    /// * `lon`: The longitude in degrees.
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self {
+            lat: lat.clamp(-90.0, 90.0),
+            lon: wrap_longitude(lon),
+        }
+    }
+
+    /// Converts the coordinate to a point on the unit sphere.
+    ///
+    /// The axes match the textured globe built in [crate::sphere]: `+Y` points to the north pole,
+    /// the prime meridian lies on `-X` and `90°E` on `+Z`.
+    pub fn to_unit_vector(self) -> DVec3 {
+        let (lat, lon) = (self.lat.to_radians(), self.lon.to_radians());
+        DVec3::new(
+            -lat.cos() * lon.cos(),
+            lat.sin(),
+            lat.cos() * lon.sin(),
+        )
+    }
+
+    /// Converts a direction from the centre of the globe back to geographic coordinates.
+    ///
+    /// The vector does not need to be normalised but must not be zero.
+    pub fn from_unit_vector(v: DVec3) -> Self {
+        let v = v.normalize();
+        let lat = v.y.clamp(-1.0, 1.0).asin().to_degrees();
+        let lon = v.z.atan2(-v.x).to_degrees();
+        Self::new(lat, lon)
+    }
+
+    /// Returns the position of this coordinate on a globe of the given `radius`.
+    pub fn to_globe_position(self, radius: f32) -> Vec3 {
+        self.to_unit_vector().as_vec3() * radius
+    }
+
+    /// The great-circle angle between two coordinates in radians.
+    pub fn angular_distance(self, other: LatLon) -> f64 {
+        angle_between(self.to_unit_vector(), other.to_unit_vector())
+    }
+}
+
+/// The serialized form of a [LatLon], which may be out of range or not a number at all.
+#[derive(Deserialize)]
+struct LatLonData {
+    lat: f64,
+    lon: f64,
+}
+
+impl TryFrom<LatLonData> for LatLon {
+    type Error = NonFiniteCoordinate;
+
+    fn try_from(data: LatLonData) -> Result<Self, Self::Error> {
+        if !data.lat.is_finite() || !data.lon.is_finite() {
+            return Err(NonFiniteCoordinate { lat: data.lat, lon: data.lon });
+        }
+        Ok(LatLon::new(data.lat, data.lon))
+    }
+}
+
+/// The error returned when reading a [LatLon] whose latitude or longitude is infinite or NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonFiniteCoordinate {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl fmt::Display for NonFiniteCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the coordinate ({}, {}) is not a finite number", self.lat, self.lon)
+    }
+}
+
+impl std::error::Error for NonFiniteCoordinate {}
+
+/// Wraps a longitude in degrees into the interval `[-180, 180]`.
+pub fn wrap_longitude(lon: f64) -> f64 {
+    if (-180.0..=180.0).contains(&lon) {
+        lon
+    } else {
+        (lon + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+/// The angle between two unit vectors in radians.
+///
+/// Uses `atan2` rather than `acos` so that very short distances stay accurate.
+pub fn angle_between(a: DVec3, b: DVec3) -> f64 {
+    a.cross(b).length().atan2(a.dot(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(p: LatLon) -> LatLon {
+        LatLon::from_unit_vector(p.to_unit_vector())
+    }
+
+    #[test]
+    fn converts_to_and_from_unit_vectors() {
+        for (lat, lon) in [(0.0, 0.0), (12.5, 34.0), (-45.0, -120.0), (89.0, 179.0), (-89.0, -179.0)] {
+            let p = LatLon::new(lat, lon);
+            let q = round_trip(p);
+            assert!((q.lat - p.lat).abs() < 1e-9 && (q.lon - p.lon).abs() < 1e-9, "{p:?} != {q:?}");
+        }
+        assert!(LatLon::new(0.0, 90.0).to_unit_vector().distance(DVec3::Z) < 1e-12);
+        assert!(LatLon::new(0.0, 0.0).to_unit_vector().distance(DVec3::NEG_X) < 1e-12);
+    }
+
+    #[test]
+    fn round_trips_are_stable_at_the_poles() {
+        for lat in [90.0, -90.0] {
+            for lon in [-180.0, -45.0, 0.0, 135.0, 180.0] {
+                let p = LatLon::new(lat, lon);
+                let q = round_trip(p);
+                assert_eq!(q.lat, lat);
+                assert!(q.lon.is_finite() && (-180.0..=180.0).contains(&q.lon));
+                assert_eq!(round_trip(q), q);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_are_stable_at_the_antimeridian() {
+        for lon in [180.0, -180.0] {
+            let q = round_trip(LatLon::new(30.0, lon));
+            assert!((q.lat - 30.0).abs() < 1e-9);
+            assert!((q.lon.abs() - 180.0).abs() < 1e-9, "{q:?}");
+            assert!(q.angular_distance(LatLon::new(30.0, lon)) < 1e-12);
+            assert_eq!(round_trip(q), q);
+        }
+        assert_eq!(LatLon::new(0.0, 190.0).lon, -170.0);
+        assert_eq!(LatLon::new(0.0, -540.0).lon, -180.0);
+    }
+
+    #[test]
+    fn deserializes_through_new() {
+        let p: LatLon = serde_json::from_str(r#"{ "lat": 95.0, "lon": 200.0 }"#).unwrap();
+        assert_eq!(p, LatLon::new(90.0, -160.0));
+
+        for (lat, lon) in [(f64::NAN, 0.0), (0.0, f64::INFINITY), (f64::NEG_INFINITY, f64::NAN)] {
+            let err = LatLon::try_from(LatLonData { lat, lon }).unwrap_err();
+            assert!(err.lat.is_nan() || err.lat == lat);
+            assert!(err.lon.is_nan() || err.lon == lon);
+        }
+    }
+}
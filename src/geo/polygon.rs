@@ -0,0 +1,377 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use glam::{DVec2, DVec3};
+
+use super::{angle_between, densify};
+
+/// The most rounds of subdivision applied to the triangles of a polygon.
+const MAX_SUBDIVISIONS: usize = 16;
+
+/// Returns the area enclosed to the left of a closed ring on the unit sphere in steradians.
+///
+/// Walking along the ring with the interior on the left means walking counter-clockwise when
+/// looking at the globe from outside. The result is in `[0, 4π]`; a ring enclosing less than a
+/// hemisphere to its right returns more than `2π`.
+///
+/// Arguments:
+///
+/// * `ring`: The points of the ring on the unit sphere, without repeating the first point.
+pub fn enclosed_area(ring: &[DVec3]) -> f64 {
+    if ring.len() < 3 {
+        return 0.0;
+    }
+
+    // Gauss-Bonnet: the area of a spherical polygon is 2π minus the sum of its turning angles.
+    let mut turning = 0.0;
+    for i in 0..ring.len() {
+        let a = ring[i];
+        let b = ring[(i + 1) % ring.len()];
+        let c = ring[(i + 2) % ring.len()];
+        let incoming = a.cross(b).cross(b);
+        let outgoing = b.cross(c).cross(b);
+        turning += incoming.cross(outgoing).dot(b).atan2(incoming.dot(outgoing));
+    }
+    (2.0 * PI - turning).rem_euclid(4.0 * PI)
+}
+
+/// Whether the shorter great-circle arcs `a`-`b` and `c`-`d` cross each other.
+pub fn arcs_intersect(a: DVec3, b: DVec3, c: DVec3, d: DVec3) -> bool {
+    let n1 = a.cross(b);
+    let n2 = c.cross(d);
+    if (c.dot(n1) > 0.0) == (d.dot(n1) > 0.0) || (a.dot(n2) > 0.0) == (b.dot(n2) > 0.0) {
+        return false;
+    }
+
+    // The great circles cross in two antipodal points, one of which has to lie on both arcs.
+    let mut crossing = n1.cross(n2);
+    if crossing.dot(a + b) < 0.0 {
+        crossing = -crossing;
+    }
+    crossing.dot(c + d) > 0.0
+}
+
+/// Tests whether a point lies inside a polygon made of closed rings on the unit sphere.
+///
+/// A sphere has no natural "outside", so the test needs a point known to be outside the polygon
+/// and counts how often the arc from it to `point` crosses the rings.
+///
+/// Arguments:
+///
+/// * `rings`: The exterior ring followed by the holes.
+/// * `outside`: Any point that is known not to be part of the polygon.
+/// * `point`: The point to test.
+pub fn contains(rings: &[Vec<DVec3>], outside: DVec3, point: DVec3) -> bool {
+    let mut crossings = 0;
+    for ring in rings {
+        for i in 0..ring.len() {
+            if arcs_intersect(outside, point, ring[i], ring[(i + 1) % ring.len()]) {
+                crossings += 1;
+            }
+        }
+    }
+    crossings % 2 == 1
+}
+
+/// Triangulates a polygon on the unit sphere.
+///
+/// The interior lies to the left of `exterior`, see [enclosed_area]. The polygon is projected
+/// stereographically from a point outside of it, which maps any region that is not the entire
+/// sphere to a bounded area of the plane, including regions around a pole, across the
+/// antimeridian or larger than a hemisphere. The planar triangles are then subdivided in the
+/// plane and projected back until they are small enough to follow the curvature of the sphere.
+///
+/// Arguments:
+///
+/// * `exterior`: The outer ring, without repeating the first point.
+/// * `holes`: Rings cut out of the polygon.
+/// * `max_angle`: The largest angle in radians spanned by any edge of the result.
+///
+/// Returns the vertices on the unit sphere and the indices of triangles that are counter-clockwise
+/// when viewed from outside the sphere.
+pub fn triangulate(exterior: &[DVec3], holes: &[Vec<DVec3>], max_angle: f64) -> (Vec<DVec3>, Vec<u32>) {
+    let Some(exterior) = prepare_ring(exterior, max_angle) else {
+        return (vec![], vec![]);
+    };
+    let mut rings = vec![exterior];
+    rings.extend(holes.iter().filter_map(|hole| prepare_ring(hole, max_angle)));
+
+    let outside = outside_point(&rings[0]);
+    let projection = Stereographic::new(projection_pole(&rings, outside));
+
+    let mut vertices = vec![];
+    let mut hole_indices = vec![];
+    for (i, ring) in rings.iter().enumerate() {
+        if i > 0 {
+            hole_indices.push(vertices.len());
+        }
+        vertices.extend(ring.iter().map(|&p| (p, projection.forward(p))));
+    }
+
+    let coords: Vec<f64> = vertices.iter().flat_map(|(_, p)| [p.x, p.y]).collect();
+    let Ok(triangles) = earcutr::earcut(&coords, &hole_indices, 2) else {
+        log::warn!("Failed to triangulate a polygon with {} vertices", vertices.len());
+        return (vec![], vec![]);
+    };
+
+    let mut indices: Vec<u32> = triangles.iter().map(|&i| i as u32).collect();
+    subdivide(&mut vertices, &mut indices, &projection, max_angle);
+
+    // The projection may mirror the polygon, so fix the winding once the triangles are small.
+    for triangle in indices.chunks_exact_mut(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].0);
+        if (b - a).cross(c - a).dot(a + b + c) < 0.0 {
+            triangle.swap(1, 2);
+        }
+    }
+
+    (vertices.into_iter().map(|(p, _)| p).collect(), indices)
+}
+
+/// Removes duplicate points from a ring and subdivides its edges along great circles.
+fn prepare_ring(ring: &[DVec3], max_angle: f64) -> Option<Vec<DVec3>> {
+    let mut points: Vec<DVec3> = vec![];
+    for &p in ring {
+        if points.last().is_none_or(|last| angle_between(*last, p) > 1e-12) {
+            points.push(p);
+        }
+    }
+    while points.len() > 1 && angle_between(points[0], points[points.len() - 1]) <= 1e-12 {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return None;
+    }
+
+    points.push(points[0]);
+    let mut points = densify(&points, max_angle);
+    points.pop();
+    Some(points)
+}
+
+/// Returns a point just to the right of the first edge of the exterior ring, which is outside.
+fn outside_point(exterior: &[DVec3]) -> DVec3 {
+    let (a, b) = (exterior[0], exterior[1]);
+    let midpoint = (a + b).normalize();
+    let left = a.cross(b).normalize();
+    let offset = (angle_between(a, b) * 0.01).clamp(1e-9, 1e-3);
+    (midpoint - left * offset).normalize()
+}
+
+/// Picks the centre of the stereographic projection used for triangulation.
+///
+/// The centre has to lie outside the polygon. The further away it is from all vertices, the less
+/// the polygon is distorted in the projection.
+fn projection_pole(rings: &[Vec<DVec3>], outside: DVec3) -> DVec3 {
+    let mean: DVec3 = rings[0].iter().sum();
+    let mut candidates = vec![];
+    if mean.length_squared() > 1e-12 {
+        // Opposite a small polygon, or in the middle of the small region a polygon covering
+        // most of the sphere leaves out.
+        candidates.push(-mean.normalize());
+        candidates.push(mean.normalize());
+    }
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                if (x, y, z) != (0, 0, 0) {
+                    candidates.push(DVec3::new(x as f64, y as f64, z as f64).normalize());
+                }
+            }
+        }
+    }
+
+    let clearance = |p: DVec3| {
+        rings
+            .iter()
+            .flatten()
+            .map(|v| angle_between(*v, p))
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    candidates
+        .into_iter()
+        // The test arc becomes ambiguous for points opposite the reference point.
+        .filter(|p| angle_between(*p, outside) < PI - 1e-3 && !contains(rings, outside, *p))
+        .map(|p| (clearance(p), p))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map_or(outside, |(_, p)| p)
+}
+
+/// Splits triangles until none of their edges spans more than `max_angle` radians on the sphere.
+///
+/// Edges are split at their midpoint in the plane of the projection, so that the triangles keep
+/// tiling the polygon exactly. Whether an edge is split only depends on the edge itself, so
+/// neighbouring triangles always agree and no cracks open up between them.
+///
+/// Arguments:
+///
+/// * `vertices`: Each vertex on the unit sphere together with its projected position.
+/// * `indices`: The triangles to split.
+/// * `projection`: The projection the triangles were built in.
+/// * `max_angle`: The longest allowed edge in radians.
+fn subdivide(vertices: &mut Vec<(DVec3, DVec2)>, indices: &mut Vec<u32>, projection: &Stereographic, max_angle: f64) {
+    let mut midpoints: HashMap<(u32, u32), Option<u32>> = HashMap::new();
+
+    for _ in 0..MAX_SUBDIVISIONS {
+        let mut result = Vec::with_capacity(indices.len());
+        let mut changed = false;
+
+        for triangle in indices.chunks_exact(3) {
+            let corners = [triangle[0], triangle[1], triangle[2]];
+            let mids = [0, 1, 2].map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let (pa, qa) = vertices[a as usize];
+                    let (pb, qb) = vertices[b as usize];
+                    let q = (qa + qb) / 2.0;
+                    let p = projection.inverse(q);
+                    // The image of a planar edge is a circular arc, which may be much longer
+                    // than the distance between its end points, so measure it via its midpoint.
+                    (angle_between(pa, p) + angle_between(p, pb) > max_angle).then(|| {
+                        vertices.push((p, q));
+                        vertices.len() as u32 - 1
+                    })
+                })
+            });
+
+            changed |= mids.iter().any(Option::is_some);
+            split_triangle(corners, mids, &mut result);
+        }
+
+        *indices = result;
+        if !changed {
+            break;
+        }
+    }
+}
+/// Splits a triangle at the midpoints of some of its edges.
+///
+/// `mids[i]` is the midpoint of the edge from `corners[i]` to `corners[(i + 1) % 3]`, if that
+/// edge is split.
+fn split_triangle(corners: [u32; 3], mids: [Option<u32>; 3], out: &mut Vec<u32>) {
+    let split = mids.iter().filter(|m| m.is_some()).count();
+    // Rotate so that the split edges come first, which leaves fewer cases.
+    let rotation = match split {
+        1 => mids.iter().position(Option::is_some).unwrap(),
+        2 => mids.iter().position(Option::is_none).map(|i| (i + 1) % 3).unwrap(),
+        _ => 0,
+    };
+    let [a, b, c] = [0, 1, 2].map(|i| corners[(i + rotation) % 3]);
+    let [ab, bc, ca] = [0, 1, 2].map(|i| mids[(i + rotation) % 3]);
+
+    match (ab, bc, ca) {
+        (None, None, None) => out.extend([a, b, c]),
+        (Some(ab), None, None) => out.extend([a, ab, c, ab, b, c]),
+        (Some(ab), Some(bc), None) => out.extend([a, ab, c, ab, bc, c, ab, b, bc]),
+        (Some(ab), Some(bc), Some(ca)) => out.extend([a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]),
+        _ => unreachable!("the split edges were rotated to the front"),
+    }
+}
+
+/// A stereographic projection of the unit sphere onto the plane through its centre.
+struct Stereographic {
+    /// The point the sphere is projected from, which maps to infinity.
+    pole: DVec3,
+    e1: DVec3,
+    e2: DVec3,
+}
+
+impl Stereographic {
+    fn new(pole: DVec3) -> Self {
+        let e1 = pole.any_orthonormal_vector();
+        Self { pole, e1, e2: pole.cross(e1) }
+    }
+
+    fn forward(&self, p: DVec3) -> DVec2 {
+        let scale = 1.0 / (1.0 - p.dot(self.pole)).max(f64::EPSILON);
+        DVec2::new(p.dot(self.e1), p.dot(self.e2)) * scale
+    }
+
+    fn inverse(&self, q: DVec2) -> DVec3 {
+        let r2 = q.length_squared();
+        (2.0 * q.x * self.e1 + 2.0 * q.y * self.e2 + (r2 - 1.0) * self.pole) / (r2 + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::LatLon;
+
+    fn ring(coords: &[(f64, f64)]) -> Vec<DVec3> {
+        coords.iter().map(|&(lat, lon)| LatLon::new(lat, lon).to_unit_vector()).collect()
+    }
+
+    /// Returns a ring along a parallel, going east or west.
+    fn parallel(lat: f64, eastward: bool) -> Vec<DVec3> {
+        let coords: Vec<_> = (0..36).map(|i| (lat, if eastward { 10.0 } else { -10.0 } * i as f64)).collect();
+        ring(&coords)
+    }
+
+    /// Sums up the areas of triangles, checking that each of them is counter-clockwise.
+    fn triangles_area((vertices, indices): &(Vec<DVec3>, Vec<u32>)) -> f64 {
+        indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let area = enclosed_area(&triangle.iter().map(|&i| vertices[i as usize]).collect::<Vec<_>>());
+                assert!(area < 2.0 * PI, "the triangle {triangle:?} is clockwise");
+                area
+            })
+            .sum()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3 * expected.max(1e-3), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn measures_the_area_left_of_a_ring() {
+        // A cap above 60°N covers 2π(1 - sin 60°) steradians. The great circles between the points
+        // of the ring bulge towards the pole, so the polygon is a bit smaller.
+        let cap = enclosed_area(&parallel(60.0, true));
+        let expected = 2.0 * PI * (1.0 - 60f64.to_radians().sin());
+        assert!(cap < expected && cap > 0.99 * expected, "{cap}");
+        assert_close(enclosed_area(&parallel(60.0, false)), 4.0 * PI - cap);
+    }
+
+    #[test]
+    fn triangulates_a_polar_cap_with_a_hole() {
+        let exterior = parallel(60.0, true);
+        let hole = parallel(80.0, false);
+        let triangles = triangulate(&exterior, std::slice::from_ref(&hole), 0.05);
+
+        let expected = enclosed_area(&exterior) - (4.0 * PI - enclosed_area(&hole));
+        assert_close(triangles_area(&triangles), expected);
+        // Nothing is left over the pole, inside the hole.
+        for p in &triangles.0 {
+            let lat = LatLon::from_unit_vector(*p).lat;
+            assert!((59.9..80.1).contains(&lat), "{lat}");
+        }
+    }
+
+    #[test]
+    fn triangulates_across_the_antimeridian() {
+        let square = ring(&[(-10.0, 170.0), (-10.0, -170.0), (10.0, -170.0), (10.0, 170.0)]);
+        let triangles = triangulate(&square, &[], 0.05);
+
+        assert!(!triangles.1.is_empty());
+        assert_close(triangles_area(&triangles), enclosed_area(&square));
+        assert!(enclosed_area(&square) < 0.5);
+        for p in &triangles.0 {
+            assert!(LatLon::from_unit_vector(*p).lon.abs() > 169.9);
+        }
+    }
+
+    #[test]
+    fn clockwise_rings_enclose_the_rest_of_the_sphere() {
+        let square = ring(&[(0.0, 0.0), (0.0, 20.0), (20.0, 20.0), (20.0, 0.0)]);
+        let mut reversed = square.clone();
+        reversed.reverse();
+
+        let small = triangles_area(&triangulate(&square, &[], 0.1));
+        let large = triangles_area(&triangulate(&reversed, &[], 0.1));
+        assert_close(small, enclosed_area(&square));
+        assert_close(large, enclosed_area(&reversed));
+        assert_close(small + large, 4.0 * PI);
+    }
+}
@@ -0,0 +1,104 @@
+use glam::DVec3;
+
+use super::{angle_between, LatLon};
+
+/// Simplifies a line on the sphere with the Ramer-Douglas-Peucker algorithm.
+///
+/// Distances are measured as great-circle angles, so the result does not depend on where on the
+/// globe the line was drawn.
+///
+/// Arguments:
+///
+/// * `points`: The line to simplify.
+/// * `tolerance`: The largest allowed deviation from the original line in radians.
+pub fn simplify(points: &[LatLon], tolerance: f64) -> Vec<LatLon> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let vectors: Vec<DVec3> = points.iter().map(|p| p.to_unit_vector()).collect();
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_distance = 0.0;
+        let mut max_index = first;
+        for (i, v) in vectors.iter().enumerate().take(last).skip(first + 1) {
+            let distance = distance_to_arc(*v, vectors[first], vectors[last]);
+            if distance > max_distance {
+                max_distance = distance;
+                max_index = i;
+            }
+        }
+
+        if max_distance > tolerance {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect()
+}
+
+/// The angular distance in radians between a point and the shorter great-circle arc from `a` to `b`.
+///
+/// All vectors are expected to be normalised.
+pub fn distance_to_arc(p: DVec3, a: DVec3, b: DVec3) -> f64 {
+    let normal = a.cross(b);
+    if normal.length_squared() < 1e-24 {
+        // The arc is degenerate, so it is just a point.
+        return angle_between(p, a);
+    }
+    let normal = normal.normalize();
+
+    // The closest point on the full great circle, which only counts if it lies on the arc.
+    let projected = p - normal * p.dot(normal);
+    if projected.length_squared() > 1e-24 {
+        let projected = projected.normalize();
+        if a.cross(projected).dot(normal) >= 0.0 && projected.cross(b).dot(normal) >= 0.0 {
+            return p.dot(normal).clamp(-1.0, 1.0).asin().abs();
+        }
+    }
+
+    angle_between(p, a).min(angle_between(p, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_points_along_a_great_circle() {
+        // The equator and a meridian are both great circles.
+        let equator: Vec<_> = (0..=20).map(|i| LatLon::new(0.0, i as f64 * 5.0 - 50.0)).collect();
+        assert_eq!(simplify(&equator, 1e-6), vec![equator[0], equator[20]]);
+
+        let meridian: Vec<_> = (0..=16).map(|i| LatLon::new(i as f64 * 10.0 - 80.0, 30.0)).collect();
+        assert_eq!(simplify(&meridian, 1e-6), vec![meridian[0], meridian[16]]);
+    }
+
+    #[test]
+    fn keeps_vertices_past_the_tolerance() {
+        let line = [LatLon::new(0.0, 0.0), LatLon::new(0.5, 10.0), LatLon::new(2.0, 20.0), LatLon::new(0.0, 40.0)];
+        let tolerance = 1f64.to_radians();
+        assert_eq!(simplify(&line, tolerance), vec![line[0], line[2], line[3]]);
+        assert_eq!(simplify(&line, 3f64.to_radians()), vec![line[0], line[3]]);
+    }
+
+    #[test]
+    fn measures_distances_to_the_arc_ends() {
+        let (a, b) = (LatLon::new(0.0, 0.0).to_unit_vector(), LatLon::new(0.0, 10.0).to_unit_vector());
+        let beside = LatLon::new(5.0, 5.0).to_unit_vector();
+        assert!((distance_to_arc(beside, a, b) - 5f64.to_radians()).abs() < 1e-9);
+        // Past the end of the arc, the nearest point is its end.
+        let beyond = LatLon::new(0.0, 30.0).to_unit_vector();
+        assert!((distance_to_arc(beyond, a, b) - 20f64.to_radians()).abs() < 1e-9);
+    }
+}
@@ -1,183 +1,35 @@
-use std::{borrow::Cow, sync::Arc};
-
-use glam::Vec3;
-use wgpu::{util::DeviceExt, Adapter, BindGroup, BindGroupLayout, Buffer, Color, CommandEncoderDescriptor, Device, DeviceDescriptor, Features, FragmentState, Instance, Limits, LoadOp, MemoryHints, Operations, PipelineLayout, PipelineLayoutDescriptor, PowerPreference, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterOptions, ShaderModuleDescriptor, ShaderSource, StoreOp, Surface, SurfaceConfiguration, TextureFormat, TextureView, TextureViewDescriptor, Trace, VertexState};
-use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::DeviceEvent, event_loop::EventLoopProxy, window::Window};
-
-use crate::{camera::{controller::CameraController, CameraUniform, OrbitCamera}, light::LightUniform, texture::Texture, vertex::Vertex};
-
-/// The number of samples taken when using multisample anti-aliasing.
-/// Valid values are `1` (no MSAA) or `4`.
-// #[cfg(feature = "msaa")]
-// const MSAA_SAMPLE_COUNT: u32 = 4;
-// #[cfg(not(feature = "msaa"))]
-const MSAA_SAMPLE_COUNT: u32 = 1;
-
-
-
-fn create_camera(size: PhysicalSize<u32>) -> (OrbitCamera, CameraController, CameraUniform) {
-    let mut camera = OrbitCamera::new(
-        15.0,
-        0.0,
-        0.0,
-        Vec3::new(0.0, 0.0, 0.0),
-        size.width as f32 / size.height as f32,
-    );
-    camera.bounds.min_distance = Some(10.0);
-    let camera_controller = CameraController::new(0.002, 0.5);
-
-    let mut camera_uniform = CameraUniform::default();
-    camera_uniform.update_view_proj(&camera);
-
-    (camera, camera_controller, camera_uniform)
-}
-
-fn create_camera_bind_group(device: &Device, camera_uniform: &CameraUniform) -> (BindGroup, BindGroupLayout, Buffer) {
-    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Camera Buffer"),
-        contents: bytemuck::cast_slice(&[*camera_uniform]),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-
-
-    let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
-
-    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &camera_bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: camera_buffer.as_entire_binding(),
-        }],
-        label: Some("camera_bind_group"),
-    });
-
-    (camera_bind_group, camera_bind_group_layout, camera_buffer)
-
-}
-
-fn create_light(device: &Device) -> (BindGroup, BindGroupLayout, Buffer, LightUniform) {
-    let light_uniform = LightUniform {
-        position: [2.0, 6.0, 4.0, 1.0],
-        color: [1.0, 1.0, 1.0, 0.1],
-    };
-    let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Light VB"),
-        contents: bytemuck::cast_slice(&[light_uniform]),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-    let light_bind_group_layout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: None,
-        }
-    );
-    let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &light_bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: light_buffer.as_entire_binding(),
-        }],
-        label: None,
-    });
-
-    (light_bind_group, light_bind_group_layout, light_buffer, light_uniform)
-}
-
-fn create_texture(device: &Device, queue: &Queue, surface_config: &SurfaceConfiguration) -> (TextureView, BindGroup, BindGroupLayout, Texture) {
-    // let diffuse_bytes = include_bytes!("assets/test.png");
-    let diffuse_texture = Texture::from_bytes(&device, &queue, &[], "grid.png").unwrap();
-
-    let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
-
-    let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &texture_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-            },
-        ],
-        label: Some("diffuse_bind_group"),
-    });
-
-    let depth_texture_view = Texture::create_depth_texture(
-        &device,
-        &surface_config,
-        MSAA_SAMPLE_COUNT,
-        "depth_texture",
-    );
-
-    (depth_texture_view, diffuse_bind_group, texture_bind_group_layout, diffuse_texture)
-
-}
-
-fn create_verts(device: &Device) -> (Buffer, Buffer, u32) {
-    let (vertices, indices) = crate::sphere::get_sphere_vertices(10.0);
-
-    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Vertex Buffer"),
-        contents: bytemuck::cast_slice(&vertices),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Index Buffer"),
-        contents: bytemuck::cast_slice(&indices),
-        usage: wgpu::BufferUsages::INDEX,
-    });
-    let num_indices = indices.len() as u32;
-
-    (vertex_buffer, index_buffer, num_indices)
-}
-
-pub async fn create_graphics(window: Arc<Window>, proxy: EventLoopProxy<Graphics>) {
+use std::{io, path::{Path, PathBuf}, sync::Arc};
+
+use glam::Vec2;
+use wgpu::{Instance, PowerPreference, RequestAdapterOptions, Surface, SurfaceConfiguration, TextureViewDescriptor};
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::{DeviceEvent, ElementState, KeyEvent, WindowEvent}, event_loop::EventLoopProxy, keyboard::{Key, NamedKey}, window::Window};
+
+use crate::{camera::controller::CameraController, document::{history::{Command, History}, Document, Geometry}, format::{geojson::{self, ExportOptions, GeoJsonError, ImportIssue}, svg}, geo::LatLon, project::{Project, ProjectError}, renderer::{request_device, Renderer}, texture::Texture, tiles::TileError, tool::{EditTool, FreehandTool, PathTool, PolygonTool, ToolKind}, ui::{LayerAction, Panels, Selection, Ui}};
+
+/// How many pixels the cursor may be away from a point on the globe to snap to it.
+const SNAP_DISTANCE: f64 = 8.0;
+
+/// The number of edits that can be undone unless another depth is given.
+const HISTORY_DEPTH: usize = 256;
+
+/// Creates the [Graphics] of a window and sends them to the event loop.
+///
+/// Arguments:
+///
+/// * `window`: The window that is drawn into.
+/// * `proxy`: The proxy of the event loop the graphics are sent to.
+/// * `texture_path`: The image or tile pyramid wrapped around the globe, or `None` for the
+///   built-in texture. If it cannot be loaded, the built-in texture is used instead.
+/// * `sample_count`: The number of samples per pixel used for anti-aliasing, or `None` for the
+///   default. If the adapter does not support it, the default is used instead.
+/// * `history_depth`: The number of edits that can be undone, or `None` for the default.
+pub async fn create_graphics(
+    window: Arc<Window>,
+    proxy: EventLoopProxy<Graphics>,
+    mut texture_path: Option<PathBuf>,
+    sample_count: Option<u32>,
+    history_depth: Option<usize>,
+) {
     let instance = Instance::default();
     let surface = instance.create_surface(Arc::clone(&window)).unwrap();
     let adapter = instance
@@ -189,19 +41,7 @@ pub async fn create_graphics(window: Arc<Window>, proxy: EventLoopProxy<Graphics
         .await
         .expect("Could not get an adapter (GPU).");
 
-    let (device, queue) = adapter
-        .request_device(
-            &DeviceDescriptor {
-                label: None,
-                required_features: Features::empty(), // Specifies the required features by the device request. Fails if the adapter can't provide them.
-                required_limits: Limits::downlevel_webgl2_defaults()
-                    .using_resolution(adapter.limits()),
-                memory_hints: MemoryHints::Performance,
-                trace: Trace::Off
-            }
-        )
-        .await
-        .expect("Failed to get device");
+    let (device, queue) = request_device(&adapter).await.expect("Failed to get device");
 
     // Get physical pixel dimensiosn inside the window
     let size = window.inner_size();
@@ -212,153 +52,70 @@ pub async fn create_graphics(window: Arc<Window>, proxy: EventLoopProxy<Graphics
     let surface_config = surface.get_default_config(&adapter, width, height).unwrap();
     surface.configure(&device, &surface_config);
 
-    // Get textures
-    let (depth_texture_view, diffuse_bind_group, texture_bind_group_layout, diffuse_texture) = create_texture(&device, &queue, &surface_config);
-
-    // Get camera
-    let (camera, camera_controller, camera_uniform) = create_camera(size);
-    let (camera_bind_group,camera_bind_group_layout, camera_buffer) = create_camera_bind_group(&device, &camera_uniform);
-
-    // Get light
-    let (light_bind_group, light_bind_group_layout, light_buffer, light_uniform) = create_light(&device);
-
-
-    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[
-            &texture_bind_group_layout,
-            &camera_bind_group_layout,
-            &light_bind_group_layout,
-        ],
-        push_constant_ranges: &[],
-    });
-
-    let multisampled_framebuffer = Texture::create_multisampled_framebuffer(
-        &device,
-        &surface_config,
-        MSAA_SAMPLE_COUNT,
-        "multisampled_framebuffer",
-    );
-
-    let render_pipeline = create_pipeline(&device, render_pipeline_layout,surface_config.format);
-
-    let (vertex_buffer, index_buffer, num_indices) = create_verts(&device);
+    let ui = Ui::new(&window, &device, surface_config.format);
+    // The built-in texture is only decoded if no other texture is given or it cannot be loaded.
+    let placeholder = Texture::placeholder(&device, &queue);
+    let mut renderer = Renderer::new(&adapter, device, queue, surface_config.format, width, height, &placeholder);
+    if let Some(sample_count) = sample_count
+        && let Err(err) = renderer.set_sample_count(sample_count)
+    {
+        log::error!("Failed to change the anti-aliasing: {err}");
+    }
+    if let Some(path) = &texture_path
+        && let Err(err) = renderer.load_texture(Some(path))
+    {
+        log::error!("Failed to load the texture {}: {err}", path.display());
+        texture_path = None;
+    }
+    if texture_path.is_none() {
+        renderer.load_texture(None).expect("The built-in texture is invalid");
+    }
 
     let gfx = Graphics {
         window: window.clone(),
-        instance,
         cursor_pos: PhysicalPosition::new(0.0, 0.0),
         surface,
         surface_config,
-        adapter,
-        device,
-        queue,
-        render_pipeline,
-
-        vertex_buffer,
-        index_buffer,
-        num_indices,
-
-        multisampled_framebuffer,
-        depth_texture_view,
-        diffuse_texture,
-        diffuse_bind_group,
-
-        camera,
-        camera_buffer,
-        camera_bind_group,
-        camera_controller,
-        camera_uniform,
-
-        light_uniform,
-        light_buffer,
-        light_bind_group,
+        renderer,
+        ui,
+        camera_controller: CameraController::new(0.002, 0.5),
+
+        texture_path,
+        document: Document::new(),
+        history: History::new(history_depth.unwrap_or(HISTORY_DEPTH)),
+        active_tool: ToolKind::default(),
+        freehand: FreehandTool::default(),
+        path_tool: PathTool::default(),
+        polygon_tool: PolygonTool::default(),
+        edit_tool: EditTool::default(),
     };
 
     let _ = proxy.send_event(gfx);
 }
 
-
-fn create_pipeline(device: &Device, layout: PipelineLayout, swap_chain_format: TextureFormat) -> RenderPipeline {
-    let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("Shader"),
-        source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("assets/shader.wgsl"))),
-    });
-
-    device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&layout),
-        vertex: VertexState {
-            module: &shader,
-            entry_point: Some("vs_main"),
-            buffers: &[Vertex::desc()],
-            compilation_options: Default::default(),
-        },
-        fragment: Some(FragmentState {
-            module: &shader,
-            entry_point: Some("fs_main"),
-            targets: &[Some(swap_chain_format.into())],
-            compilation_options: Default::default(),
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            // Requires Features::NON_FILL_POLYGON_MODE
-            polygon_mode: wgpu::PolygonMode::Fill,
-            // Requires Features::DEPTH_CLIP_CONTROL
-            unclipped_depth: false,
-            // Requires Features::CONSERVATIVE_RASTERIZATION
-            conservative: false,
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: MSAA_SAMPLE_COUNT,
-            ..Default::default()
-        },
-        multiview: None,
-        cache: None,
-    })
-}
-
-
 #[derive(Debug)]
 pub struct Graphics {
     window: Arc<Window>,
-    instance: Instance,
     cursor_pos: PhysicalPosition<f32>,
     surface: Surface<'static>,
     surface_config: SurfaceConfiguration,
-    adapter: Adapter,
-    device: Device,
-    queue: Queue,
-    render_pipeline: RenderPipeline,
-    // Triangles
-    vertex_buffer: Buffer,
-    index_buffer: Buffer,
-    num_indices: u32,
-    // Texture Stuff
-    multisampled_framebuffer: TextureView,
-    depth_texture_view: TextureView,
-    diffuse_texture: Texture,
-    diffuse_bind_group: BindGroup,
-    // The camera used for rendering the scene.
-    camera: OrbitCamera,
-    camera_buffer: Buffer,
-    camera_bind_group: BindGroup,
+    // Draws the globe or the flat map into the surface.
+    renderer: Renderer,
+    // The panels drawn on top of the globe.
+    ui: Ui,
     pub camera_controller: CameraController,
-    camera_uniform: CameraUniform,
-    // Lighting Stuff
-    light_uniform: LightUniform,
-    light_buffer: Buffer,
-    light_bind_group: BindGroup,
+    // The image wrapped around the globe, or `None` for the built-in texture.
+    texture_path: Option<PathBuf>,
+    // The vector features drawn onto the globe.
+    pub document: Document,
+    // The undo and redo stacks of the document.
+    pub history: History,
+    // Tools
+    active_tool: ToolKind,
+    freehand: FreehandTool,
+    path_tool: PathTool,
+    polygon_tool: PolygonTool,
+    edit_tool: EditTool,
 }
 
 impl Graphics {
@@ -366,106 +123,389 @@ impl Graphics {
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.surface_config.width = new_size.width.max(1);
         self.surface_config.height = new_size.height.max(1);
-
-        self.depth_texture_view = Texture::create_depth_texture(
-            &self.device,
-            &self.surface_config,
-            MSAA_SAMPLE_COUNT,
-            "depth_texture",
-        );
-        self.multisampled_framebuffer = Texture::create_multisampled_framebuffer(
-            &self.device,
-            &self.surface_config,
-            MSAA_SAMPLE_COUNT,
-            "multisampled_framebuffer",
-        );
-
-        self.surface.configure(&self.device, &self.surface_config);
-
-        self.camera.aspect = self.surface_config.width as f32 / self.surface_config.height as f32;
+        self.surface.configure(self.renderer.device(), &self.surface_config);
+        self.renderer.resize(self.surface_config.width, self.surface_config.height);
     }
 
     /// Updates the state.
     pub fn update(&mut self) {
-        self.camera_uniform.update_view_proj(&self.camera);
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
-
-        // Update the light so that it is transformed with the camera
-        self.light_uniform.position = [
-            self.camera_uniform.view_position[0],
-            self.camera_uniform.view_position[1],
-            self.camera_uniform.view_position[2],
-            1.0,
-        ];
-        self.queue.write_buffer(
-            &self.light_buffer,
-            0,
-            bytemuck::cast_slice(&[self.light_uniform]),
-        );
+        let preview = match self.active_tool {
+            ToolKind::Freehand => self.freehand.stroke().map(<[LatLon]>::to_vec),
+            ToolKind::Path => self.path_tool.path().map(|path| path.flatten(self.renderer.segment_angle())),
+            ToolKind::Polygon => self.polygon_tool.outline(),
+            ToolKind::Edit => None,
+        };
+        self.renderer.update(&self.document, preview.as_deref());
     }
 
     pub fn update_cursor_position(&mut self, pos: PhysicalPosition<f64>) {
         self.cursor_pos = pos.cast();
-        println!("{:?}", self.cursor_pos);
+        log::trace!("cursor {:?} over {:?}", self.cursor_pos, self.pick(self.cursor_pos));
+
+        if let Some(point) = self.cursor_lat_lon() {
+            match self.active_tool {
+                ToolKind::Freehand => {
+                    self.freehand.extend(point, self.pixel_angle());
+                },
+                ToolKind::Path => self.path_tool.drag(point),
+                ToolKind::Polygon => {},
+                ToolKind::Edit => self.drag_vertex(point),
+            }
+        }
+    }
+
+    /// Moves the vertex grabbed by the edit tool, merging all moves of one drag into a single undo step.
+    fn drag_vertex(&mut self, point: LatLon) {
+        let Some(vertex) = self.edit_tool.dragging() else {
+            return;
+        };
+        let Some(feature) = self.document.get(vertex.feature) else {
+            return;
+        };
+
+        let before = feature.geometry.clone();
+        let mut after = before.clone();
+        after.set_vertex(vertex.index, point);
+        self.history.execute(&mut self.document, Command::SetGeometry { id: vertex.feature, before, after }, true);
+        self.window.request_redraw();
+    }
+
+    /// The approximate angle in radians one pixel covers at the centre of the visible globe or map.
+    fn pixel_angle(&self) -> f64 {
+        self.renderer.pixel_angle()
+    }
+
+    /// Returns the coordinate on the globe or map under a window position, if it is hit.
+    ///
+    /// Arguments:
+    ///
+    /// * `pos`: The position in physical pixels, relative to the top left corner of the window.
+    pub fn pick(&self, pos: PhysicalPosition<f32>) -> Option<LatLon> {
+        self.renderer.pick(Vec2::new(pos.x, pos.y))
+    }
+
+    /// Returns the coordinate on the globe under the cursor, if the globe is hit.
+    pub fn cursor_lat_lon(&self) -> Option<LatLon> {
+        self.pick(self.cursor_pos)
     }
 
     /// Renders the scene based on the [State].
     pub fn draw(&mut self) {
         let frame = self.surface.get_current_texture().expect("Failed to acquire next swap chain texture.");
         let view = frame.texture.create_view(&TextureViewDescriptor::default());
+        self.renderer.render(&view);
+        let mut panels = Panels {
+            tool: self.active_tool,
+            layers: self.document.layers().cloned().collect(),
+            active_layer: self.document.active_layer().id(),
+            layer_action: None,
+            selection: self.edit_tool.selected().and_then(|id| self.document.get(id)).map(|feature| Selection {
+                feature: feature.id(),
+                style: self.document.style_of(feature).clone(),
+                own: feature.style.is_some(),
+            }),
+            style_change: None,
+            graticule: self.renderer.graticule,
+            graticule_labels: self.renderer.graticule_labels(),
+        };
+        self.ui.draw(&self.window, self.renderer.device(), self.renderer.queue(), &view, &mut panels);
+        frame.present();
+
+        if panels.tool != self.active_tool {
+            self.set_tool(panels.tool);
+            self.window.request_redraw();
+        }
+        if let Some(action) = panels.layer_action {
+            self.apply_layer_action(action);
+        }
+        if let Some(change) = panels.style_change {
+            self.history.set_style(&mut self.document, change.feature, change.style, change.merge);
+            self.window.request_redraw();
+        }
+        if panels.graticule != self.renderer.graticule {
+            self.renderer.graticule = panels.graticule;
+            self.window.request_redraw();
+        }
+
+        // Keep drawing until the tiles of the texture that are visible have been read.
+        if self.renderer.is_loading() {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Forwards a window event to the panels.
+    ///
+    /// Returns `true` if the panels used the event, so that it must not be handled as a shortcut.
+    pub fn process_ui_event(&mut self, event: &WindowEvent) -> bool {
+        self.ui.on_window_event(&self.window, event)
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Encoder") });
+    pub fn process_camera_event(&mut self,  event: &DeviceEvent) {
+        // Presses over a panel belong to it, but releases still end drags started on the globe.
+        let pressed = matches!(event, DeviceEvent::Button { state: ElementState::Pressed, .. } | DeviceEvent::MouseWheel { .. });
+        if pressed && self.ui.wants_pointer() {
+            return;
+        }
 
-        let rpass_view = if MSAA_SAMPLE_COUNT == 1 {
-            &view
+        if self.renderer.is_map_view() {
+            self.camera_controller.process_map_events(event, &self.window, &mut self.renderer.map_camera);
         } else {
-            &self.multisampled_framebuffer
-        };
+            self.camera_controller.process_events(event, &self.window, &mut self.renderer.camera);
+        }
 
-        {
-            let mut r_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: rpass_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
-                        store: StoreOp::Store,
+        if let DeviceEvent::Button { button: 1, state } = event {
+            self.process_tool_button(*state);
+        }
+    }
+
+    /// Forwards a press or release of the left mouse button to the active tool.
+    fn process_tool_button(&mut self, state: ElementState) {
+        // The edit tool only grabs the features of editable layers, see [Document::editable_features].
+        let layer = self.document.active_layer();
+        if state == ElementState::Pressed && self.active_tool != ToolKind::Edit && !layer.is_editable() {
+            log::warn!("Cannot draw on the layer {}, since it is hidden or locked", layer.name);
+            return;
+        }
+        let point = self.cursor_lat_lon();
+        match (self.active_tool, state) {
+            (ToolKind::Freehand, ElementState::Pressed) => {
+                if let Some(start) = point {
+                    self.freehand.begin(start);
+                }
+            },
+            (ToolKind::Freehand, ElementState::Released) => {
+                // Simplify to about the precision of the mouse on screen
+                if let Some(stroke) = self.freehand.finish(1.5 * self.pixel_angle()) {
+                    self.history.add_feature(&mut self.document, Geometry::Polyline(stroke));
+                }
+            },
+            (ToolKind::Path, ElementState::Pressed) => {
+                if let Some(point) = point
+                    && let Some(path) = self.path_tool.press(point, SNAP_DISTANCE * self.pixel_angle())
+                {
+                    self.history.add_feature(&mut self.document, Geometry::Path(path));
+                }
+            },
+            (ToolKind::Path, ElementState::Released) => self.path_tool.release(),
+            (ToolKind::Polygon, ElementState::Pressed) => {
+                if let Some(point) = point
+                    && let Some(polygon) = self.polygon_tool.press(point, SNAP_DISTANCE * self.pixel_angle())
+                {
+                    self.history.add_feature(&mut self.document, Geometry::Polygon(polygon));
+                }
+            },
+            (ToolKind::Polygon, ElementState::Released) => {},
+            (ToolKind::Edit, ElementState::Pressed) => {
+                self.history.seal();
+                match point {
+                    Some(point) => {
+                        self.edit_tool.press(&self.document, point, SNAP_DISTANCE * self.pixel_angle());
                     },
-                })],
-                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture_view,
-                    depth_ops: Some(Operations {
-                        load: LoadOp::Clear(1.0),
-                        store: StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            r_pass.set_pipeline(&self.render_pipeline);
-            r_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            r_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            r_pass.set_bind_group(2, &self.light_bind_group, &[]);
-
-            r_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            r_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            r_pass.draw_indexed(0..self.num_indices, 0, 0..1);
-
-        } // `r_pass` dropped here
-
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
+                    None => self.edit_tool.deselect(),
+                }
+            },
+            (ToolKind::Edit, ElementState::Released) => {
+                self.edit_tool.release();
+                self.history.seal();
+            },
+        }
+        self.window.request_redraw();
     }
 
-    pub fn process_camera_event(&mut self,  event: &DeviceEvent) {
-        self.camera_controller.process_events(event, &self.window, &mut self.camera);
+    /// Handles keyboard shortcuts for switching and controlling tools.
+    pub fn process_keyboard_event(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed || event.repeat {
+            return;
+        }
+
+        match event.logical_key.as_ref() {
+            Key::Character("f" | "F") => self.set_tool(ToolKind::Freehand),
+            Key::Character("p" | "P") => self.set_tool(ToolKind::Path),
+            Key::Character("a" | "A") => self.set_tool(ToolKind::Polygon),
+            Key::Character("e" | "E") => self.set_tool(ToolKind::Edit),
+            Key::Character("m" | "M") => self.toggle_map_view(),
+            Key::Character("n" | "N") => self.next_projection(),
+            Key::Character("g" | "G") => self.next_sphere_mesh(),
+            Key::Character("x" | "X") => self.next_sample_count(),
+            Key::Named(NamedKey::Enter) => self.finish_shapes(),
+            Key::Named(NamedKey::Escape) => {
+                self.path_tool.cancel();
+                self.polygon_tool.cancel();
+                self.edit_tool.deselect();
+            },
+            Key::Named(NamedKey::Delete | NamedKey::Backspace) => {
+                if let Some(id) = self.edit_tool.selected() {
+                    self.history.remove_feature(&mut self.document, id);
+                    self.edit_tool.deselect();
+                }
+            },
+            _ => return,
+        }
+        self.window.request_redraw();
+    }
+
+    /// Switches between the globe and the flat map, keeping the coordinate in the centre of the view.
+    pub fn toggle_map_view(&mut self) {
+        self.renderer.toggle_map_view();
+        self.window.request_redraw();
+    }
+
+    /// Switches the flat map to the next projection, see [crate::projection::ProjectionKind::next].
+    pub fn next_projection(&mut self) {
+        self.renderer.next_projection();
+        self.window.request_redraw();
+    }
+
+    /// Draws the globe with the next mesh, see [crate::sphere::SphereMesh::next].
+    pub fn next_sphere_mesh(&mut self) {
+        self.renderer.set_sphere_mesh(self.renderer.sphere_mesh().next());
+        self.window.request_redraw();
+    }
+
+    /// Switches to the next number of samples per pixel the adapter supports for anti-aliasing.
+    pub fn next_sample_count(&mut self) {
+        let counts = self.renderer.sample_counts();
+        let index = counts.iter().position(|&count| count == self.renderer.sample_count()).map_or(0, |index| (index + 1) % counts.len());
+        if let Err(err) = self.renderer.set_sample_count(counts[index]) {
+            log::error!("Failed to change the anti-aliasing: {err}");
+        }
+        self.window.request_redraw();
+    }
+
+    /// Switches the tool used with the left mouse button, finishing any unfinished shape.
+    pub fn set_tool(&mut self, tool: ToolKind) {
+        self.finish_shapes();
+        self.active_tool = tool;
+    }
+
+    /// Adds the paths and polygons under construction to the document.
+    fn finish_shapes(&mut self) {
+        if let Some(path) = self.path_tool.finish() {
+            self.history.add_feature(&mut self.document, Geometry::Path(path));
+        }
+        if let Some(polygon) = self.polygon_tool.finish() {
+            self.history.add_feature(&mut self.document, Geometry::Polygon(polygon));
+        }
+    }
+
+    /// Changes the layers of the document as picked in the layer panel.
+    pub fn apply_layer_action(&mut self, action: LayerAction) {
+        // Shapes under construction belong to the layer that was active when they were started.
+        self.finish_shapes();
+        self.edit_tool.deselect();
+        match action {
+            LayerAction::Add => {
+                let name = format!("Layer {}", self.document.layers().count() + 1);
+                self.history.add_layer(&mut self.document, name);
+            },
+            LayerAction::Select(id) => self.document.set_active_layer(id),
+            LayerAction::Set { layer, merge } => self.history.set_layer(&mut self.document, layer, merge),
+            LayerAction::Move(id, to) => {
+                self.history.move_layer(&mut self.document, id, to);
+            },
+            LayerAction::MergeDown(id) => {
+                self.history.merge_layer_down(&mut self.document, id);
+            },
+            LayerAction::Duplicate(id) => {
+                if let Some(copy) = self.history.duplicate_layer(&mut self.document, id) {
+                    self.document.set_active_layer(copy);
+                }
+            },
+            LayerAction::Remove(id) => {
+                self.history.remove_layer(&mut self.document, id);
+            },
+        }
+        self.window.request_redraw();
+    }
+
+    /// Reverts the last edit of the document.
+    pub fn undo(&mut self) {
+        self.finish_shapes();
+        self.edit_tool.release();
+        if self.history.undo(&mut self.document) {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Writes the document and the view to a project file.
+    ///
+    /// Shapes that are still under construction are finished first, so that they are saved too.
+    pub fn save_project(&mut self, path: &Path) -> Result<(), ProjectError> {
+        self.finish_shapes();
+        let mut project = Project::new(self.document.clone(), self.renderer.camera_state(), None);
+        project.set_texture_path(self.texture_path.as_deref(), path);
+        project.save(path)
+    }
+
+    /// Replaces the document and the view with those stored in a project file.
+    ///
+    /// The edit history is cleared, since it refers to the previous document.
+    pub fn open_project(&mut self, path: &Path) -> Result<(), ProjectError> {
+        let project = Project::load(path)?;
+        let texture_path = project.texture_path(path);
+
+        self.freehand.finish(0.0);
+        self.path_tool.cancel();
+        self.polygon_tool.cancel();
+        self.edit_tool.deselect();
+
+        self.document = project.document;
+        self.history.clear();
+        self.renderer.invalidate();
+        self.renderer.set_camera_state(&project.camera);
+        if texture_path != self.texture_path
+            && let Err(err) = self.set_texture(texture_path)
+        {
+            log::error!("Failed to load the texture of the project: {err}");
+        }
+
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Replaces the image wrapped around the globe.
+    ///
+    /// Arguments:
+    ///
+    /// * `path`: The image file or tile pyramid, or `None` for the built-in texture.
+    pub fn set_texture(&mut self, path: Option<PathBuf>) -> Result<(), TileError> {
+        self.renderer.load_texture(path.as_deref())?;
+        self.texture_path = path;
+        self.window.request_redraw();
+        Ok(())
+    }
+
+    /// Adds the features of a GeoJSON file to the document as a single undoable step.
+    ///
+    /// Returns the problems found in the file, whose affected features were skipped or repaired.
+    pub fn import_geojson(&mut self, path: &Path) -> Result<Vec<ImportIssue>, GeoJsonError> {
+        let import = geojson::import_file(path)?;
+        self.finish_shapes();
+        self.history.add_features(&mut self.document, import.features);
+        self.window.request_redraw();
+        Ok(import.issues)
+    }
+
+    /// Writes the document to a GeoJSON file, see [geojson::export].
+    pub fn export_geojson(&mut self, path: &Path, options: ExportOptions) -> Result<(), GeoJsonError> {
+        self.finish_shapes();
+        geojson::export_file(&self.document, options, path)
+    }
+
+    /// Writes the document to an SVG file in the projection picked for the flat map, see [svg::export].
+    ///
+    /// While the flat map is shown, the part of it that is visible is exported. Otherwise the whole
+    /// map is exported, centred on the coordinate in the centre of the globe.
+    pub fn export_svg(&mut self, path: &Path) -> io::Result<()> {
+        self.finish_shapes();
+        svg::export_file(&self.document, self.renderer.svg_options(), path)
+    }
+
+    /// Applies the last undone edit of the document again.
+    pub fn redo(&mut self) {
+        self.edit_tool.release();
+        if self.history.redo(&mut self.document) {
+            self.window.request_redraw();
+        }
     }
 }
\ No newline at end of file
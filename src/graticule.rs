@@ -0,0 +1,381 @@
+use crate::{document::{LineCap, LineJoin, Stroke, StrokeWidth}, geo::{wrap_longitude, LatLon}};
+
+/// The spacings in degrees the lines of a [Graticule] can have, from the widest to the finest.
+///
+/// Each of them divides `90` evenly, so the equator and the poles always fall on the grid.
+pub const SPACINGS: [f64; 12] = [30.0, 15.0, 10.0, 5.0, 2.0, 1.0, 0.5, 0.2, 0.1, 0.05, 0.02, 0.01];
+
+/// The latitude of the tropics in degrees, which is the tilt of the axis of the earth.
+pub const TROPIC_LATITUDE: f64 = 23.44;
+
+/// The latitude of the polar circles in degrees.
+pub const POLAR_CIRCLE_LATITUDE: f64 = 90.0 - TROPIC_LATITUDE;
+
+/// The number of lines an adaptive spacing aims for across the height of the view.
+const TARGET_LINES: f64 = 8.0;
+
+/// The most lines a fixed spacing may put across the height of the view before it is widened.
+const MAX_LINES: f64 = 64.0;
+
+/// How far around the centre of the view lines are built, relative to the height of the view.
+const EXTENT_MARGIN: f64 = 1.5;
+
+/// The number of grid cells along the side of the blocks an [Extent] is snapped to.
+const BLOCK_CELLS: f64 = 10.0;
+
+/// The longest step in degrees between the points of a parallel.
+///
+/// Parallels are not great circles, so they are drawn as chords between points close enough
+/// that the difference does not show.
+const PARALLEL_STEP: f64 = 1.0;
+
+/// The longest step in degrees between the points of a meridian, which keeps consecutive points
+/// from being antipodal.
+const MERIDIAN_STEP: f64 = 45.0;
+
+/// The settings of the latitude and longitude grid drawn over the globe and exported maps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Graticule {
+    pub visible: bool,
+    /// Whether the lines are labelled with their latitude or longitude.
+    pub labels: bool,
+    /// The distance between lines in degrees, one of [SPACINGS], or `None` to adapt it to the zoom level.
+    pub spacing: Option<f64>,
+}
+
+impl Default for Graticule {
+    fn default() -> Self {
+        Self { visible: false, labels: true, spacing: None }
+    }
+}
+
+impl Graticule {
+    /// Returns the spacing of the lines in degrees for a view.
+    ///
+    /// A fixed spacing is widened when zoomed out so far that the view would get crowded.
+    ///
+    /// Arguments:
+    ///
+    /// * `view_angle`: The angle in degrees the height of the view covers on the globe.
+    pub fn spacing_for(&self, view_angle: f64) -> f64 {
+        match self.spacing {
+            Some(spacing) => spacing.max(spacing_with_lines(view_angle, MAX_LINES)),
+            None => adaptive_spacing(view_angle),
+        }
+    }
+}
+
+/// Picks a spacing in degrees that puts a few lines across a view, finer the closer it gets.
+///
+/// Arguments:
+///
+/// * `view_angle`: The angle in degrees the height of the view covers on the globe.
+pub fn adaptive_spacing(view_angle: f64) -> f64 {
+    spacing_with_lines(view_angle, TARGET_LINES)
+}
+
+/// Returns the finest of [SPACINGS] that puts no more than `lines` lines across `view_angle` degrees.
+fn spacing_with_lines(view_angle: f64, lines: f64) -> f64 {
+    narrowest_at_least(view_angle / lines)
+}
+
+/// Returns the finest of [SPACINGS] that is not narrower than `angle`.
+fn narrowest_at_least(angle: f64) -> f64 {
+    SPACINGS.iter().rev().copied().find(|&spacing| spacing >= angle - 1e-9).unwrap_or(SPACINGS[0])
+}
+
+/// The part of the globe the lines of a graticule are built for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extent {
+    /// The smallest and largest latitude in degrees.
+    pub lat: (f64, f64),
+    /// The westernmost and easternmost longitude in degrees, which may reach past the
+    /// antimeridian, or `None` for all the way around.
+    pub lon: Option<(f64, f64)>,
+}
+
+impl Extent {
+    /// The whole globe.
+    pub const WORLD: Extent = Extent { lat: (-90.0, 90.0), lon: None };
+
+    /// Returns the part of the globe around the centre of a view that lines are built for.
+    ///
+    /// It reaches beyond what is visible and is snapped to blocks of grid cells, so that panning
+    /// and zooming only change it once in a while.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The coordinate in the centre of the view.
+    /// * `view_angle`: The angle in degrees the height of the view covers on the globe.
+    /// * `spacing`: The spacing of the lines in degrees.
+    pub fn around(center: LatLon, view_angle: f64, spacing: f64) -> Self {
+        let block = spacing * BLOCK_CELLS;
+        let snap_down = |value: f64| (value / block).floor() * block;
+        let snap_up = |value: f64| (value / block).ceil() * block;
+
+        let radius = snap_up(view_angle * EXTENT_MARGIN);
+        let lat = (snap_down(center.lat - radius).max(-90.0), snap_up(center.lat + radius).min(90.0));
+        // Meridians converge towards the poles, so more longitudes are needed to cover the radius.
+        let widest = lat.0.abs().max(lat.1.abs());
+        let lon_radius = radius / widest.to_radians().cos();
+        let lon = (lon_radius < 180.0)
+            .then(|| (snap_down(center.lon - lon_radius), snap_up(center.lon + lon_radius)))
+            .filter(|(min, max)| max - min < 360.0);
+        Self { lat, lon }
+    }
+
+    /// Returns the spacing of the meridians in degrees, which is widened where they converge
+    /// towards a pole.
+    fn meridian_spacing(&self, spacing: f64) -> f64 {
+        let (min, max) = self.lat;
+        let nearest = if min <= 0.0 && max >= 0.0 { 0.0 } else { min.abs().min(max.abs()) };
+        narrowest_at_least(spacing / nearest.to_radians().cos())
+    }
+
+    /// The longitudes covered, as the westernmost and easternmost one.
+    fn lon_range(&self) -> (f64, f64) {
+        self.lon.unwrap_or((-180.0, 180.0))
+    }
+}
+
+/// What a line of a graticule stands for, which decides how it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// A parallel or meridian of the grid.
+    Regular,
+    Equator,
+    /// The Tropic of Cancer or the Tropic of Capricorn.
+    Tropic,
+    /// The Arctic or the Antarctic Circle.
+    PolarCircle,
+    PrimeMeridian,
+}
+
+impl LineKind {
+    /// Returns how lines of this kind are drawn, with the special parallels and the prime
+    /// meridian standing out from the grid.
+    pub fn stroke(self) -> Stroke {
+        let (color, width, dash) = match self {
+            LineKind::Regular => ([0.5, 0.5, 0.5, 0.5], 1.0, vec![]),
+            LineKind::Equator | LineKind::PrimeMeridian => ([0.6, 0.6, 0.6, 0.9], 2.0, vec![]),
+            LineKind::Tropic => ([0.8, 0.45, 0.1, 0.8], 1.5, vec![6.0, 4.0]),
+            LineKind::PolarCircle => ([0.2, 0.5, 0.8, 0.8], 1.5, vec![6.0, 4.0]),
+        };
+        Stroke { color, width: StrokeWidth::Pixels(width), dash, cap: LineCap::Butt, join: LineJoin::Round }
+    }
+}
+
+/// A parallel or meridian of a graticule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraticuleLine {
+    pub kind: LineKind,
+    pub points: Vec<LatLon>,
+    /// Whether the line is a parallel running all the way around the globe, in which case its
+    /// last point connects to the first one.
+    pub closed: bool,
+}
+
+/// Returns the parallels and meridians of a graticule within an extent.
+///
+/// The equator, the tropics, the polar circles and the prime meridian are always part of it
+/// where they cross the extent.
+///
+/// Arguments:
+///
+/// * `spacing`: The spacing of the lines in degrees.
+/// * `extent`: The part of the globe the lines are built for.
+pub fn lines(spacing: f64, extent: &Extent) -> Vec<GraticuleLine> {
+    let (west, east) = extent.lon_range();
+    let closed = extent.lon.is_none();
+    let parallel = |lat: f64, kind: LineKind| {
+        let steps = ((east - west) / PARALLEL_STEP.min(spacing)).ceil().max(1.0) as usize;
+        let count = if closed { steps } else { steps + 1 };
+        let points = (0..count).map(|i| LatLon::new(lat, west + (east - west) * i as f64 / steps as f64)).collect();
+        GraticuleLine { kind, points, closed }
+    };
+
+    let mut lines = vec![];
+    for lat in grid(spacing, extent.lat) {
+        // The poles are points, not lines.
+        if lat.abs() < 90.0 {
+            lines.push(parallel(lat, if lat == 0.0 { LineKind::Equator } else { LineKind::Regular }));
+        }
+    }
+    for (lat, kind) in [
+        (TROPIC_LATITUDE, LineKind::Tropic),
+        (-TROPIC_LATITUDE, LineKind::Tropic),
+        (POLAR_CIRCLE_LATITUDE, LineKind::PolarCircle),
+        (-POLAR_CIRCLE_LATITUDE, LineKind::PolarCircle),
+    ] {
+        if (extent.lat.0..=extent.lat.1).contains(&lat) {
+            lines.push(parallel(lat, kind));
+        }
+    }
+
+    let (south, north) = extent.lat;
+    let steps = ((north - south) / MERIDIAN_STEP).ceil().max(1.0) as usize;
+    for lon in grid(extent.meridian_spacing(spacing), (west, east)) {
+        // The antimeridian is already the first meridian when going all the way around.
+        if closed && lon >= 180.0 {
+            continue;
+        }
+        let points = (0..=steps).map(|i| LatLon::new(south + (north - south) * i as f64 / steps as f64, lon)).collect();
+        let kind = if wrap_longitude(lon) == 0.0 { LineKind::PrimeMeridian } else { LineKind::Regular };
+        lines.push(GraticuleLine { kind, points, closed: false });
+    }
+    lines
+}
+
+/// A label naming the latitude of a parallel or the longitude of a meridian.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// Where the label is centred, on its line.
+    pub position: LatLon,
+    pub text: String,
+}
+
+/// Returns the labels of the lines of a graticule within an extent.
+///
+/// The labels of the parallels are lined up between the two meridians next to the centre of the
+/// view, and the labels of the meridians between the two parallels next to it, so they do not
+/// cover each other.
+///
+/// Arguments:
+///
+/// * `spacing`: The spacing of the lines in degrees.
+/// * `extent`: The part of the globe the lines are built for.
+/// * `center`: The coordinate in the centre of the view.
+pub fn labels(spacing: f64, extent: &Extent, center: LatLon) -> Vec<Label> {
+    let meridian_spacing = extent.meridian_spacing(spacing);
+    let between = |value: f64, spacing: f64| (value / spacing).floor() * spacing + spacing / 2.0;
+    let label_lon = between(center.lon, meridian_spacing);
+    let label_lat = between(center.lat, spacing).clamp(-90.0 + spacing / 2.0, 90.0 - spacing / 2.0);
+
+    let parallels = grid(spacing, extent.lat).filter(|lat| lat.abs() < 90.0).map(|lat| Label {
+        position: LatLon::new(lat, label_lon),
+        text: format_latitude(lat, spacing),
+    });
+    let (west, east) = extent.lon_range();
+    let meridians = grid(meridian_spacing, (west, east))
+        .filter(|&lon| extent.lon.is_some() || lon < 180.0)
+        .map(|lon| Label {
+            position: LatLon::new(label_lat, lon),
+            text: format_longitude(lon, meridian_spacing),
+        });
+    parallels.chain(meridians).collect()
+}
+
+/// Formats a latitude as a label, like `30°N`, with as many decimals as the spacing needs.
+pub fn format_latitude(lat: f64, spacing: f64) -> String {
+    let hemisphere = if lat > 0.0 { "N" } else { "S" };
+    format_degrees(lat, spacing, hemisphere)
+}
+
+/// Formats a longitude as a label, like `45°W`, with as many decimals as the spacing needs.
+pub fn format_longitude(lon: f64, spacing: f64) -> String {
+    let lon = wrap_longitude(lon);
+    if (lon.abs() - 180.0).abs() < 1e-9 {
+        return "180°".to_string();
+    }
+    let hemisphere = if lon > 0.0 { "E" } else { "W" };
+    format_degrees(lon, spacing, hemisphere)
+}
+
+fn format_degrees(value: f64, spacing: f64, hemisphere: &str) -> String {
+    let decimals = (0..4).find(|&d| {
+        let scaled = spacing * 10f64.powi(d);
+        (scaled - scaled.round()).abs() < 1e-6
+    });
+    let text = format!("{:.*}", decimals.unwrap_or(4) as usize, value.abs());
+    if text.trim_start_matches(['0', '.']).is_empty() {
+        return "0°".to_string();
+    }
+    format!("{text}°{hemisphere}")
+}
+
+/// Returns the multiples of `spacing` within a range, rounded so that repeated additions do not
+/// leave them slightly off.
+fn grid(spacing: f64, (min, max): (f64, f64)) -> impl Iterator<Item = f64> {
+    let first = (min / spacing - 1e-9).ceil() as i64;
+    let last = (max / spacing + 1e-9).floor() as i64;
+    (first..=last).map(move |i| {
+        let value = i as f64 * spacing;
+        // Keeps the labels and the kinds of lines from seeing values like `-0.0` or `0.30000000000000004`.
+        let value = (value * 1e6).round() / 1e6;
+        if value == 0.0 { 0.0 } else { value }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(lines: &[GraticuleLine], kind: LineKind) -> usize {
+        lines.iter().filter(|line| line.kind == kind).count()
+    }
+
+    #[test]
+    fn adapts_the_spacing_to_the_view() {
+        assert_eq!(adaptive_spacing(360.0), 30.0);
+        assert_eq!(adaptive_spacing(90.0), 15.0);
+        assert_eq!(adaptive_spacing(8.0), 1.0);
+        assert_eq!(adaptive_spacing(1e-6), 0.01);
+
+        let fixed = Graticule { spacing: Some(0.1), ..Graticule::default() };
+        assert_eq!(fixed.spacing_for(1.0), 0.1);
+        assert_eq!(fixed.spacing_for(180.0), 5.0);
+    }
+
+    #[test]
+    fn emphasises_special_lines() {
+        let lines = lines(30.0, &Extent::WORLD);
+
+        // Parallels at 60°S to 60°N and meridians from 180° to 150°E.
+        assert_eq!(count(&lines, LineKind::Regular), 4 + 11);
+        assert_eq!(count(&lines, LineKind::Equator), 1);
+        assert_eq!(count(&lines, LineKind::PrimeMeridian), 1);
+        assert_eq!(count(&lines, LineKind::Tropic), 2);
+        assert_eq!(count(&lines, LineKind::PolarCircle), 2);
+        let equator = lines.iter().find(|line| line.kind == LineKind::Equator).unwrap();
+        assert!(equator.closed);
+        assert!(equator.points.iter().all(|p| p.lat == 0.0));
+    }
+
+    #[test]
+    fn builds_lines_around_the_view() {
+        let extent = Extent::around(LatLon::new(48.3, 11.6), 4.0, 0.5);
+        assert_eq!(extent.lat, (35.0, 60.0));
+        assert_eq!(extent.lon, Some((-10.0, 35.0)));
+        // Zooming in a little or panning a bit keeps the extent.
+        assert_eq!(Extent::around(LatLon::new(48.0, 12.0), 3.5, 0.5), extent);
+
+        let lines = lines(0.5, &extent);
+        assert_eq!(count(&lines, LineKind::Equator) + count(&lines, LineKind::Tropic), 0);
+        for line in &lines {
+            assert!(!line.closed);
+            assert!(line.points.iter().all(|p| (35.0..=60.0).contains(&p.lat) && (-10.0..=35.0).contains(&p.lon)));
+        }
+
+        // Near the poles, the meridians go all the way around but are spread out.
+        let extent = Extent::around(LatLon::new(88.0, 0.0), 4.0, 0.5);
+        assert_eq!(extent.lon, None);
+        let lines = super::lines(0.5, &extent);
+        assert_eq!(count(&lines, LineKind::Regular) + count(&lines, LineKind::PrimeMeridian), 30 + 360 / 2);
+    }
+
+    #[test]
+    fn formats_labels() {
+        assert_eq!(format_latitude(30.0, 15.0), "30°N");
+        assert_eq!(format_latitude(-0.5, 0.5), "0.5°S");
+        assert_eq!(format_latitude(0.0, 10.0), "0°");
+        assert_eq!(format_longitude(-45.0, 15.0), "45°W");
+        assert_eq!(format_longitude(180.0, 30.0), "180°");
+        assert_eq!(format_longitude(190.0, 10.0), "170°W");
+
+        let labels = labels(30.0, &Extent::WORLD, LatLon::new(10.0, 20.0));
+        let equator = labels.iter().find(|label| label.text == "0°" && label.position.lat == 0.0).unwrap();
+        assert_eq!(equator.position.lon, 15.0);
+        let meridian = labels.iter().find(|label| label.text == "60°E").unwrap();
+        assert_eq!(meridian.position.lat, 15.0);
+    }
+}
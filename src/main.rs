@@ -1,9 +1,23 @@
 mod app;
 mod camera;
+mod cli;
+mod document;
+mod format;
+mod geo;
 mod graphics;
+mod graticule;
 mod light;
+mod mipmap;
+mod project;
+mod projection;
+mod render;
+mod renderer;
+mod screenshot;
 mod sphere;
 mod texture;
+mod tiles;
+mod tool;
+mod ui;
 mod vertex;
 
 use winit::event_loop::{ControlFlow, EventLoop};
@@ -11,12 +25,9 @@ use winit::event_loop::{ControlFlow, EventLoop};
 
 use graphics::Graphics;
 use app::App;
+use cli::Options;
 
 fn run_app(event_loop: EventLoop<Graphics>, mut app: App) {
-    // Allows the setting of the log level through RUST_LOG env var.
-    // It also allows wgpu logs to be seen.
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error")).init();
-
     // Runs the app on the current thread.
     let _ = event_loop.run_app(&mut app);
 }
@@ -24,6 +35,26 @@ fn run_app(event_loop: EventLoop<Graphics>, mut app: App) {
 
 
 fn main() {
+    // Allows the setting of the log level through RUST_LOG env var.
+    // It also allows wgpu logs to be seen.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error")).init();
+
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            log::error!("{err}");
+            std::process::exit(2);
+        },
+    };
+    // Screenshots are rendered without a window, so that they also work on machines without a display.
+    if let Some(screenshot) = &options.screenshot {
+        if let Err(err) = screenshot::take(&options, screenshot) {
+            log::error!("Failed to take a screenshot: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // <T> (T -> AppEvent) extends regular platform specific events (resize, mouse, etc.).
     // This allows our app to inject custom events and handle them alongside regular ones.
     // let event_loop = EventLoop::<()>::new().unwrap();
@@ -38,6 +69,6 @@ fn main() {
     // input, and uses significantly less power/CPU time than ControlFlow::Poll.
     event_loop.set_control_flow(ControlFlow::Wait);
 
-    let app = App::new(&event_loop);
+    let app = App::new(&event_loop, options);
     run_app(event_loop, app);
 }
\ No newline at end of file
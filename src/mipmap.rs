@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+
+use wgpu::{BindGroupLayout, CommandEncoder, Device, FragmentState, PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, ShaderModuleDescriptor, ShaderSource, TextureFormat, VertexState};
+
+/// Returns the number of levels of a full mip chain, down to a single pixel.
+///
+/// Arguments:
+///
+/// * `width`: The width of the largest level in pixels.
+/// * `height`: The height of the largest level in pixels.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Fills the mip chain of a texture on the GPU.
+///
+/// Each level is drawn from the level above it with a linear filter, which averages the four
+/// texels covering each texel of the smaller level. The filter wraps around horizontally, since
+/// the left and right edges of a texture of the globe meet at the antimeridian.
+#[derive(Debug)]
+pub struct MipmapGenerator {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl MipmapGenerator {
+    /// Creates a new [MipmapGenerator].
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `format`: The format of the textures whose mip chains are generated. It has to be renderable.
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("assets/blit.wgsl"))),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("mipmap_bind_group_layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mipmap Pipeline"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Mipmap Pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mipmap_sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self { pipeline, bind_group_layout, sampler }
+    }
+
+    /// Records the commands that draw every mip level of a texture from its first level.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `encoder`: The encoder the commands are recorded into.
+    /// * `texture`: The texture, which needs the `RENDER_ATTACHMENT` and `TEXTURE_BINDING` usages
+    ///   and the format the generator was created for.
+    pub fn generate(&self, device: &Device, encoder: &mut CommandEncoder, texture: &wgpu::Texture) {
+        let views: Vec<_> = (0..texture.mip_level_count())
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("mip"),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        for pair in views.windows(2) {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&pair[0]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: None,
+            });
+
+            let mut r_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pair[1],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            r_pass.set_pipeline(&self.pipeline);
+            r_pass.set_bind_group(0, &bind_group, &[]);
+            r_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mip_chains() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 1), 2);
+        assert_eq!(mip_level_count(8192, 4096), 14);
+        assert_eq!(mip_level_count(5400, 2700), 13);
+        assert_eq!(mip_level_count(0, 0), 1);
+    }
+}
@@ -0,0 +1,397 @@
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{camera::OrbitCamera, document::Document};
+
+/// The file extension of project files.
+pub const EXTENSION: &str = "sitelen";
+
+/// The version of the project format written by this build.
+///
+/// Increment this whenever the meaning of existing fields changes and add a step to [migrate].
+/// Fields that are only added need a `#[serde(default)]` instead, so that older builds can still
+/// read the parts of a newer file they understand.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Everything that is stored in a `.sitelen` project file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Project {
+    /// The version of the format the project was written in.
+    pub version: u32,
+    #[serde(default)]
+    pub document: Document,
+    #[serde(default)]
+    pub camera: CameraState,
+    /// The image wrapped around the globe, or `None` for the built-in texture.
+    #[serde(default)]
+    pub texture: Option<PathBuf>,
+}
+
+/// The part of an [OrbitCamera] that is restored when opening a project.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraState {
+    /// The distance of the eye from the centre of the globe.
+    pub distance: f32,
+    /// The pitch angle in radians.
+    pub pitch: f32,
+    /// The yaw angle in radians.
+    pub yaw: f32,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        Self { distance: 15.0, pitch: 0.0, yaw: 0.0 }
+    }
+}
+
+impl CameraState {
+    /// Captures the state of a camera.
+    pub fn from_camera(camera: &OrbitCamera) -> Self {
+        Self {
+            distance: camera.distance,
+            pitch: camera.pitch,
+            yaw: camera.yaw,
+        }
+    }
+
+    /// Moves a camera to the stored state, respecting its bounds.
+    pub fn apply(&self, camera: &mut OrbitCamera) {
+        camera.set_distance(self.distance);
+        camera.set_pitch(self.pitch);
+        camera.set_yaw(self.yaw);
+    }
+}
+
+/// An error that occurred while saving or opening a project.
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The file does not state which version of the format it uses.
+    MissingVersion,
+    /// The file was written by a newer build and could not be read.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(err) => write!(f, "{err}"),
+            ProjectError::Json(err) => write!(f, "invalid project file: {err}"),
+            ProjectError::MissingVersion => write!(f, "the project file has no version"),
+            ProjectError::UnsupportedVersion(version) => write!(
+                f,
+                "the project file uses version {version} of the format, but only versions up to {FORMAT_VERSION} are supported"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<io::Error> for ProjectError {
+    fn from(err: io::Error) -> Self {
+        ProjectError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProjectError {
+    fn from(err: serde_json::Error) -> Self {
+        ProjectError::Json(err)
+    }
+}
+
+impl Project {
+    /// Creates a new [Project] in the current format version.
+    pub fn new(document: Document, camera: CameraState, texture: Option<PathBuf>) -> Self {
+        Self { version: FORMAT_VERSION, document, camera, texture }
+    }
+
+    /// Serializes the project to JSON.
+    pub fn to_json(&self) -> Result<String, ProjectError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a project from JSON, upgrading files written in older versions of the format.
+    ///
+    /// Files from newer versions are read as far as possible: fields this build does not know
+    /// are ignored. If that fails, [ProjectError::UnsupportedVersion] is returned.
+    pub fn from_json(json: &str) -> Result<Self, ProjectError> {
+        let value: Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(ProjectError::MissingVersion)?;
+        let version = u32::try_from(version).map_err(|_| ProjectError::UnsupportedVersion(u32::MAX))?;
+
+        if version > FORMAT_VERSION {
+            log::warn!("Reading a project written in the newer format version {version}");
+            return serde_json::from_value::<Project>(value)
+                .map(Project::upgraded)
+                .map_err(|_| ProjectError::UnsupportedVersion(version));
+        }
+
+        let project: Project = serde_json::from_value(migrate(value, version))?;
+        Ok(project.upgraded())
+    }
+
+    /// Writes the project to a file.
+    pub fn save(&self, path: &Path) -> Result<(), ProjectError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Reads a project from a file, see [Project::from_json].
+    pub fn load(path: &Path) -> Result<Self, ProjectError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the path of the texture, resolving relative paths against the folder of the project.
+    ///
+    /// Arguments:
+    ///
+    /// * `project_path`: The file the project was loaded from.
+    pub fn texture_path(&self, project_path: &Path) -> Option<PathBuf> {
+        let texture = self.texture.as_ref()?;
+        match project_path.parent() {
+            Some(folder) if texture.is_relative() => Some(folder.join(texture)),
+            _ => Some(texture.clone()),
+        }
+    }
+
+    /// Stores the path of the texture so that [Project::texture_path] finds it again.
+    ///
+    /// Textures inside the folder of the project are stored relative to it, so that both can be
+    /// moved together. Other textures are stored with their absolute path.
+    ///
+    /// Arguments:
+    ///
+    /// * `texture`: The image or tile pyramid, relative to the working directory or absolute.
+    /// * `project_path`: The file the project is saved to.
+    pub fn set_texture_path(&mut self, texture: Option<&Path>, project_path: &Path) {
+        self.texture = texture.map(|texture| {
+            let Ok(texture) = std::path::absolute(texture) else {
+                return texture.to_path_buf();
+            };
+            std::path::absolute(project_path)
+                .ok()
+                .and_then(|path| Some(texture.strip_prefix(path.parent()?).ok()?.to_path_buf()))
+                .unwrap_or(texture)
+        });
+    }
+
+    /// Marks the project as being in the current format, so that saving it writes that version.
+    fn upgraded(mut self) -> Self {
+        self.version = FORMAT_VERSION;
+        self
+    }
+}
+
+/// Rewrites the JSON of a project from an older format version into the current one.
+///
+/// Each step should upgrade by a single version, so that old files pass through all of them in
+/// order. Version 1 is the first version of the format, so there is nothing to upgrade yet.
+fn migrate(value: Value, _version: u32) -> Value {
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{document::{history::History, testing::DocumentExt, BezierPath, Geometry, PathAnchor, Polygon}, geo::LatLon};
+
+    use super::*;
+
+    fn sample_document() -> Document {
+        let mut document = Document::new();
+        document.add(Geometry::Point(LatLon::new(52.5, 13.4)));
+        document.add(Geometry::Polyline(vec![LatLon::new(0.0, 170.0), LatLon::new(10.0, -170.0)]));
+        let mut polygon = Polygon::new(vec![LatLon::new(0.0, 0.0), LatLon::new(0.0, 10.0), LatLon::new(10.0, 5.0)]);
+        polygon.holes.push(vec![LatLon::new(2.0, 5.0), LatLon::new(4.0, 4.0), LatLon::new(4.0, 6.0)]);
+        document.add(Geometry::Polygon(polygon));
+        let removed = document.add(Geometry::Point(LatLon::new(0.0, 0.0)));
+        document.remove(removed);
+        document.add(Geometry::Path(BezierPath {
+            anchors: vec![
+                PathAnchor::corner(LatLon::new(-20.0, 30.0)),
+                PathAnchor {
+                    point: LatLon::new(-25.0, 40.0),
+                    in_handle: LatLon::new(-22.0, 38.0),
+                    out_handle: LatLon::new(-28.0, 42.0),
+                },
+            ],
+            closed: true,
+        }));
+        document
+    }
+
+    #[test]
+    fn round_trip() {
+        let camera = CameraState { distance: 12.5, pitch: 0.3, yaw: -1.2 };
+        let project = Project::new(sample_document(), camera, Some(PathBuf::from("textures/earth.jpg")));
+
+        let loaded = Project::from_json(&project.to_json().unwrap()).unwrap();
+
+        assert_eq!(loaded.version, FORMAT_VERSION);
+        assert_eq!(loaded.camera, camera);
+        assert_eq!(loaded.texture, project.texture);
+        assert!(loaded.document.features().eq(project.document.features()));
+    }
+
+    #[test]
+    fn texture_paths_are_relative_to_the_project() {
+        let project = Project::new(Document::new(), CameraState::default(), Some(PathBuf::from("textures/earth.jpg")));
+        let absolute = std::env::temp_dir().join("earth.jpg");
+
+        assert_eq!(project.texture_path(Path::new("maps/world.sitelen")), Some(PathBuf::from("maps/textures/earth.jpg")));
+        let project = Project::new(Document::new(), CameraState::default(), Some(absolute.clone()));
+        assert_eq!(project.texture_path(Path::new("maps/world.sitelen")), Some(absolute));
+        let project = Project::new(Document::new(), CameraState::default(), None);
+        assert_eq!(project.texture_path(Path::new("maps/world.sitelen")), None);
+    }
+
+    #[test]
+    fn texture_paths_survive_saving_into_another_folder() {
+        let root = std::env::temp_dir().join(format!("sitelen-texture-{}", std::process::id()));
+        let (maps, projects) = (root.join("maps"), root.join("projects"));
+        fs::create_dir_all(&maps).unwrap();
+        fs::create_dir_all(&projects).unwrap();
+        let texture = maps.join("earth.jpg");
+        let save_and_open = |texture: &Path, path: &Path| {
+            let mut project = Project::new(Document::new(), CameraState::default(), None);
+            project.set_texture_path(Some(texture), path);
+            project.save(path).unwrap();
+            let stored = project.texture.clone();
+            (stored, Project::load(path).unwrap().texture_path(path))
+        };
+
+        // Next to the project, the texture is stored relative to it.
+        let first = maps.join("world.sitelen");
+        let (stored, opened) = save_and_open(&texture, &first);
+        assert_eq!(stored, Some(PathBuf::from("earth.jpg")));
+        assert_eq!(opened.as_deref(), Some(texture.as_path()));
+
+        // Saving the opened project into another folder keeps pointing at the same texture.
+        let (stored, opened) = save_and_open(&opened.unwrap(), &projects.join("copy.sitelen"));
+        assert_eq!(stored.as_deref(), Some(texture.as_path()));
+        assert_eq!(opened.as_deref(), Some(texture.as_path()));
+
+        // Textures given relative to the working directory are found from any folder.
+        let (_, opened) = save_and_open(Path::new("textures/earth.jpg"), &projects.join("relative.sitelen"));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(opened, Some(std::env::current_dir().unwrap().join("textures/earth.jpg")));
+        let mut project = Project::new(Document::new(), CameraState::default(), None);
+        project.set_texture_path(None, &first);
+        assert_eq!(project.texture, None);
+    }
+
+    #[test]
+    fn round_trip_through_file() {
+        let path = std::env::temp_dir().join(format!("sitelen-round-trip-{}.{EXTENSION}", std::process::id()));
+        let project = Project::new(sample_document(), CameraState::default(), None);
+
+        project.save(&path).unwrap();
+        let loaded = Project::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(loaded.unwrap().document.features().eq(project.document.features()));
+    }
+
+    #[test]
+    fn loaded_document_does_not_reuse_identifiers() {
+        let project = Project::new(sample_document(), CameraState::default(), None);
+        let mut loaded = Project::from_json(&project.to_json().unwrap()).unwrap();
+
+        let id = loaded.document.add(Geometry::Point(LatLon::new(1.0, 1.0)));
+        assert!(project.document.features().all(|f| f.id() != id));
+    }
+
+    #[test]
+    fn round_trip_layers() {
+        let mut document = sample_document();
+        let mut history = History::new(8);
+        let rivers = history.add_layer(&mut document, "Rivers".to_string());
+        history.add_feature(&mut document, Geometry::Point(LatLon::new(1.0, 2.0)));
+        let mut layer = document.layer(rivers).unwrap().clone();
+        (layer.locked, layer.opacity) = (true, 0.25);
+        history.set_layer(&mut document, layer.clone(), false);
+        let project = Project::new(document, CameraState::default(), None);
+
+        let loaded = Project::from_json(&project.to_json().unwrap()).unwrap().document;
+
+        assert!(loaded.layers().eq(project.document.layers()));
+        assert_eq!(loaded.layer(rivers), Some(&layer));
+        assert_eq!(loaded.features_in(rivers).count(), 1);
+    }
+
+    #[test]
+    fn features_without_layers_go_to_the_first_layer() {
+        let json = r#"{
+            "version": 1,
+            "document": { "features": [{ "id": 3, "geometry": { "point": { "lat": 1.0, "lon": 2.0 } } }] }
+        }"#;
+        let mut document = Project::from_json(json).unwrap().document;
+
+        let layer = document.layers().next().unwrap().id();
+        assert_eq!(document.layers().count(), 1);
+        assert_eq!(document.features_in(layer).count(), 1);
+        let id = document.add(Geometry::Point(LatLon::new(0.0, 0.0)));
+        assert_eq!(document.get(id).unwrap().layer(), layer);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let project = Project::from_json(r#"{ "version": 1 }"#).unwrap();
+
+        assert!(project.document.is_empty());
+        assert_eq!(project.camera, CameraState::default());
+        assert_eq!(project.texture, None);
+    }
+
+    #[test]
+    fn newer_version_with_unknown_fields() {
+        let json = r#"{
+            "version": 99,
+            "document": { "features": [], "layers": [] },
+            "camera": { "distance": 15.0, "pitch": 0.0, "yaw": 0.0, "roll": 1.0 },
+            "future": true
+        }"#;
+        let project = Project::from_json(json).unwrap();
+
+        assert_eq!(project.version, FORMAT_VERSION);
+        assert_eq!(project.camera.distance, 15.0);
+    }
+
+    #[test]
+    fn newer_version_that_cannot_be_read() {
+        let json = r#"{ "version": 99, "camera": "orthographic" }"#;
+
+        assert!(matches!(Project::from_json(json), Err(ProjectError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    fn rejects_coordinates_that_are_not_finite() {
+        let json = |lat: &str| {
+            format!(r#"{{ "version": 1, "document": {{ "features": [{{ "id": 1, "geometry": {{ "point": {{ "lat": {lat}, "lon": 0.0 }} }} }}] }} }}"#)
+        };
+        assert!(Project::from_json(&json("10.0")).is_ok());
+        for lat in ["null", "1e400", "\"NaN\""] {
+            assert!(matches!(Project::from_json(&json(lat)), Err(ProjectError::Json(_))), "{lat}");
+        }
+    }
+
+    #[test]
+    fn normalizes_coordinates_out_of_range() {
+        let json = r#"{ "version": 1, "document": { "features": [{ "id": 1, "geometry": { "point": { "lat": 100.0, "lon": 190.0 } } }] } }"#;
+        let project = Project::from_json(json).unwrap();
+        let feature = project.document.features().next().unwrap();
+        assert_eq!(feature.geometry, Geometry::Point(LatLon::new(90.0, -170.0)));
+    }
+
+    #[test]
+    fn missing_version() {
+        assert!(matches!(Project::from_json("{}"), Err(ProjectError::MissingVersion)));
+    }
+}
@@ -0,0 +1,130 @@
+use glam::DVec2;
+
+use super::{from_relative, relative_lon, Projection};
+use crate::geo::LatLon;
+
+/// The cosines and sines of a point's latitude and longitude relative to the centre of an
+/// azimuthal projection.
+struct Relative {
+    sin_lat: f64,
+    cos_lat: f64,
+    sin_lon: f64,
+    cos_lon: f64,
+}
+
+impl Relative {
+    fn new(p: LatLon, center: LatLon) -> Self {
+        let (sin_lat, cos_lat) = p.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = relative_lon(p, center.lon).sin_cos();
+        Self { sin_lat, cos_lat, sin_lon, cos_lon }
+    }
+
+    /// The cosine of the angular distance to the centre.
+    fn cos_distance(&self, center: LatLon) -> f64 {
+        let (sin_center, cos_center) = center.lat.to_radians().sin_cos();
+        sin_center * self.sin_lat + cos_center * self.cos_lat * self.cos_lon
+    }
+
+    /// The direction from the centre, scaled by `k`.
+    fn project(&self, center: LatLon, k: f64) -> DVec2 {
+        let (sin_center, cos_center) = center.lat.to_radians().sin_cos();
+        DVec2::new(
+            k * self.cos_lat * self.sin_lon,
+            k * (cos_center * self.sin_lat - sin_center * self.cos_lat * self.cos_lon),
+        )
+    }
+}
+
+/// Finds the point at angular distance `c` from the centre in the direction of `p`, which is
+/// the inverse shared by all azimuthal projections.
+fn azimuthal_inverse(center: LatLon, p: DVec2, c: f64) -> LatLon {
+    let rho = p.length();
+    if rho < 1e-15 {
+        return center;
+    }
+    let (sin_center, cos_center) = center.lat.to_radians().sin_cos();
+    let (sin_c, cos_c) = c.sin_cos();
+    let lat = (cos_c * sin_center + p.y * sin_c * cos_center / rho).clamp(-1.0, 1.0).asin();
+    let lon = (p.x * sin_c).atan2(rho * cos_center * cos_c - p.y * sin_center * sin_c);
+    from_relative(lat, lon, center.lon)
+}
+
+/// An azimuthal projection that keeps areas, showing the whole globe in a disc of radius `2`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LambertAzimuthalEqualArea {
+    /// The point at the centre of the map.
+    pub center: LatLon,
+}
+
+impl LambertAzimuthalEqualArea {
+    /// Creates a new [LambertAzimuthalEqualArea] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The point at the centre of the map.
+    pub fn new(center: LatLon) -> Self {
+        Self { center }
+    }
+}
+
+impl Projection for LambertAzimuthalEqualArea {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        if !self.contains(p) {
+            return None;
+        }
+        let relative = Relative::new(p, self.center);
+        let denominator = 1.0 + relative.cos_distance(self.center);
+        Some(relative.project(self.center, (2.0 / denominator).sqrt()))
+    }
+
+    fn contains(&self, p: LatLon) -> bool {
+        // The point opposite the centre becomes the whole rim of the map.
+        1.0 + Relative::new(p, self.center).cos_distance(self.center) >= 1e-12
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        let rho = p.length();
+        (rho <= 2.0).then(|| azimuthal_inverse(self.center, p, 2.0 * (rho / 2.0).asin()))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        (DVec2::splat(-2.0), DVec2::splat(2.0))
+    }
+}
+
+/// The view of the globe from infinitely far away, showing one hemisphere in a disc of radius `1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orthographic {
+    /// The point at the centre of the map.
+    pub center: LatLon,
+}
+
+impl Orthographic {
+    /// Creates a new [Orthographic] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The point at the centre of the map.
+    pub fn new(center: LatLon) -> Self {
+        Self { center }
+    }
+}
+
+impl Projection for Orthographic {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        self.contains(p).then(|| Relative::new(p, self.center).project(self.center, 1.0))
+    }
+
+    fn contains(&self, p: LatLon) -> bool {
+        Relative::new(p, self.center).cos_distance(self.center) >= 0.0
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        let rho = p.length();
+        (rho <= 1.0).then(|| azimuthal_inverse(self.center, p, rho.asin()))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        (DVec2::splat(-1.0), DVec2::splat(1.0))
+    }
+}
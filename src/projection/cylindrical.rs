@@ -0,0 +1,101 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use glam::DVec2;
+
+use super::{from_relative, relative_lon, Projection};
+use crate::geo::LatLon;
+
+/// The latitude in degrees at which web maps cut off the [Mercator] projection, making the map
+/// square.
+pub const WEB_MERCATOR_MAX_LAT: f64 = 85.051_128_779_806_59;
+
+/// The plate carrée and its variants, which map longitude and latitude linearly to `x` and `y`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Equirectangular {
+    /// The longitude in degrees at the centre of the map.
+    pub center_lon: f64,
+    /// The latitude in degrees at which the map is true to scale in all directions.
+    pub standard_parallel: f64,
+}
+
+impl Equirectangular {
+    /// Creates a new [Equirectangular] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center_lon`: The longitude in degrees at the centre of the map.
+    /// * `standard_parallel`: The latitude in degrees without distortion, `0` for the plate carrée.
+    pub fn new(center_lon: f64, standard_parallel: f64) -> Self {
+        Self { center_lon, standard_parallel }
+    }
+
+    fn scale(&self) -> f64 {
+        self.standard_parallel.to_radians().cos()
+    }
+}
+
+impl Projection for Equirectangular {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        Some(DVec2::new(relative_lon(p, self.center_lon) * self.scale(), p.lat.to_radians()))
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        let lon = p.x / self.scale();
+        (lon.abs() <= PI && p.y.abs() <= FRAC_PI_2).then(|| from_relative(p.y, lon, self.center_lon))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        let max = DVec2::new(PI * self.scale(), FRAC_PI_2);
+        (-max, max)
+    }
+}
+
+/// The conformal cylindrical projection, which keeps angles but cannot show the poles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mercator {
+    /// The longitude in degrees at the centre of the map.
+    pub center_lon: f64,
+    /// The highest latitude in degrees shown on the map.
+    pub max_lat: f64,
+}
+
+impl Mercator {
+    /// Creates a new [Mercator] projection cut off at [WEB_MERCATOR_MAX_LAT].
+    ///
+    /// Arguments:
+    ///
+    /// * `center_lon`: The longitude in degrees at the centre of the map.
+    pub fn new(center_lon: f64) -> Self {
+        Self { center_lon, max_lat: WEB_MERCATOR_MAX_LAT }
+    }
+
+    fn max_y(&self) -> f64 {
+        (FRAC_PI_4 + self.max_lat.to_radians() / 2.0).tan().ln()
+    }
+}
+
+impl Projection for Mercator {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        if !self.contains(p) {
+            return None;
+        }
+        let y = (FRAC_PI_4 + p.lat.to_radians() / 2.0).tan().ln();
+        Some(DVec2::new(relative_lon(p, self.center_lon), y))
+    }
+
+    fn contains(&self, p: LatLon) -> bool {
+        p.lat.abs() <= self.max_lat
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        if p.x.abs() > PI || p.y.abs() > self.max_y() {
+            return None;
+        }
+        Some(from_relative(p.y.sinh().atan(), p.x, self.center_lon))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        let max = DVec2::new(PI, self.max_y());
+        (-max, max)
+    }
+}
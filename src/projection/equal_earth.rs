@@ -0,0 +1,74 @@
+use std::f64::consts::PI;
+
+use glam::DVec2;
+
+use super::{from_relative, newton, relative_lon, Projection};
+use crate::geo::LatLon;
+
+const A1: f64 = 1.340264;
+const A2: f64 = -0.081106;
+const A3: f64 = 0.000893;
+const A4: f64 = 0.003796;
+
+/// `√3 / 2`, the sine of the auxiliary angle at the poles.
+const M: f64 = 0.866_025_403_784_438_6;
+
+/// The equal-area pseudocylindrical projection by Šavrič, Patterson and Jenny (2018).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqualEarth {
+    /// The longitude in degrees at the centre of the map.
+    pub center_lon: f64,
+}
+
+impl EqualEarth {
+    /// Creates a new [EqualEarth] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center_lon`: The longitude in degrees at the centre of the map.
+    pub fn new(center_lon: f64) -> Self {
+        Self { center_lon }
+    }
+}
+
+/// The polynomial giving `y` for the auxiliary angle `theta`.
+fn y_of(theta: f64) -> f64 {
+    let t2 = theta * theta;
+    let t6 = t2 * t2 * t2;
+    theta * (A1 + A2 * t2 + t6 * (A3 + A4 * t2))
+}
+
+/// The derivative of [y_of].
+fn dy_of(theta: f64) -> f64 {
+    let t2 = theta * theta;
+    let t6 = t2 * t2 * t2;
+    A1 + 3.0 * A2 * t2 + t6 * (7.0 * A3 + 9.0 * A4 * t2)
+}
+
+impl Projection for EqualEarth {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        let theta = (M * p.lat.to_radians().sin()).asin();
+        let lon = relative_lon(p, self.center_lon);
+        let x = 2.0 * 3f64.sqrt() * lon * theta.cos() / (3.0 * dy_of(theta));
+        Some(DVec2::new(x, y_of(theta)))
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        let max_theta = M.asin();
+        if p.y.abs() > y_of(max_theta) {
+            return None;
+        }
+        let theta = newton(p.y / A1, |t| (y_of(t) - p.y, dy_of(t))).clamp(-max_theta, max_theta);
+        let lon = 3.0 * p.x * dy_of(theta) / (2.0 * 3f64.sqrt() * theta.cos());
+        if lon.abs() > PI + 1e-12 {
+            return None;
+        }
+        let lat = (theta.sin() / M).clamp(-1.0, 1.0).asin();
+        Some(from_relative(lat, lon.clamp(-PI, PI), self.center_lon))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        let max = DVec2::new(2.0 * 3f64.sqrt() * PI / (3.0 * A1), y_of(M.asin()));
+        (-max, max)
+    }
+}
@@ -0,0 +1,294 @@
+mod azimuthal;
+mod cylindrical;
+mod equal_earth;
+mod mollweide;
+mod robinson;
+
+pub use azimuthal::{LambertAzimuthalEqualArea, Orthographic};
+pub use cylindrical::{Equirectangular, Mercator, WEB_MERCATOR_MAX_LAT};
+pub use equal_earth::EqualEarth;
+pub use mollweide::Mollweide;
+pub use robinson::Robinson;
+
+use std::fmt;
+
+use glam::DVec2;
+
+use crate::geo::{wrap_longitude, LatLon};
+
+/// A map projection of the unit sphere onto the plane.
+///
+/// Map coordinates are given for a sphere with radius `1`, with `x` pointing east and `y`
+/// pointing north.
+pub trait Projection: fmt::Debug {
+    /// Projects a coordinate onto the map.
+    ///
+    /// Returns `None` if the coordinate lies outside the domain of the projection, see
+    /// [Projection::contains].
+    fn forward(&self, p: LatLon) -> Option<DVec2>;
+
+    /// Whether a coordinate lies inside the domain of the projection.
+    ///
+    /// Most projections show the whole globe. Exceptions are the far side of the globe for
+    /// [Orthographic] or the poles for [Mercator].
+    fn contains(&self, _p: LatLon) -> bool {
+        true
+    }
+
+    /// Finds the coordinate shown at a point of the map.
+    ///
+    /// Returns `None` if the point lies outside the projected globe.
+    fn inverse(&self, p: DVec2) -> Option<LatLon>;
+
+    /// The smallest rectangle containing the projected domain, as its minimum and maximum corner.
+    fn bounds(&self) -> (DVec2, DVec2);
+}
+
+/// The projections that can be picked for the flat map view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProjectionKind {
+    /// See [Equirectangular], as a plate carrée.
+    #[default]
+    Equirectangular,
+    /// See [Mercator].
+    Mercator,
+    /// See [Robinson].
+    Robinson,
+    /// See [Mollweide].
+    Mollweide,
+    /// See [EqualEarth].
+    EqualEarth,
+    /// See [LambertAzimuthalEqualArea].
+    LambertAzimuthalEqualArea,
+    /// See [Orthographic].
+    Orthographic,
+}
+
+impl ProjectionKind {
+    /// All projections, in the order they are cycled through.
+    pub const ALL: [ProjectionKind; 7] = [
+        ProjectionKind::Equirectangular,
+        ProjectionKind::Mercator,
+        ProjectionKind::Robinson,
+        ProjectionKind::Mollweide,
+        ProjectionKind::EqualEarth,
+        ProjectionKind::LambertAzimuthalEqualArea,
+        ProjectionKind::Orthographic,
+    ];
+
+    /// The name of the projection as shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProjectionKind::Equirectangular => "Equirectangular",
+            ProjectionKind::Mercator => "Mercator",
+            ProjectionKind::Robinson => "Robinson",
+            ProjectionKind::Mollweide => "Mollweide",
+            ProjectionKind::EqualEarth => "Equal Earth",
+            ProjectionKind::LambertAzimuthalEqualArea => "Lambert azimuthal equal-area",
+            ProjectionKind::Orthographic => "Orthographic",
+        }
+    }
+
+    /// Returns the projection following this one in [ProjectionKind::ALL], wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Creates the projection with `center` in the middle of the map.
+    ///
+    /// Cylindrical and pseudo-cylindrical projections only use the longitude of `center`, since
+    /// their central parallel is always the equator.
+    pub fn create(self, center: LatLon) -> Box<dyn Projection> {
+        match self {
+            ProjectionKind::Equirectangular => Box::new(Equirectangular::new(center.lon, 0.0)),
+            ProjectionKind::Mercator => Box::new(Mercator::new(center.lon)),
+            ProjectionKind::Robinson => Box::new(Robinson::new(center.lon)),
+            ProjectionKind::Mollweide => Box::new(Mollweide::new(center.lon)),
+            ProjectionKind::EqualEarth => Box::new(EqualEarth::new(center.lon)),
+            ProjectionKind::LambertAzimuthalEqualArea => Box::new(LambertAzimuthalEqualArea::new(center)),
+            ProjectionKind::Orthographic => Box::new(Orthographic::new(center)),
+        }
+    }
+}
+
+/// Returns the longitude of `p` relative to a central meridian in radians, within `[-π, π]`.
+fn relative_lon(p: LatLon, center_lon: f64) -> f64 {
+    wrap_longitude(p.lon - center_lon).to_radians()
+}
+
+/// Builds a coordinate from a latitude and a longitude relative to a central meridian in radians.
+fn from_relative(lat: f64, lon: f64, center_lon: f64) -> LatLon {
+    LatLon::new(lat.to_degrees(), lon.to_degrees() + center_lon)
+}
+
+/// Solves `f(x) = 0` with Newton's method.
+///
+/// Arguments:
+///
+/// * `x`: The initial guess.
+/// * `f`: Returns the value of the function and its derivative at `x`.
+fn newton(mut x: f64, f: impl Fn(f64) -> (f64, f64)) -> f64 {
+    for _ in 0..32 {
+        let (value, derivative) = f(x);
+        if derivative == 0.0 {
+            break;
+        }
+        let step = value / derivative;
+        x -= step;
+        if step.abs() < 1e-14 {
+            break;
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+    use super::*;
+
+    fn assert_close(actual: DVec2, expected: DVec2, tolerance: f64) {
+        assert!((actual - expected).abs().max_element() < tolerance, "{actual} != {expected}");
+    }
+
+    fn projections() -> Vec<(&'static str, Box<dyn Projection>)> {
+        vec![
+            ("equirectangular", Box::new(Equirectangular::new(10.0, 30.0))),
+            ("mercator", Box::new(Mercator::new(-20.0))),
+            ("lambert", Box::new(LambertAzimuthalEqualArea::new(LatLon::new(40.0, 10.0)))),
+            ("orthographic", Box::new(Orthographic::new(LatLon::new(-30.0, 120.0)))),
+            ("robinson", Box::new(Robinson::new(0.0))),
+            ("mollweide", Box::new(Mollweide::new(150.0))),
+            ("equal earth", Box::new(EqualEarth::new(0.0))),
+        ]
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        for (name, projection) in projections() {
+            for lat in (-85..=85).step_by(5) {
+                for lon in (-175..=175).step_by(5) {
+                    let p = LatLon::new(lat as f64, lon as f64);
+                    let Some(q) = projection.forward(p) else {
+                        continue;
+                    };
+                    let (min, max) = projection.bounds();
+                    assert!(q.cmpge(min - 1e-9).all() && q.cmple(max + 1e-9).all(), "{name}: {q} outside bounds");
+                    let back = projection.inverse(q).unwrap_or_else(|| panic!("{name}: no inverse for {q}"));
+                    assert!(p.angular_distance(back) < 1e-9, "{name}: {p:?} came back as {back:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forward_projects_exactly_the_domain() {
+        for (name, projection) in projections() {
+            for lat in (-90..=90).step_by(5) {
+                for lon in (-180..=180).step_by(5) {
+                    let p = LatLon::new(lat as f64, lon as f64);
+                    assert_eq!(projection.forward(p).is_some(), projection.contains(p), "{name}: {p:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_rejects_points_outside_the_map() {
+        for (name, projection) in projections() {
+            let (_, max) = projection.bounds();
+            assert!(projection.inverse(max * 1.01).is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn created_projections_are_centred() {
+        let center = LatLon::new(35.0, 160.0);
+        for kind in ProjectionKind::ALL {
+            let projection = kind.create(center);
+            let q = projection.forward(center).unwrap();
+            assert!(q.x.abs() < 1e-9, "{}: {q}", kind.name());
+            assert!(projection.inverse(q).unwrap().angular_distance(center) < 1e-9, "{}", kind.name());
+        }
+        assert_eq!(ProjectionKind::Orthographic.next(), ProjectionKind::Equirectangular);
+    }
+
+    #[test]
+    fn equirectangular() {
+        let projection = Equirectangular::new(0.0, 0.0);
+        assert_close(projection.forward(LatLon::new(45.0, -90.0)).unwrap(), DVec2::new(-FRAC_PI_2, PI / 4.0), 1e-12);
+        assert_close(projection.bounds().1, DVec2::new(PI, FRAC_PI_2), 1e-12);
+
+        assert!(projection.contains(LatLon::new(90.0, 180.0)) && projection.contains(LatLon::new(-90.0, -180.0)));
+
+        let projection = Equirectangular::new(0.0, 60.0);
+        assert_close(projection.forward(LatLon::new(0.0, 180.0)).unwrap(), DVec2::new(PI / 2.0, 0.0), 1e-12);
+    }
+
+    #[test]
+    fn mercator() {
+        let projection = Mercator::new(0.0);
+        assert_close(projection.forward(LatLon::new(45.0, 0.0)).unwrap(), DVec2::new(0.0, 0.8813736), 1e-7);
+        assert_close(projection.forward(LatLon::new(-45.0, 90.0)).unwrap(), DVec2::new(FRAC_PI_2, -0.8813736), 1e-7);
+        // The web mercator limit makes the map square.
+        let (min, max) = projection.bounds();
+        assert!(((max.y - min.y) - (max.x - min.x)).abs() < 1e-6);
+        assert!(projection.forward(LatLon::new(89.0, 0.0)).is_none());
+        assert!(projection.contains(LatLon::new(WEB_MERCATOR_MAX_LAT, 180.0)));
+        assert!(!projection.contains(LatLon::new(-89.0, 0.0)));
+    }
+
+    #[test]
+    fn lambert_azimuthal_equal_area() {
+        let projection = LambertAzimuthalEqualArea::new(LatLon::new(0.0, 0.0));
+        assert_close(projection.forward(LatLon::new(0.0, 0.0)).unwrap(), DVec2::ZERO, 1e-12);
+        // Points 90° away from the centre lie on a circle of radius √2.
+        assert_close(projection.forward(LatLon::new(0.0, 90.0)).unwrap(), DVec2::new(SQRT_2, 0.0), 1e-12);
+        assert_close(projection.forward(LatLon::new(90.0, 0.0)).unwrap(), DVec2::new(0.0, SQRT_2), 1e-12);
+        assert_close(projection.bounds().1, DVec2::splat(2.0), 1e-12);
+        // Only the point opposite the centre is left out.
+        assert!(projection.contains(LatLon::new(0.0, 179.0)) && projection.contains(LatLon::new(-90.0, 0.0)));
+        assert!(!projection.contains(LatLon::new(0.0, 180.0)));
+    }
+
+    #[test]
+    fn orthographic() {
+        let projection = Orthographic::new(LatLon::new(0.0, 0.0));
+        assert_close(projection.forward(LatLon::new(0.0, 30.0)).unwrap(), DVec2::new(0.5, 0.0), 1e-12);
+        assert_close(projection.forward(LatLon::new(90.0, 0.0)).unwrap(), DVec2::new(0.0, 1.0), 1e-12);
+        assert!(projection.forward(LatLon::new(0.0, 120.0)).is_none());
+        assert!(!projection.contains(LatLon::new(10.0, 180.0)));
+        assert!(projection.contains(LatLon::new(0.0, 90.0)) && projection.contains(LatLon::new(-90.0, 0.0)));
+    }
+
+    #[test]
+    fn robinson() {
+        let projection = Robinson::new(0.0);
+        // Table values at 45°: X = 0.8962, Y = 0.5571.
+        let expected = DVec2::new(0.8487 * 0.8962 * FRAC_PI_2, 1.3523 * 0.5571);
+        assert_close(projection.forward(LatLon::new(45.0, 90.0)).unwrap(), expected, 1e-9);
+        assert_close(projection.forward(LatLon::new(-90.0, 0.0)).unwrap(), DVec2::new(0.0, -1.3523), 1e-9);
+        assert_close(projection.bounds().1, DVec2::new(0.8487 * PI, 1.3523), 1e-9);
+        assert!(projection.contains(LatLon::new(90.0, 180.0)) && projection.contains(LatLon::new(-90.0, -180.0)));
+    }
+
+    #[test]
+    fn mollweide() {
+        let projection = Mollweide::new(0.0);
+        assert_close(projection.forward(LatLon::new(0.0, 180.0)).unwrap(), DVec2::new(2.0 * SQRT_2, 0.0), 1e-9);
+        assert_close(projection.forward(LatLon::new(90.0, 0.0)).unwrap(), DVec2::new(0.0, SQRT_2), 1e-9);
+        assert_close(projection.bounds().1, DVec2::new(2.0 * SQRT_2, SQRT_2), 1e-12);
+        assert!(projection.contains(LatLon::new(90.0, 180.0)) && projection.contains(LatLon::new(-90.0, -180.0)));
+    }
+
+    #[test]
+    fn equal_earth() {
+        let projection = EqualEarth::new(0.0);
+        assert_close(projection.forward(LatLon::new(0.0, 180.0)).unwrap(), DVec2::new(2.70663, 0.0), 1e-5);
+        assert_close(projection.forward(LatLon::new(90.0, 0.0)).unwrap(), DVec2::new(0.0, 1.31736), 1e-5);
+        assert_close(projection.bounds().1, DVec2::new(2.70663, 1.31736), 1e-5);
+        assert!(projection.contains(LatLon::new(90.0, 180.0)) && projection.contains(LatLon::new(-90.0, -180.0)));
+    }
+}
@@ -0,0 +1,57 @@
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+use glam::DVec2;
+
+use super::{from_relative, newton, relative_lon, Projection};
+use crate::geo::LatLon;
+
+/// An equal-area pseudocylindrical projection showing the globe as an ellipse twice as wide as
+/// it is tall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mollweide {
+    /// The longitude in degrees at the centre of the map.
+    pub center_lon: f64,
+}
+
+impl Mollweide {
+    /// Creates a new [Mollweide] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center_lon`: The longitude in degrees at the centre of the map.
+    pub fn new(center_lon: f64) -> Self {
+        Self { center_lon }
+    }
+}
+
+impl Projection for Mollweide {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        let lat = p.lat.to_radians();
+        // The auxiliary angle solves 2θ + sin 2θ = π sin φ, which is singular at the poles.
+        let theta = if lat.abs() > FRAC_PI_2 - 1e-9 {
+            lat
+        } else {
+            let target = PI * lat.sin();
+            let double = newton(lat, |t| (t + t.sin() - target, 1.0 + t.cos()));
+            double / 2.0
+        };
+        let lon = relative_lon(p, self.center_lon);
+        Some(DVec2::new(2.0 * SQRT_2 / PI * lon * theta.cos(), SQRT_2 * theta.sin()))
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        if (p.x / (2.0 * SQRT_2)).powi(2) + (p.y / SQRT_2).powi(2) > 1.0 + 1e-12 {
+            return None;
+        }
+        let theta = (p.y / SQRT_2).clamp(-1.0, 1.0).asin();
+        let lat = ((2.0 * theta + (2.0 * theta).sin()) / PI).clamp(-1.0, 1.0).asin();
+        let cos = theta.cos();
+        let lon = if cos < 1e-12 { 0.0 } else { PI * p.x / (2.0 * SQRT_2 * cos) };
+        Some(from_relative(lat, lon.clamp(-PI, PI), self.center_lon))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        let max = DVec2::new(2.0 * SQRT_2, SQRT_2);
+        (-max, max)
+    }
+}
@@ -0,0 +1,112 @@
+use std::f64::consts::PI;
+
+use glam::DVec2;
+
+use super::{from_relative, newton, relative_lon, Projection};
+use crate::geo::LatLon;
+
+/// The relative length of each parallel from the equator to the pole in steps of 5°.
+const PARALLEL_LENGTH: [f64; 19] = [
+    1.0000, 0.9986, 0.9954, 0.9900, 0.9822, 0.9730, 0.9600, 0.9427, 0.9216, 0.8962,
+    0.8679, 0.8350, 0.7986, 0.7597, 0.7186, 0.6732, 0.6213, 0.5722, 0.5322,
+];
+
+/// The relative distance of each parallel from the equator from the equator to the pole in
+/// steps of 5°.
+const PARALLEL_DISTANCE: [f64; 19] = [
+    0.0000, 0.0620, 0.1240, 0.1860, 0.2480, 0.3100, 0.3720, 0.4340, 0.4958, 0.5571,
+    0.6176, 0.6769, 0.7346, 0.7903, 0.8435, 0.8936, 0.9394, 0.9761, 1.0000,
+];
+
+/// The latitude in degrees between two rows of the tables.
+const STEP: f64 = 5.0;
+
+/// Scales [PARALLEL_LENGTH] to map units.
+const X_SCALE: f64 = 0.8487;
+
+/// Scales [PARALLEL_DISTANCE] to map units.
+const Y_SCALE: f64 = 1.3523;
+
+/// The compromise projection by Arthur Robinson (1963), defined by a table of parallels.
+///
+/// Values between the rows of the table are interpolated with cubic Hermite splines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Robinson {
+    /// The longitude in degrees at the centre of the map.
+    pub center_lon: f64,
+}
+
+impl Robinson {
+    /// Creates a new [Robinson] projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `center_lon`: The longitude in degrees at the centre of the map.
+    pub fn new(center_lon: f64) -> Self {
+        Self { center_lon }
+    }
+}
+
+/// Interpolates a table at a latitude in degrees.
+///
+/// Returns the value and its derivative per degree. The tables describe the northern
+/// hemisphere and are mirrored for the southern one, `odd` tables changing their sign.
+fn interpolate(table: &[f64; 19], lat: f64, odd: bool) -> (f64, f64) {
+    let sign = if odd && lat < 0.0 { -1.0 } else { 1.0 };
+    let lat = lat.abs().min(90.0);
+    let i = ((lat / STEP) as usize).min(table.len() - 2);
+    let t = lat / STEP - i as f64;
+
+    // Rows beyond the equator are the mirrored rows of the other hemisphere.
+    let row = |j: isize| match j {
+        j if j < 0 && odd => -table[(-j) as usize],
+        j if j < 0 => table[(-j) as usize],
+        j => table[(j as usize).min(table.len() - 1)],
+    };
+    let slope = |j: usize| {
+        if j == table.len() - 1 {
+            table[j] - table[j - 1]
+        } else {
+            (row(j as isize + 1) - row(j as isize - 1)) / 2.0
+        }
+    };
+
+    let (p0, p1) = (table[i], table[i + 1]);
+    let (m0, m1) = (slope(i), slope(i + 1));
+    let (t2, t3) = (t * t, t * t * t);
+    let value = (2.0 * t3 - 3.0 * t2 + 1.0) * p0 + (t3 - 2.0 * t2 + t) * m0 + (-2.0 * t3 + 3.0 * t2) * p1 + (t3 - t2) * m1;
+    let derivative = (6.0 * t2 - 6.0 * t) * p0 + (3.0 * t2 - 4.0 * t + 1.0) * m0 + (-6.0 * t2 + 6.0 * t) * p1 + (3.0 * t2 - 2.0 * t) * m1;
+    (sign * value, derivative / STEP)
+}
+
+impl Projection for Robinson {
+    fn forward(&self, p: LatLon) -> Option<DVec2> {
+        let (length, _) = interpolate(&PARALLEL_LENGTH, p.lat, false);
+        let (distance, _) = interpolate(&PARALLEL_DISTANCE, p.lat, true);
+        Some(DVec2::new(X_SCALE * length * relative_lon(p, self.center_lon), Y_SCALE * distance))
+    }
+
+    fn inverse(&self, p: DVec2) -> Option<LatLon> {
+        let distance = p.y / Y_SCALE;
+        if distance.abs() > 1.0 {
+            return None;
+        }
+        let lat = newton(distance * 90.0, |lat| {
+            let (value, derivative) = interpolate(&PARALLEL_DISTANCE, lat, true);
+            (value - distance, derivative)
+        })
+        .clamp(-90.0, 90.0);
+
+        let (length, _) = interpolate(&PARALLEL_LENGTH, lat, false);
+        let lon = p.x / (X_SCALE * length);
+        if lon.abs() > PI + 1e-12 {
+            return None;
+        }
+        Some(from_relative(lat.to_radians(), lon.clamp(-PI, PI), self.center_lon))
+    }
+
+    fn bounds(&self) -> (DVec2, DVec2) {
+        let max = DVec2::new(X_SCALE * PI, Y_SCALE);
+        (-max, max)
+    }
+}
@@ -0,0 +1,230 @@
+use std::borrow::Cow;
+
+use glam::DVec2;
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, FragmentState, PipelineLayoutDescriptor, RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, TextureFormat, VertexState};
+
+use super::{BaseTexture, Placement};
+use crate::{geo::{wrap_longitude, LatLon}, projection::Projection, vertex::Vertex};
+
+/// The spacing in degrees of the parallels and meridians the texture is projected along.
+const GRID_STEP: f64 = 2.0;
+
+/// How far in degrees the outermost meridians stay inside the border of the map, so that they
+/// do not wrap around to the opposite side.
+const BORDER_INSET: f64 = 1e-6;
+
+/// Draws the texture of the globe onto a flat map.
+///
+/// The texture is projected on the CPU by placing the vertices of a grid of parallels and
+/// meridians on the map. Straight edges between them are a good enough approximation as long as
+/// the grid is fine compared to the curvature of the projection.
+#[derive(Debug)]
+pub struct BaseMapRenderer {
+    pipeline: RenderPipeline,
+    // Draws the map with a tile pyramid instead of a single texture.
+    tiled_pipeline: RenderPipeline,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+    num_indices: u32,
+}
+
+impl BaseMapRenderer {
+    /// Creates a new [BaseMapRenderer].
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `texture_bind_group_layout`: The layout of the texture and its sampler, bound to group `0`.
+    /// * `tile_bind_group_layout`: The layout of a [super::TileAtlas], bound to group `0` instead
+    ///   of the texture when the map is drawn from tiles.
+    /// * `camera_bind_group_layout`: The layout of the camera uniform, bound to group `1`.
+    /// * `format`: The format of the color target.
+    /// * `sample_count`: The number of samples used for _MSAA_.
+    pub fn new(
+        device: &Device,
+        texture_bind_group_layout: &BindGroupLayout,
+        tile_bind_group_layout: &BindGroupLayout,
+        camera_bind_group_layout: &BindGroupLayout,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Map Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("../assets/map.wgsl"), include_str!("../assets/tiles.wgsl")))),
+        });
+
+        Self {
+            pipeline: create_pipeline(device, &shader, "fs_main", texture_bind_group_layout, camera_bind_group_layout, format, sample_count),
+            tiled_pipeline: create_pipeline(device, &shader, "fs_tiled", tile_bind_group_layout, camera_bind_group_layout, format, sample_count),
+            vertex_buffer: None,
+            index_buffer: None,
+            num_indices: 0,
+        }
+    }
+
+    /// Projects the grid the texture is drawn on.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `projection`: The projection of the map.
+    /// * `center_lon`: The longitude in degrees at the centre of the map. The grid is built
+    ///   around it, so that the border of the map falls between two columns of the grid.
+    pub fn rebuild(&mut self, device: &Device, projection: &dyn Projection, center_lon: f64) {
+        let placement = Placement::Map(projection);
+
+        // The texture wraps around at the antimeridian, which gets a column boundary of its own
+        // so that no cell has to interpolate across it.
+        let mut columns: Vec<f64> = grid_lines(-180.0 + BORDER_INSET, 180.0 - BORDER_INSET);
+        columns.push(wrap_longitude(180.0 - center_lon));
+        let mut rows = grid_lines(-90.0, 90.0);
+        // Maps that cannot show the poles end at the parallel where their domain ends.
+        let (min, max) = projection.bounds();
+        for y in [min.y, max.y] {
+            if let Some(edge) = projection.inverse(DVec2::new(0.0, y)) {
+                rows.push(edge.lat * (1.0 - 1e-12));
+            }
+        }
+        sort_lines(&mut columns, -180.0 + BORDER_INSET, 180.0 - BORDER_INSET);
+        sort_lines(&mut rows, -90.0, 90.0);
+
+        let mut vertices = vec![];
+        let mut indices: Vec<u32> = vec![];
+        for pair in columns.windows(2) {
+            let middle = (pair[0] + pair[1]) / 2.0;
+            let middle_lon = wrap_longitude(center_lon + middle);
+            let offset = vertices.len() as u32;
+            let mut placed = vec![];
+            for &lat in &rows {
+                for &x in pair {
+                    let point = LatLon::new(lat, center_lon + x);
+                    let position = placement.place(point.to_unit_vector(), 1.0);
+                    placed.push(position);
+                    vertices.push(Vertex {
+                        position: position.unwrap_or_default().to_array(),
+                        tex_coords: [
+                            ((middle_lon + x - middle + 180.0) / 360.0) as f32,
+                            ((90.0 - lat) / 180.0) as f32,
+                        ],
+                        normal: [0.0, 0.0, 1.0],
+                    });
+                }
+            }
+
+            for row in 0..rows.len() - 1 {
+                let [a, b, c, d] = [2 * row, 2 * row + 1, 2 * row + 3, 2 * row + 2];
+                for triangle in [[a, b, c], [a, c, d]] {
+                    let corners: Option<Vec<_>> = triangle.iter().map(|&i| placed[i]).collect();
+                    if let Some(corners) = corners
+                        && (0..3).all(|i| placement.connects(corners[i], corners[(i + 1) % 3]))
+                    {
+                        indices.extend(triangle.iter().map(|&i| i as u32 + offset));
+                    }
+                }
+            }
+        }
+
+        self.num_indices = indices.len() as u32;
+        if indices.is_empty() {
+            self.vertex_buffer = None;
+            self.index_buffer = None;
+            return;
+        }
+        self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Base Map Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+        self.index_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Base Map Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }));
+    }
+
+    /// Records the draw commands into a render pass.
+    ///
+    /// Arguments:
+    ///
+    /// * `r_pass`: The render pass the map is drawn in.
+    /// * `texture`: The texture of the globe.
+    /// * `camera_bind_group`: The bind group of the camera uniform.
+    pub fn draw(&self, r_pass: &mut RenderPass, texture: &BaseTexture, camera_bind_group: &BindGroup) {
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else {
+            return;
+        };
+        match texture {
+            BaseTexture::Image(_) => r_pass.set_pipeline(&self.pipeline),
+            BaseTexture::Tiles(_) => r_pass.set_pipeline(&self.tiled_pipeline),
+        }
+        r_pass.set_bind_group(0, texture.bind_group(), &[]);
+        r_pass.set_bind_group(1, camera_bind_group, &[]);
+        r_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        r_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        r_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// Creates the pipeline drawing the map with one of the fragment entry points of `map.wgsl`.
+fn create_pipeline(
+    device: &Device,
+    shader: &ShaderModule,
+    fragment_entry_point: &str,
+    texture_bind_group_layout: &BindGroupLayout,
+    camera_bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Base Map Pipeline"),
+        bind_group_layouts: &[texture_bind_group_layout, camera_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Base Map Pipeline"),
+        layout: Some(&layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry_point),
+            targets: &[Some(format.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Returns lines [GRID_STEP] apart, starting at `min` and ending with `max`.
+fn grid_lines(min: f64, max: f64) -> Vec<f64> {
+    let steps = ((max - min) / GRID_STEP).ceil() as usize;
+    (0..steps).map(|i| min + i as f64 * GRID_STEP).chain([max]).collect()
+}
+
+/// Sorts grid lines, dropping those outside `[min, max]` and those too close to their neighbour.
+fn sort_lines(lines: &mut Vec<f64>, min: f64, max: f64) {
+    lines.retain(|x| (min..=max).contains(x));
+    lines.sort_by(f64::total_cmp);
+    lines.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+}
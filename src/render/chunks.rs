@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::{util::DeviceExt, Buffer, Device, RenderPass};
+
+use super::MAX_ERROR_PIXELS;
+use crate::{camera::{Camera, OrbitCamera}, sphere::{cube_sphere_direction, normal_vertex, CUBE_FACES, GLOBE_RADIUS}, vertex::Vertex};
+
+/// The number of cells along each edge of a chunk.
+const CHUNK_SEGMENTS: u32 = 32;
+
+/// The finest level chunks are split to, where a cell is still many times larger than the
+/// precision of the vertex positions.
+const MAX_CHUNK_LEVEL: u8 = 14;
+
+/// The number of chunk meshes kept on the GPU, including the ones that are not visible anymore.
+const MAX_CACHED_CHUNKS: usize = 512;
+
+/// A chunk of a [ChunkedGlobe].
+///
+/// Level `l` splits each face of the cube sphere into `2^l` by `2^l` chunks, with `(0, 0)` at
+/// the corner where both coordinates of the face are `-1`, see [cube_sphere_direction].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    pub face: u8,
+    pub level: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl ChunkId {
+    /// Returns the chunk covering a whole face of the cube sphere.
+    fn root(face: u8) -> Self {
+        Self { face, level: 0, x: 0, y: 0 }
+    }
+
+    /// Returns the four chunks covering this chunk at the next level.
+    fn children(self) -> [Self; 4] {
+        let (level, x, y) = (self.level + 1, 2 * self.x, 2 * self.y);
+        [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].map(|(x, y)| Self { face: self.face, level, x, y })
+    }
+
+    /// Returns the direction from the centre of the globe to a point of the chunk.
+    ///
+    /// Arguments:
+    ///
+    /// * `i`: The position along the `u` axis of the face, from `0` to `1` across the chunk.
+    /// * `j`: The position along the `v` axis of the face, from `0` to `1` across the chunk.
+    fn direction(self, i: f32, j: f32) -> Vec3 {
+        let size = 2.0 / (1u32 << self.level) as f32;
+        let s = -1.0 + (self.x as f32 + i) * size;
+        let t = -1.0 + (self.y as f32 + j) * size;
+        cube_sphere_direction(self.face as usize, s, t)
+    }
+
+    /// The angle between two neighbouring vertices of the chunk, at most.
+    fn cell_angle(self) -> f32 {
+        std::f32::consts::FRAC_PI_2 / (1u32 << self.level) as f32 / CHUNK_SEGMENTS as f32
+    }
+
+    /// Returns the direction to the centre of the chunk and the angle from it to the farthest
+    /// point of the chunk.
+    ///
+    /// The edges of a chunk are great circles, so its farthest points are its corners.
+    fn bounds(self) -> (Vec3, f32) {
+        let center = self.direction(0.5, 0.5);
+        let radius = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .map(|(i, j)| center.dot(self.direction(i, j)).clamp(-1.0, 1.0).acos())
+            .into_iter()
+            .fold(0.0, f32::max);
+        (center, radius)
+    }
+
+    /// Creates the vertices of the chunk, matching the indices of [chunk_indices].
+    fn vertices(self) -> Vec<Vertex> {
+        let side = CHUNK_SEGMENTS + 1;
+        let mut vertices = Vec::with_capacity((side * side + 4 * side) as usize);
+        for j in 0..side {
+            for i in 0..side {
+                let direction = self.direction(i as f32 / CHUNK_SEGMENTS as f32, j as f32 / CHUNK_SEGMENTS as f32);
+                vertices.push(normal_vertex(direction, GLOBE_RADIUS));
+            }
+        }
+
+        // The skirts hang down from the edges, hiding the cracks between a chunk and a neighbour
+        // of another level, whose edge vertices do not line up. They reach as deep as the middle
+        // of a cell of a chunk five levels coarser sags below the sphere.
+        let depth = (self.cell_angle() * 32.0 / 2.0).cos();
+        for index in edge_indices() {
+            let direction = vertices[index as usize].normal.into();
+            vertices.push(normal_vertex(direction, GLOBE_RADIUS * depth));
+        }
+        vertices
+    }
+}
+
+/// Returns the indices of the vertices along the edges of a chunk, going around it.
+fn edge_indices() -> impl Iterator<Item = u32> {
+    let side = CHUNK_SEGMENTS + 1;
+    let bottom = 0..side;
+    let right = (0..side).map(move |j| j * side + side - 1);
+    let top = (0..side).rev().map(move |i| (side - 1) * side + i);
+    let left = (0..side).rev().map(move |j| j * side);
+    bottom.chain(right).chain(top).chain(left)
+}
+
+/// Returns the triangles of a chunk, which are the same for all chunks.
+fn chunk_indices() -> Vec<u32> {
+    let side = CHUNK_SEGMENTS + 1;
+    let mut indices = vec![];
+    for j in 0..CHUNK_SEGMENTS {
+        for i in 0..CHUNK_SEGMENTS {
+            let a = j * side + i;
+            let (b, c, d) = (a + 1, a + side + 1, a + side);
+            indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+    }
+
+    // The skirts are seen from both sides, depending on which of the neighbours is finer.
+    let edge: Vec<u32> = edge_indices().collect();
+    let skirt_start = side * side;
+    for k in 0..edge.len() as u32 - 1 {
+        if k % side == side - 1 {
+            // The corners are repeated at the start of the next edge.
+            continue;
+        }
+        let (a, b) = (edge[k as usize], edge[k as usize + 1]);
+        let (c, d) = (skirt_start + k + 1, skirt_start + k);
+        indices.extend_from_slice(&[a, b, c, a, c, d, a, c, b, a, d, c]);
+    }
+    indices
+}
+
+/// Returns the planes bounding the view of a camera, pointing inwards.
+///
+/// A point `p` is inside the view if `plane.dot(p.extend(1.0)) >= 0` for every plane.
+fn frustum_planes(view_proj: Mat4) -> [Vec4; 6] {
+    let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+    // The depth in clip space goes from `0` to `w`.
+    [w + x, w - x, w + y, w - y, z, w - z]
+}
+
+/// A cached mesh of a chunk.
+#[derive(Debug)]
+struct ChunkMesh {
+    vertex_buffer: Buffer,
+    // The update in which the chunk was last visible.
+    last_used: u64,
+}
+
+/// Draws the globe as a quadtree of chunks on each face of a cube sphere, which is refined
+/// where the camera is close and keeps the number of triangles about the same at any zoom level.
+///
+/// Chunks are split until their triangles are within [MAX_ERROR_PIXELS] of the sphere on
+/// screen. Chunks on the far side of the globe or outside the view are not drawn.
+#[derive(Debug)]
+pub struct ChunkedGlobe {
+    // The triangles of a chunk, shared by all chunks.
+    index_buffer: Buffer,
+    num_indices: u32,
+    meshes: HashMap<ChunkId, ChunkMesh>,
+    // The chunks drawn by the next render pass.
+    visible: Vec<ChunkId>,
+    // The number of updates so far.
+    updates: u64,
+}
+
+impl ChunkedGlobe {
+    /// Creates a new [ChunkedGlobe], which draws nothing until it is updated.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    pub fn new(device: &Device) -> Self {
+        let indices = chunk_indices();
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            index_buffer,
+            num_indices: indices.len() as u32,
+            meshes: HashMap::new(),
+            visible: vec![],
+            updates: 0,
+        }
+    }
+
+    /// Picks the chunks seen by a camera and creates the meshes of the new ones.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `camera`: The camera looking at the globe.
+    /// * `height`: The height of the rendered image in pixels.
+    pub fn update(&mut self, device: &Device, camera: &OrbitCamera, height: u32) {
+        self.updates += 1;
+        let view = View::new(camera, height);
+        self.visible.clear();
+        for face in 0..CUBE_FACES.len() as u8 {
+            select(ChunkId::root(face), &view, &mut self.visible);
+        }
+
+        for &chunk in &self.visible {
+            let mesh = self.meshes.entry(chunk).or_insert_with(|| ChunkMesh {
+                vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Chunk Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&chunk.vertices()),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+                last_used: 0,
+            });
+            mesh.last_used = self.updates;
+        }
+
+        if self.meshes.len() > MAX_CACHED_CHUNKS {
+            let mut last_used: Vec<u64> = self.meshes.values().map(|mesh| mesh.last_used).collect();
+            last_used.sort_unstable_by(|a, b| b.cmp(a));
+            // The visible chunks were used last, so they are always kept.
+            let oldest_kept = last_used[MAX_CACHED_CHUNKS - 1];
+            self.meshes.retain(|_, mesh| mesh.last_used >= oldest_kept);
+        }
+    }
+
+    /// Draws the visible chunks with the pipeline and bind groups that are set.
+    pub fn draw(&self, r_pass: &mut RenderPass) {
+        r_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for chunk in &self.visible {
+            if let Some(mesh) = self.meshes.get(chunk) {
+                r_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                r_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+            }
+        }
+    }
+}
+
+/// Adds a chunk or its descendants to the visible chunks, unless they cannot be seen.
+fn select(chunk: ChunkId, view: &View, visible: &mut Vec<ChunkId>) {
+    let (center, angle) = chunk.bounds();
+    if !view.sees(center, angle) {
+        return;
+    }
+    if chunk.level < MAX_CHUNK_LEVEL && view.error_pixels(center, angle, chunk.cell_angle()) > MAX_ERROR_PIXELS {
+        for child in chunk.children() {
+            select(child, view, visible);
+        }
+    } else {
+        visible.push(chunk);
+    }
+}
+
+/// What is needed from a camera to pick the chunks it sees.
+struct View {
+    eye: Vec3,
+    planes: [Vec4; 6],
+    // The cosine of the angle from the point below the eye to the horizon.
+    horizon: f32,
+    // The size in pixels of something one unit wide at a distance of one unit.
+    pixels_per_unit: f32,
+}
+
+impl View {
+    fn new(camera: &OrbitCamera, height: u32) -> Self {
+        let eye = camera.eye_position();
+        Self {
+            eye,
+            planes: frustum_planes(camera.build_view_projection_matrix()),
+            horizon: (GLOBE_RADIUS / eye.length()).min(1.0),
+            pixels_per_unit: height as f32 / 2.0 / (camera.fovy / 2.0).tan(),
+        }
+    }
+
+    /// Returns the sphere containing the part of the globe within an angle around a direction.
+    fn bounding_sphere(center: Vec3, angle: f32) -> (Vec3, f32) {
+        if angle >= std::f32::consts::FRAC_PI_2 {
+            return (Vec3::ZERO, GLOBE_RADIUS);
+        }
+        (center * GLOBE_RADIUS * angle.cos(), GLOBE_RADIUS * angle.sin())
+    }
+
+    /// Whether any part of the globe within an angle around a direction is in front of the
+    /// horizon and inside the view.
+    fn sees(&self, center: Vec3, angle: f32) -> bool {
+        let to_eye = center.dot(self.eye.normalize()).clamp(-1.0, 1.0).acos();
+        if (to_eye - angle).max(0.0).cos() < self.horizon {
+            return false;
+        }
+        let (sphere_center, radius) = Self::bounding_sphere(center, angle);
+        self.planes
+            .iter()
+            .all(|plane| plane.dot(sphere_center.extend(1.0)) >= -radius * plane.truncate().length())
+    }
+
+    /// Returns how far in pixels the triangles of a chunk can be from the sphere on screen.
+    ///
+    /// Arguments:
+    ///
+    /// * `center`: The direction to the centre of the chunk.
+    /// * `angle`: The angle from the centre to the farthest point of the chunk.
+    /// * `cell_angle`: The angle between neighbouring vertices of the chunk.
+    fn error_pixels(&self, center: Vec3, angle: f32, cell_angle: f32) -> f32 {
+        // The middle of a chord sags below the sphere by this much.
+        let sag = GLOBE_RADIUS * (1.0 - (cell_angle / 2.0).cos());
+        let (sphere_center, radius) = Self::bounding_sphere(center, angle);
+        let distance = (self.eye.distance(sphere_center) - radius).max(f32::EPSILON);
+        sag / distance * self.pixels_per_unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(distance: f32) -> OrbitCamera {
+        let mut camera = OrbitCamera::new(distance, 0.0, 0.0, Vec3::ZERO, 1.0);
+        camera.bounds.min_distance = Some(GLOBE_RADIUS);
+        camera
+    }
+
+    fn visible(camera: &OrbitCamera) -> Vec<ChunkId> {
+        let view = View::new(camera, 1000);
+        let mut visible = vec![];
+        for face in 0..6 {
+            select(ChunkId::root(face), &view, &mut visible);
+        }
+        visible
+    }
+
+    #[test]
+    fn chunks_are_refined_near_the_camera() {
+        let far = visible(&camera(30.0));
+        let near = visible(&camera(GLOBE_RADIUS + 0.01));
+
+        // The face on the far side of the globe is never drawn.
+        assert!(far.len() < 6 && far.iter().all(|chunk| chunk.level == 0), "{far:?}");
+        assert!(near.iter().any(|chunk| chunk.level > 5), "{near:?}");
+        // The number of chunks stays about the same at any zoom level.
+        assert!(near.len() < 100, "{}", near.len());
+    }
+
+    #[test]
+    fn skirts_hang_below_the_edges() {
+        let chunk = ChunkId { face: 2, level: 3, x: 5, y: 1 };
+        let vertices = chunk.vertices();
+        let indices = chunk_indices();
+
+        let side = CHUNK_SEGMENTS + 1;
+        assert_eq!(vertices.len() as u32, side * side + 4 * side);
+        assert!(indices.iter().all(|&index| index < vertices.len() as u32));
+        for (k, index) in edge_indices().enumerate() {
+            let edge = Vec3::from(vertices[index as usize].position);
+            let skirt = Vec3::from(vertices[(side * side) as usize + k].position);
+            assert!(skirt.length() < edge.length());
+            assert!(skirt.normalize().distance(edge.normalize()) < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, RenderPass, RenderPipeline, TextureFormat};
+
+use super::{create_color_pipeline, with_opacity, Placement};
+use crate::{document::{Document, Geometry}, vertex::ColorVertex};
+
+/// Areas are drawn slightly above the globe so that they do not z-fight with its surface.
+///
+/// This stays below the altitude of lines, so outlines are drawn on top of the fill, and above
+/// the sag of a triangle edge spanning [MAX_EDGE_ANGLE].
+const FILL_ALTITUDE: f32 = 1.0005;
+
+/// The longest great-circle arc spanned by the edge of a fill triangle.
+const MAX_EDGE_ANGLE: f64 = 2.0 * std::f64::consts::PI / 180.0;
+
+/// Draws the filled polygons of a [Document] on top of the globe, in the colors of their styles.
+#[derive(Debug)]
+pub struct FillRenderer {
+    pipeline: RenderPipeline,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+    /// The indices of each visible layer, from the bottom to the top.
+    layers: Vec<Range<u32>>,
+}
+
+impl FillRenderer {
+    /// Creates a new [FillRenderer].
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `camera_bind_group_layout`: The layout of the camera uniform, bound to group `0`.
+    /// * `format`: The format of the color target.
+    /// * `sample_count`: The number of samples used for _MSAA_.
+    pub fn new(device: &Device, camera_bind_group_layout: &BindGroupLayout, format: TextureFormat, sample_count: u32) -> Self {
+        let pipeline = create_color_pipeline(
+            device,
+            "Fill Pipeline",
+            camera_bind_group_layout,
+            format,
+            sample_count,
+            wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            // The fills are translucent, so they must not hide each other.
+            false,
+        );
+
+        Self {
+            pipeline,
+            vertex_buffer: None,
+            index_buffer: None,
+            layers: vec![],
+        }
+    }
+
+    /// Triangulates the polygons of the visible layers of a document and uploads them.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `document`: The document whose polygons are drawn.
+    /// * `placement`: Whether the polygons are drawn on the globe or on a map.
+    pub fn rebuild(&mut self, device: &Device, document: &Document, placement: Placement) {
+        let mut vertices = vec![];
+        let mut indices: Vec<u32> = vec![];
+        self.layers.clear();
+        for layer in document.layers().filter(|layer| layer.visible) {
+            let start = indices.len() as u32;
+            for feature in document.features_in(layer.id()) {
+                let (Geometry::Polygon(polygon), Some(fill)) = (&feature.geometry, &document.style_of(feature).fill) else {
+                    continue;
+                };
+                let color = with_opacity(fill.color, layer.opacity);
+                let (points, triangles) = polygon.triangulate(MAX_EDGE_ANGLE);
+                let placed: Vec<_> = points.iter().map(|&p| placement.place(p, FILL_ALTITUDE)).collect();
+                let offset = vertices.len() as u32;
+                vertices.extend(placed.iter().map(|p| ColorVertex {
+                    position: p.unwrap_or_default().to_array(),
+                    color,
+                }));
+                // Triangles that cannot be shown completely are dropped, leaving their unused
+                // vertices behind.
+                for triangle in triangles.chunks_exact(3) {
+                    let corners = triangle.iter().map(|&i| placed[i as usize]);
+                    let Some(corners) = corners.collect::<Option<Vec<_>>>() else {
+                        continue;
+                    };
+                    if (0..3).all(|i| placement.connects(corners[i], corners[(i + 1) % 3])) {
+                        indices.extend(triangle.iter().map(|i| i + offset));
+                    }
+                }
+            }
+            self.layers.push(start..indices.len() as u32);
+        }
+
+        if indices.is_empty() {
+            self.vertex_buffer = None;
+            self.index_buffer = None;
+            return;
+        }
+        self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fill Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+        self.index_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fill Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }));
+    }
+
+    /// Records the draw commands of a layer into a render pass.
+    ///
+    /// Arguments:
+    ///
+    /// * `r_pass`: The render pass the globe is drawn in.
+    /// * `camera_bind_group`: The bind group of the camera uniform.
+    /// * `layer`: The position of the layer among the visible layers, with `0` being the bottom.
+    pub fn draw(&self, r_pass: &mut RenderPass, camera_bind_group: &BindGroup, layer: usize) {
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else {
+            return;
+        };
+        let Some(indices) = self.layers.get(layer).filter(|indices| !indices.is_empty()) else {
+            return;
+        };
+        r_pass.set_pipeline(&self.pipeline);
+        r_pass.set_bind_group(0, camera_bind_group, &[]);
+        r_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        r_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        r_pass.draw_indexed(indices.clone(), 0, 0..1);
+    }
+}
@@ -0,0 +1,163 @@
+pub mod base_map;
+pub mod chunks;
+pub mod fill;
+pub mod polyline;
+pub mod procedural;
+pub mod tiles;
+
+use std::borrow::Cow;
+
+use glam::{DVec3, Vec3};
+use wgpu::{BindGroup, BindGroupLayout, Device, FragmentState, PipelineLayoutDescriptor, PrimitiveState, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, TextureFormat, VertexState};
+
+pub use base_map::BaseMapRenderer;
+pub use chunks::ChunkedGlobe;
+pub use fill::FillRenderer;
+pub use polyline::{map_segment_angle, segment_angle, PolylineRenderer};
+pub use procedural::ProceduralGlobe;
+pub use tiles::TileAtlas;
+
+use crate::{geo::LatLon, projection::Projection, sphere::GLOBE_RADIUS, vertex::ColorVertex};
+
+/// The longest edge drawn on a flat map, relative to the width of the map.
+///
+/// Edges crossing the border of a map, such as the antimeridian of a cylindrical projection,
+/// connect points on opposite sides of the map and are dropped instead.
+const MAX_MAP_EDGE: f64 = 0.25;
+
+/// The largest distance in pixels allowed between the triangles of the globe and the sphere,
+/// which keeps its silhouette smooth.
+const MAX_ERROR_PIXELS: f32 = 0.5;
+
+/// The image wrapped around the globe, bound to group `0` of the pipelines drawing it.
+#[derive(Debug)]
+pub enum BaseTexture {
+    /// A single texture with a mip chain, drawn by the `fs_main` entry points of the shaders.
+    Image(BindGroup),
+    /// A tile pyramid streamed into an atlas, drawn by the `fs_tiled` entry points.
+    Tiles(Box<TileAtlas>),
+}
+
+impl BaseTexture {
+    /// The bind group of the texture.
+    pub fn bind_group(&self) -> &BindGroup {
+        match self {
+            BaseTexture::Image(bind_group) => bind_group,
+            BaseTexture::Tiles(atlas) => atlas.bind_group(),
+        }
+    }
+}
+
+/// Where the geometry of a document is placed in the scene.
+#[derive(Debug, Clone, Copy)]
+pub enum Placement<'a> {
+    /// On the surface of the globe.
+    Globe,
+    /// On a flat map in the `z = 0` plane, scaled to the size of the globe.
+    Map(&'a dyn Projection),
+}
+
+impl Placement<'_> {
+    /// Returns the position of a point of the unit sphere in the scene.
+    ///
+    /// Arguments:
+    ///
+    /// * `p`: The point on the unit sphere.
+    /// * `altitude`: The height above the surface relative to the radius of the globe, with `1`
+    ///   being on the surface. On a map, this lifts the point towards the camera.
+    ///
+    /// Returns `None` if the point cannot be shown on the map.
+    fn place(&self, p: DVec3, altitude: f32) -> Option<Vec3> {
+        match self {
+            Placement::Globe => Some(p.as_vec3() * GLOBE_RADIUS * altitude),
+            Placement::Map(projection) => {
+                let q = projection.forward(LatLon::from_unit_vector(p))?;
+                Some((q.as_vec2() * GLOBE_RADIUS).extend((altitude - 1.0) * GLOBE_RADIUS))
+            },
+        }
+    }
+
+    /// Whether a straight edge between two placed points can be drawn.
+    fn connects(&self, a: Vec3, b: Vec3) -> bool {
+        match self {
+            Placement::Globe => true,
+            Placement::Map(projection) => {
+                let (min, max) = projection.bounds();
+                let max_edge = MAX_MAP_EDGE * (max.x - min.x) * GLOBE_RADIUS as f64;
+                (a.truncate() - b.truncate()).length() as f64 <= max_edge
+            },
+        }
+    }
+}
+
+/// Returns a color drawn on a layer with an opacity, see [crate::document::Layer::opacity].
+fn with_opacity(color: [f32; 4], opacity: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], color[3] * opacity.clamp(0.0, 1.0)]
+}
+
+/// Creates a pipeline drawing [ColorVertex] geometry with alpha blending on top of the globe.
+///
+/// Arguments:
+///
+/// * `device`: The wgpu device used for rendering.
+/// * `label`: The label of the pipeline.
+/// * `camera_bind_group_layout`: The layout of the camera uniform, bound to group `0`.
+/// * `format`: The format of the color target.
+/// * `sample_count`: The number of samples used for _MSAA_.
+/// * `primitive`: How the vertices are assembled.
+/// * `depth_write_enabled`: Whether the geometry hides what is drawn after it.
+fn create_color_pipeline(
+    device: &Device,
+    label: &str,
+    camera_bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+    primitive: PrimitiveState,
+    depth_write_enabled: bool,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("Color Shader"),
+        source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/color.wgsl"))),
+    });
+
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[camera_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[ColorVertex::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
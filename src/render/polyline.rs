@@ -0,0 +1,463 @@
+use std::{borrow::Cow, ops::Range};
+
+use glam::{DVec3, Vec2, Vec3};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPass, RenderPipeline, TextureFormat};
+
+use super::{with_opacity, Placement};
+use crate::{document::{Document, Geometry, Stroke, StrokeWidth}, geo::{densify, LatLon, EARTH_RADIUS_KM}, graticule::GraticuleLine, sphere::GLOBE_RADIUS, vertex::LineSegment};
+
+/// Lines are drawn slightly above the globe so that they are not hidden by its surface.
+///
+/// Segments are straight chords, so this has to stay larger than the sag of a chord spanning
+/// [MAX_SEGMENT_ANGLE], otherwise long segments would dip below the surface.
+const LINE_ALTITUDE: f32 = 1.001;
+
+/// The longest great-circle arc drawn as a single segment, used when zoomed out.
+const MAX_SEGMENT_ANGLE: f64 = 2.0 * std::f64::consts::PI / 180.0;
+
+/// The shortest great-circle arc drawn as a single segment, used right above the surface.
+const MIN_SEGMENT_ANGLE: f64 = 0.01 * std::f64::consts::PI / 180.0;
+
+/// The radius of the circles marking point features, relative to the segment angle.
+///
+/// The segment angle scales with the height of the camera, which keeps the markers at roughly
+/// the same size on screen.
+const MARKER_RADIUS: f64 = 0.25;
+
+/// The number of segments of the circles marking point features.
+const MARKER_SEGMENTS: usize = 12;
+
+/// The color of the line that is currently being drawn.
+const PREVIEW_COLOR: [f32; 4] = [1.0, 0.8, 0.1, 1.0];
+
+/// The size of the image and the scale of the view, see `LineParams` in `line.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineParams {
+    viewport: [f32; 2],
+    /// The number of pixels covered by a world unit where the globe or map is closest to the camera.
+    pixels_per_unit: f32,
+    /// The length of a kilometre on the ground in world units.
+    units_per_kilometre: f32,
+}
+
+/// Draws the points, polylines, paths and polygon outlines of a [Document] on top of the globe.
+///
+/// Every segment of a line is an instance that `line.wgsl` expands into a quad on screen, so
+/// lines keep their width in pixels or kilometres at any zoom level, with anti-aliased edges,
+/// caps, joins and dashes as given by their [Stroke]. Dash patterns with more than two dashes
+/// repeat their first two.
+#[derive(Debug)]
+pub struct PolylineRenderer {
+    pipeline: RenderPipeline,
+    params: LineParams,
+    params_buffer: Buffer,
+    params_bind_group: BindGroup,
+    instance_buffer: Option<Buffer>,
+    /// The segments of each visible layer, from the bottom to the top.
+    layers: Vec<Range<u32>>,
+    /// The segments of the line that is being drawn.
+    preview: Range<u32>,
+    /// The segments of the graticule, which change far less often than the document.
+    graticule_buffer: Option<Buffer>,
+    graticule: Range<u32>,
+}
+
+impl PolylineRenderer {
+    /// Creates a new [PolylineRenderer].
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `camera_bind_group_layout`: The layout of the camera uniform, bound to group `0`.
+    /// * `format`: The format of the color target.
+    /// * `sample_count`: The number of samples used for _MSAA_.
+    pub fn new(device: &Device, camera_bind_group_layout: &BindGroupLayout, format: TextureFormat, sample_count: u32) -> Self {
+        let params_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("line_bind_group_layout"),
+        });
+        let params = LineParams {
+            viewport: [1.0, 1.0],
+            pixels_per_unit: 1.0,
+            units_per_kilometre: (GLOBE_RADIUS as f64 / EARTH_RADIUS_KM) as f32,
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Line Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("line_bind_group"),
+        });
+        let pipeline = create_line_pipeline(device, camera_bind_group_layout, &params_bind_group_layout, format, sample_count);
+
+        Self {
+            pipeline,
+            params,
+            params_buffer,
+            params_bind_group,
+            instance_buffer: None,
+            layers: vec![],
+            preview: 0..0,
+            graticule_buffer: None,
+            graticule: 0..0,
+        }
+    }
+
+    /// Adapts the lines to the size of the image and the zoom level.
+    ///
+    /// Arguments:
+    ///
+    /// * `queue`: The queue the uniform is written with.
+    /// * `viewport`: The width and height of the rendered image in pixels.
+    /// * `pixels_per_unit`: The number of pixels covered by a world unit at the centre of the
+    ///   view, which sets the length of dashes given in pixels.
+    pub fn update(&mut self, queue: &Queue, viewport: Vec2, pixels_per_unit: f32) {
+        let params = LineParams { viewport: viewport.to_array(), pixels_per_unit, ..self.params };
+        if params != self.params {
+            queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+            self.params = params;
+        }
+    }
+
+    /// Rebuilds the segments of the lines of the visible layers of a document.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `document`: The document whose lines are drawn.
+    /// * `preview`: A line that is still being drawn and is not part of the document yet.
+    /// * `segment_angle`: The longest arc in radians that is drawn as a single segment, see [segment_angle].
+    /// * `placement`: Whether the lines are drawn on the globe or on a map.
+    pub fn rebuild(
+        &mut self,
+        device: &Device,
+        document: &Document,
+        preview: Option<&[LatLon]>,
+        segment_angle: f64,
+        placement: Placement,
+    ) {
+        let mut segments = vec![];
+        self.layers.clear();
+        for layer in document.layers().filter(|layer| layer.visible) {
+            let start = segments.len() as u32;
+            for feature in document.features_in(layer.id()) {
+                let Some(stroke) = &document.style_of(feature).stroke else {
+                    continue;
+                };
+                let style = SegmentStyle::new(stroke, layer.opacity);
+                let mut push = |points: &[DVec3], closed| push_line(&mut segments, points, closed, segment_angle, &style, placement);
+                match &feature.geometry {
+                    Geometry::Point(point) => push(&marker(*point, MARKER_RADIUS * segment_angle), true),
+                    Geometry::Polyline(line) => push(&to_vectors(line), false),
+                    Geometry::Path(path) => push(&to_vectors(&path.flatten(segment_angle)), path.closed),
+                    Geometry::Polygon(polygon) => {
+                        for ring in std::iter::once(&polygon.exterior).chain(&polygon.holes) {
+                            push(&to_vectors(ring), true);
+                        }
+                    },
+                }
+            }
+            self.layers.push(start..segments.len() as u32);
+        }
+        let start = segments.len() as u32;
+        if let Some(line) = preview {
+            let style = SegmentStyle::new(&Stroke { color: PREVIEW_COLOR, ..Stroke::default() }, 1.0);
+            push_line(&mut segments, &to_vectors(line), false, segment_angle, &style, placement);
+        }
+        self.preview = start..segments.len() as u32;
+        self.instance_buffer = create_instance_buffer(device, &segments, "Line Instance Buffer");
+    }
+
+    /// Rebuilds the segments of a graticule, see [crate::graticule].
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `lines`: The parallels and meridians of the graticule, or none to hide it.
+    /// * `segment_angle`: The longest arc in radians that is drawn as a single segment, see [segment_angle].
+    /// * `placement`: Whether the lines are drawn on the globe or on a map.
+    pub fn rebuild_graticule(&mut self, device: &Device, lines: &[GraticuleLine], segment_angle: f64, placement: Placement) {
+        let mut segments = vec![];
+        for line in lines {
+            let style = SegmentStyle::new(&line.kind.stroke(), 1.0);
+            push_line(&mut segments, &to_vectors(&line.points), line.closed, segment_angle, &style, placement);
+        }
+        self.graticule = 0..segments.len() as u32;
+        self.graticule_buffer = create_instance_buffer(device, &segments, "Graticule Instance Buffer");
+    }
+
+    /// Records the draw commands of a layer into a render pass.
+    ///
+    /// Arguments:
+    ///
+    /// * `r_pass`: The render pass the globe is drawn in.
+    /// * `camera_bind_group`: The bind group of the camera uniform.
+    /// * `layer`: The position of the layer among the visible layers, with `0` being the bottom.
+    pub fn draw(&self, r_pass: &mut RenderPass, camera_bind_group: &BindGroup, layer: usize) {
+        if let Some(segments) = self.layers.get(layer) {
+            self.draw_range(r_pass, camera_bind_group, self.instance_buffer.as_ref(), segments.clone());
+        }
+    }
+
+    /// Records the draw commands of the line that is being drawn, on top of all layers.
+    pub fn draw_preview(&self, r_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        self.draw_range(r_pass, camera_bind_group, self.instance_buffer.as_ref(), self.preview.clone());
+    }
+
+    /// Records the draw commands of the graticule, below all layers.
+    pub fn draw_graticule(&self, r_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        self.draw_range(r_pass, camera_bind_group, self.graticule_buffer.as_ref(), self.graticule.clone());
+    }
+
+    fn draw_range(&self, r_pass: &mut RenderPass, camera_bind_group: &BindGroup, instance_buffer: Option<&Buffer>, segments: Range<u32>) {
+        let Some(instance_buffer) = instance_buffer else {
+            return;
+        };
+        if segments.is_empty() {
+            return;
+        }
+        r_pass.set_pipeline(&self.pipeline);
+        r_pass.set_bind_group(0, camera_bind_group, &[]);
+        r_pass.set_bind_group(1, &self.params_bind_group, &[]);
+        r_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        r_pass.draw(0..4, segments);
+    }
+}
+
+/// Creates a buffer holding [LineSegment] instances, or `None` if there are none.
+fn create_instance_buffer(device: &Device, segments: &[LineSegment], label: &str) -> Option<Buffer> {
+    (!segments.is_empty()).then(|| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(segments),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    })
+}
+
+/// Creates the pipeline drawing [LineSegment] instances as quads with `line.wgsl`.
+fn create_line_pipeline(
+    device: &Device,
+    camera_bind_group_layout: &BindGroupLayout,
+    params_bind_group_layout: &BindGroupLayout,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Line Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../assets/line.wgsl"))),
+    });
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Line Pipeline Layout"),
+        bind_group_layouts: &[camera_bind_group_layout, params_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Line Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[LineSegment::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // The quads are flat on screen and would cut into the curved globe, so `line.wgsl`
+            // hides the far side of the globe itself.
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Picks how finely great-circle arcs are subdivided when viewed from a camera at `distance`.
+///
+/// The closer the camera gets to the surface, the larger a single arc appears on screen, so
+/// it has to be split into more segments to keep looking curved.
+pub fn segment_angle(distance: f32) -> f64 {
+    segment_angle_for_height(((distance - GLOBE_RADIUS) / GLOBE_RADIUS) as f64)
+}
+
+/// Picks how finely great-circle arcs are subdivided on a flat map, see [segment_angle].
+///
+/// Arguments:
+///
+/// * `half_height`: Half the height of the visible part of the map in world units.
+pub fn map_segment_angle(half_height: f32) -> f64 {
+    segment_angle_for_height((half_height / GLOBE_RADIUS) as f64)
+}
+
+/// Picks the subdivision for a view whose height is given relative to the radius of the globe.
+fn segment_angle_for_height(height: f64) -> f64 {
+    let height = height.max(f32::EPSILON as f64);
+    // Snap to powers of two so that zooming only rebuilds the lines once in a while.
+    let height = 2f64.powf(height.log2().floor());
+    (height * MAX_SEGMENT_ANGLE).clamp(MIN_SEGMENT_ANGLE, MAX_SEGMENT_ANGLE)
+}
+
+/// A [Stroke] drawn on a layer, in the form of the fields of [LineSegment].
+#[derive(Clone, Copy)]
+struct SegmentStyle {
+    color: [f32; 4],
+    dash: [f32; 4],
+    width: f32,
+    flags: u32,
+}
+
+impl SegmentStyle {
+    fn new(stroke: &Stroke, opacity: f32) -> Self {
+        let mut dash = [0.0; 4];
+        for (slot, length) in dash.iter_mut().zip(stroke.dash_pattern()) {
+            *slot = length;
+        }
+        let mut flags = (stroke.cap as u32) << LineSegment::CAP_SHIFT | (stroke.join as u32) << LineSegment::JOIN_SHIFT;
+        if let StrokeWidth::Kilometres(_) = stroke.width {
+            flags |= LineSegment::KILOMETRES;
+        }
+        Self {
+            color: with_opacity(stroke.color, opacity),
+            dash,
+            width: stroke.width.value().max(0.0),
+            flags,
+        }
+    }
+}
+
+fn to_vectors(line: &[LatLon]) -> Vec<DVec3> {
+    line.iter().map(|p| p.to_unit_vector()).collect()
+}
+
+/// Returns the corners of a small circle around a point, without repeating the first one.
+fn marker(point: LatLon, radius: f64) -> Vec<DVec3> {
+    let center = point.to_unit_vector();
+    let e1 = center.any_orthonormal_vector();
+    let e2 = center.cross(e1);
+    (0..MARKER_SEGMENTS)
+        .map(|i| {
+            let angle = i as f64 / MARKER_SEGMENTS as f64 * std::f64::consts::TAU;
+            center * radius.cos() + (e1 * angle.cos() + e2 * angle.sin()) * radius.sin()
+        })
+        .collect()
+}
+
+/// Appends the segments of a line following the great circles between points of the unit sphere.
+///
+/// The line is split where it cannot be shown with the placement, and each part gets caps of
+/// its own. A closed ring that can be shown completely has no ends and is joined all around.
+fn push_line(segments: &mut Vec<LineSegment>, points: &[DVec3], closed: bool, segment_angle: f64, style: &SegmentStyle, placement: Placement) {
+    let mut style = *style;
+    if let Placement::Globe = placement {
+        style.flags |= LineSegment::ON_GLOBE;
+    }
+    let style = &style;
+    let mut ring = points.to_vec();
+    if closed {
+        ring.extend(points.first());
+    }
+    let mut placed: Vec<_> = densify(&ring, segment_angle).iter().map(|&p| placement.place(p, LINE_ALTITUDE)).collect();
+    placed.dedup();
+    let links = |p: Option<Vec3>, q: Option<Vec3>| matches!((p, q), (Some(a), Some(b)) if placement.connects(a, b));
+
+    if closed {
+        placed.pop();
+        let n = placed.len();
+        match (0..n).find(|&i| !links(placed[i], placed[(i + 1) % n])) {
+            None => {
+                let ring: Vec<_> = placed.iter().flatten().copied().collect();
+                push_run(segments, &ring, true, style);
+                return;
+            },
+            // Start right after a gap, so that the part crossing the first point stays whole.
+            Some(i) => placed.rotate_left(i + 1),
+        }
+    }
+
+    let mut run = vec![];
+    for (i, &p) in placed.iter().enumerate() {
+        run.extend(p);
+        if placed.get(i + 1).is_none_or(|&q| !links(p, q)) {
+            push_run(segments, &run, false, style);
+            run.clear();
+        }
+    }
+}
+
+/// Appends the segments between consecutive points in the scene.
+fn push_run(segments: &mut Vec<LineSegment>, points: &[Vec3], closed: bool, style: &SegmentStyle) {
+    let n = points.len();
+    if n < 2 {
+        return;
+    }
+    let closed = closed && n > 2;
+    let count = if closed { n } else { n - 1 };
+    let mut along = 0.0;
+    for i in 0..count {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (prev, next) = if closed {
+            (Some(points[(i + n - 1) % n]), Some(points[(i + 2) % n]))
+        } else {
+            (i.checked_sub(1).map(|j| points[j]), points.get(i + 2).copied())
+        };
+        let mut flags = style.flags;
+        if prev.is_none() {
+            flags |= LineSegment::LINE_START;
+        }
+        if next.is_none() {
+            flags |= LineSegment::LINE_END;
+        }
+        let length = a.distance(b);
+        segments.push(LineSegment {
+            prev: prev.unwrap_or(a).to_array(),
+            a: a.to_array(),
+            b: b.to_array(),
+            next: next.unwrap_or(b).to_array(),
+            color: style.color,
+            dash: style.dash,
+            width: style.width,
+            along: [along, along + length],
+            flags,
+        });
+        along += length;
+    }
+}
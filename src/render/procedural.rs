@@ -0,0 +1,129 @@
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPass};
+
+use super::MAX_ERROR_PIXELS;
+use crate::{camera::OrbitCamera, sphere::GLOBE_RADIUS};
+
+/// The fewest rows of cells the sphere is generated with.
+const MIN_ROWS: u32 = 16;
+
+/// The most rows of cells the sphere is generated with, which keeps the number of vertices about
+/// as large as that of the UV sphere built on the CPU. Closer to the surface, the chunked globe
+/// draws smoother silhouettes.
+const MAX_ROWS: u32 = 512;
+
+/// The resolution of the generated sphere, see `SphereParams` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct SphereParams {
+    rows: u32,
+    radius: f32,
+}
+
+/// Draws the globe as a UV sphere that the vertex shader generates from the index of each vertex,
+/// without any vertex or index buffer.
+///
+/// The resolution is a uniform, which is picked for the distance of the camera in every update,
+/// so that the sphere is re-tessellated without building any buffers.
+#[derive(Debug)]
+pub struct ProceduralGlobe {
+    params: SphereParams,
+    params_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl ProceduralGlobe {
+    /// Creates the layout of the uniform of the sphere, bound to group `3` of the pipelines.
+    pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("sphere_bind_group_layout"),
+        })
+    }
+
+    /// Creates a new [ProceduralGlobe] with the fewest rows.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `layout`: The layout created by [ProceduralGlobe::create_bind_group_layout].
+    pub fn new(device: &Device, layout: &BindGroupLayout) -> Self {
+        let params = SphereParams { rows: MIN_ROWS, radius: GLOBE_RADIUS };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sphere Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("sphere_bind_group"),
+        });
+
+        Self { params, params_buffer, bind_group }
+    }
+
+    /// Picks the resolution of the sphere for a camera.
+    ///
+    /// Arguments:
+    ///
+    /// * `queue`: The queue the uniform is written with.
+    /// * `camera`: The camera looking at the globe.
+    /// * `height`: The height of the rendered image in pixels.
+    pub fn update(&mut self, queue: &Queue, camera: &OrbitCamera, height: u32) {
+        let params = SphereParams { rows: rows_for_view(camera, height), radius: GLOBE_RADIUS };
+        if params != self.params {
+            queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+            self.params = params;
+        }
+    }
+
+    /// Draws the sphere with the pipeline and the bind groups `0` to `2` that are set.
+    pub fn draw(&self, r_pass: &mut RenderPass) {
+        let columns = 2 * self.params.rows;
+        r_pass.set_bind_group(3, &self.bind_group, &[]);
+        r_pass.draw(0..self.params.rows * columns * 6, 0..1);
+    }
+}
+
+/// Returns the number of rows of cells that keep the triangles within [MAX_ERROR_PIXELS] of the
+/// sphere on screen, where it is closest to the camera.
+fn rows_for_view(camera: &OrbitCamera, height: u32) -> u32 {
+    let distance = (camera.distance - GLOBE_RADIUS).max(f32::EPSILON);
+    let pixels_per_unit = height as f32 / 2.0 / (camera.fovy / 2.0).tan();
+    // The middle of a chord spanning the angle `a` sags by about `r · a² / 8` below the sphere.
+    let max_sag = MAX_ERROR_PIXELS * distance / pixels_per_unit;
+    let cell_angle = (8.0 * max_sag / GLOBE_RADIUS).sqrt();
+    ((std::f32::consts::PI / cell_angle).ceil() as u32).clamp(MIN_ROWS, MAX_ROWS)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn rows_grow_towards_the_surface() {
+        let camera = |distance| OrbitCamera::new(distance, 0.0, 0.0, Vec3::ZERO, 1.0);
+
+        let far = rows_for_view(&camera(100.0), 600);
+        let middle = rows_for_view(&camera(15.0), 600);
+        let near = rows_for_view(&camera(GLOBE_RADIUS), 600);
+
+        assert_eq!(far, MIN_ROWS);
+        assert!(far < middle && middle < near, "{far} {middle} {near}");
+        assert_eq!(near, MAX_ROWS);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{collections::{HashMap, HashSet}, ops::Range};
+
+use image::RgbaImage;
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Device, Queue};
+
+use crate::tiles::{tile_counts, TileError, TileId, TileLoader, TilePyramid, TILE_BORDER, TILE_SIZE};
+
+/// The width and height in pixels of a slot of the atlas, which holds one tile with its border.
+const SLOT_SIZE: u32 = TILE_SIZE + 2 * TILE_BORDER;
+
+/// The largest width and height of the atlas in pixels.
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+/// The number of tiles read from disk at the same time.
+///
+/// Requests beyond this are made again in later frames if the tiles are still visible, so that
+/// tiles that scrolled out of view on the way are not read at all.
+const MAX_PENDING: usize = 16;
+
+/// The number of tiles of level `0`, which always stay in the atlas as a fallback.
+const ROOT_TILES: usize = 2;
+
+/// The parameters needed by the shaders to find a tile in the atlas, see `tiles.wgsl`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TileParams {
+    max_level: u32,
+    tile_size: f32,
+    border: f32,
+    atlas_size: f32,
+}
+
+/// The contents of the page table, kept so that only the texels under changed tiles are rewritten.
+#[derive(Debug)]
+struct PageTable {
+    max_level: u32,
+    columns: u32,
+    /// The slot column, slot row and level of the tile each texel points to, and whether it
+    /// points to one at all. Rows follow each other from the north pole to the south pole.
+    entries: Vec<[u8; 4]>,
+}
+
+impl PageTable {
+    fn new(max_level: u32) -> Self {
+        let (columns, rows) = tile_counts(max_level);
+        Self { max_level, columns, entries: vec![[0; 4]; (columns * rows) as usize] }
+    }
+
+    /// The texels covered by a tile, as the column and row of the first one and the number of
+    /// texels along each side.
+    fn footprint(&self, tile: TileId) -> (u32, u32, u32) {
+        let size = 1 << (self.max_level - tile.level);
+        (tile.x * size, tile.y * size, size)
+    }
+
+    /// Points the texels under `region` to the finest resident tile covering each of them.
+    ///
+    /// Arguments:
+    ///
+    /// * `region`: The tile whose texels are rewritten.
+    /// * `resident`: The tiles in the atlas with their entries.
+    fn update(&mut self, region: TileId, resident: impl IntoIterator<Item = (TileId, [u8; 4])>) {
+        let mut tiles: Vec<_> = resident
+            .into_iter()
+            .filter(|&(tile, _)| tile.covers(region) || region.covers(tile))
+            .collect();
+        // Finer tiles overwrite the coarser ones they lie in.
+        tiles.sort_by_key(|&(tile, _)| tile);
+
+        let (x0, y0, size) = self.footprint(region);
+        self.fill(x0..x0 + size, y0..y0 + size, [0; 4]);
+        for (tile, entry) in tiles {
+            let (x, y, tile_size) = self.footprint(tile);
+            self.fill(x.max(x0)..(x + tile_size).min(x0 + size), y.max(y0)..(y + tile_size).min(y0 + size), entry);
+        }
+    }
+
+    fn fill(&mut self, columns: Range<u32>, rows: Range<u32>, entry: [u8; 4]) {
+        for y in rows {
+            let row = (y * self.columns) as usize;
+            self.entries[row + columns.start as usize..row + columns.end as usize].fill(entry);
+        }
+    }
+}
+
+/// A tile in a slot of the atlas.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    tile: TileId,
+    // The last frame in which the tile was wanted.
+    last_used: u64,
+}
+
+/// Streams the tiles of a [TilePyramid] into a texture atlas on the GPU.
+///
+/// The atlas is a grid of slots that each hold one tile. Which tile is in which slot is stored
+/// in the page table, a texture with one texel per tile of the finest level. Each texel points to
+/// the finest tile in the atlas that covers it, so that coarser tiles stand in for tiles that are
+/// still being read.
+#[derive(Debug)]
+pub struct TileAtlas {
+    loader: TileLoader,
+    max_level: u32,
+    atlas: wgpu::Texture,
+    page_table: wgpu::Texture,
+    page_entries: PageTable,
+    bind_group: BindGroup,
+    slots_per_row: u32,
+    slots: Vec<Option<Slot>>,
+    resident: HashMap<TileId, usize>,
+    // Tiles that could not be read, which are not requested again.
+    failed: HashSet<TileId>,
+    frame: u64,
+    // The tiles that entered or left the atlas since the page table was last written.
+    changed: Vec<TileId>,
+}
+
+impl TileAtlas {
+    /// Creates the layout of the bind group of a [TileAtlas], bound to group `0`.
+    ///
+    /// Binding `1` is the sampler, followed by the atlas, the page table and the [TileParams].
+    pub fn create_bind_group_layout(device: &Device) -> BindGroupLayout {
+        let texture = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture(2, wgpu::TextureSampleType::Float { filterable: true }),
+                texture(3, wgpu::TextureSampleType::Uint),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("tile_bind_group_layout"),
+        })
+    }
+
+    /// Creates a new [TileAtlas] and starts reading the tiles of level `0`.
+    ///
+    /// Arguments:
+    ///
+    /// * `device`: The wgpu device used for rendering.
+    /// * `layout`: The layout created by [TileAtlas::create_bind_group_layout].
+    /// * `pyramid`: The pyramid whose tiles are streamed.
+    pub fn new(device: &Device, layout: &BindGroupLayout, pyramid: TilePyramid) -> Self {
+        let max_level = pyramid.max_level();
+        let atlas_size = device.limits().max_texture_dimension_2d.min(MAX_ATLAS_SIZE);
+        // Slots are addressed with 8 bits in the page table.
+        let slots_per_row = (atlas_size / SLOT_SIZE).min(u8::MAX as u32);
+        let atlas_size = slots_per_row * SLOT_SIZE;
+
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("tile_atlas"),
+            size: wgpu::Extent3d { width: atlas_size, height: atlas_size, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let (columns, rows) = tile_counts(max_level);
+        let page_table = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("tile_page_table"),
+            size: wgpu::Extent3d { width: columns, height: rows, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let params = TileParams {
+            max_level,
+            tile_size: TILE_SIZE as f32,
+            border: TILE_BORDER as f32,
+            atlas_size: atlas_size as f32,
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tile Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        // Mip levels are not needed, since the level of the tiles follows the zoom level.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&atlas.create_view(&Default::default())),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&page_table.create_view(&Default::default())),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("tile_bind_group"),
+        });
+
+        let mut loader = TileLoader::new(pyramid);
+        for x in 0..ROOT_TILES as u32 {
+            loader.request(TileId { level: 0, x, y: 0 });
+        }
+
+        Self {
+            loader,
+            max_level,
+            atlas,
+            page_table,
+            page_entries: PageTable::new(max_level),
+            bind_group,
+            slots_per_row,
+            slots: vec![None; (slots_per_row * slots_per_row) as usize],
+            resident: HashMap::new(),
+            failed: HashSet::new(),
+            frame: 0,
+            changed: vec![],
+        }
+    }
+
+    /// The bind group of the atlas, see [TileAtlas::create_bind_group_layout].
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// The finest level of the pyramid.
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    /// The number of tiles that fit into the atlas next to the tiles of level `0`.
+    pub fn capacity(&self) -> usize {
+        self.slots.len() - ROOT_TILES
+    }
+
+    /// Whether tiles are still being read.
+    pub fn is_loading(&self) -> bool {
+        self.loader.pending() > 0
+    }
+
+    /// Uploads the tiles read since the last update and requests the visible ones.
+    ///
+    /// Arguments:
+    ///
+    /// * `queue`: The queue the tiles are uploaded with.
+    /// * `wanted`: The visible tiles, the most important first. Only as many as fit into the
+    ///   atlas are considered.
+    pub fn update(&mut self, queue: &Queue, wanted: &[TileId]) {
+        self.frame += 1;
+        let wanted = &wanted[..wanted.len().min(self.capacity())];
+        for tile in wanted {
+            if let Some(&slot) = self.resident.get(tile)
+                && let Some(slot) = &mut self.slots[slot]
+            {
+                slot.last_used = self.frame;
+            }
+        }
+
+        let loaded = self.loader.receive();
+        self.upload(queue, loaded);
+
+        for &tile in wanted {
+            if !self.resident.contains_key(&tile)
+                && !self.failed.contains(&tile)
+                && self.loader.pending() < MAX_PENDING
+            {
+                self.loader.request(tile);
+            }
+        }
+
+        self.write_page_table(queue);
+    }
+
+    /// Waits for the requested tiles and uploads them, used when rendering a single image.
+    pub fn finish_loading(&mut self, queue: &Queue) {
+        let loaded = self.loader.receive_all();
+        self.upload(queue, loaded);
+        self.write_page_table(queue);
+    }
+
+    /// Copies tiles into free slots of the atlas, replacing the tiles that were not wanted for the longest time.
+    fn upload(&mut self, queue: &Queue, loaded: Vec<(TileId, Result<RgbaImage, TileError>)>) {
+        for (tile, image) in loaded {
+            let image = match image {
+                Ok(image) => image,
+                Err(err) => {
+                    log::warn!("Failed to read the tile {tile:?}: {err}");
+                    self.failed.insert(tile);
+                    continue;
+                },
+            };
+            let Some(slot) = self.free_slot(tile) else {
+                continue;
+            };
+
+            if let Some(old) = self.slots[slot].take() {
+                self.resident.remove(&old.tile);
+                self.changed.push(old.tile);
+            }
+            self.slots[slot] = Some(Slot { tile, last_used: self.frame });
+            self.resident.insert(tile, slot);
+            self.changed.push(tile);
+
+            let slot = slot as u32;
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &self.atlas,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: slot % self.slots_per_row * SLOT_SIZE,
+                        y: slot / self.slots_per_row * SLOT_SIZE,
+                        z: 0,
+                    },
+                },
+                &image,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * SLOT_SIZE),
+                    rows_per_image: Some(SLOT_SIZE),
+                },
+                wgpu::Extent3d { width: SLOT_SIZE, height: SLOT_SIZE, depth_or_array_layers: 1 },
+            );
+        }
+    }
+
+    /// Returns the slot a tile is stored in: an empty one if possible, otherwise the one that was
+    /// not wanted for the longest time. Tiles of level `0` are never replaced.
+    fn free_slot(&self, tile: TileId) -> Option<usize> {
+        if let Some(&slot) = self.resident.get(&tile) {
+            return Some(slot);
+        }
+        if let Some(slot) = self.slots.iter().position(Option::is_none) {
+            return Some(slot);
+        }
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.map(|slot| (i, slot)))
+            .filter(|(_, slot)| slot.tile.level > 0 && slot.last_used < self.frame)
+            .min_by_key(|(_, slot)| slot.last_used)
+            .map(|(i, _)| i)
+    }
+
+    /// Points the texels of the page table under the tiles that entered or left the atlas to the
+    /// finest tile in the atlas covering them, and uploads only those texels.
+    fn write_page_table(&mut self, queue: &Queue) {
+        let mut changed = std::mem::take(&mut self.changed);
+        // Coarser tiles first, so that the finer ones inside them can be skipped.
+        changed.sort();
+        changed.dedup();
+        let mut written: Vec<TileId> = vec![];
+        for region in changed {
+            if written.iter().any(|tile| tile.covers(region)) {
+                continue;
+            }
+            let slots_per_row = self.slots_per_row as usize;
+            let resident = self.resident.iter().map(|(&tile, &slot)| {
+                (tile, [(slot % slots_per_row) as u8, (slot / slots_per_row) as u8, tile.level as u8, u8::MAX])
+            });
+            self.page_entries.update(region, resident);
+
+            let (x, y, size) = self.page_entries.footprint(region);
+            let columns = self.page_entries.columns;
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &self.page_table,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                },
+                bytemuck::cast_slice(&self.page_entries.entries),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 4 * (y * columns + x) as u64,
+                    bytes_per_row: Some(4 * columns),
+                    rows_per_image: Some(size),
+                },
+                wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            );
+            written.push(region);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(slot: u8, tile: TileId) -> [u8; 4] {
+        [slot, 0, tile.level as u8, u8::MAX]
+    }
+
+    /// Fills the whole table from scratch, as a reference for the updates of single regions.
+    fn rebuilt(max_level: u32, resident: &[(TileId, [u8; 4])]) -> Vec<[u8; 4]> {
+        let mut table = PageTable::new(max_level);
+        for x in 0..2 {
+            table.update(TileId { level: 0, x, y: 0 }, resident.iter().copied());
+        }
+        table.entries
+    }
+
+    #[test]
+    fn page_table_points_to_the_finest_resident_tile() {
+        let root = TileId { level: 0, x: 0, y: 0 };
+        let child = TileId { level: 1, x: 1, y: 1 };
+        let grandchild = TileId { level: 2, x: 2, y: 3 };
+        let resident = [(root, entry(1, root)), (grandchild, entry(3, grandchild)), (child, entry(2, child))];
+        let table = rebuilt(2, &resident);
+
+        // Level 2 has 8 columns and 4 rows of texels.
+        assert_eq!(table.len(), 32);
+        assert_eq!(table[0], entry(1, root));
+        assert_eq!(table[2 * 8 + 2], entry(2, child));
+        assert_eq!(table[3 * 8 + 2], entry(3, grandchild));
+        // The eastern half has no resident tile at all.
+        assert_eq!(table[4], [0; 4]);
+    }
+
+    #[test]
+    fn page_table_updates_only_changed_regions() {
+        let max_level = 3;
+        let root = TileId { level: 0, x: 1, y: 0 };
+        let child = TileId { level: 1, x: 2, y: 0 };
+        let leaf = TileId { level: 3, x: 9, y: 2 };
+        let elsewhere = TileId { level: 2, x: 6, y: 3 };
+        let mut resident = vec![(root, entry(1, root)), (elsewhere, entry(4, elsewhere))];
+        let mut table = PageTable::new(max_level);
+        table.entries = rebuilt(max_level, &resident);
+
+        // Tiles arriving one by one only rewrite their own texels.
+        for tile in [child, leaf] {
+            resident.push((tile, entry(resident.len() as u8 + 1, tile)));
+            table.update(tile, resident.iter().copied());
+            assert_eq!(table.entries, rebuilt(max_level, &resident));
+        }
+
+        // An evicted tile makes the coarser tile it lies in visible again.
+        resident.retain(|&(tile, _)| tile != child);
+        table.update(child, resident.iter().copied());
+        assert_eq!(table.entries, rebuilt(max_level, &resident));
+        let (x, y, _) = table.footprint(child);
+        assert_eq!(table.entries[(y * table.columns + x) as usize], entry(1, root));
+    }
+}
@@ -0,0 +1,1414 @@
+use std::{borrow::Cow, fmt, path::Path, sync::mpsc};
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use image::{DynamicImage, RgbaImage};
+use wgpu::{util::DeviceExt, Adapter, BindGroup, BindGroupLayout, Buffer, Color, CommandEncoderDescriptor, Device, DeviceDescriptor, Features, FragmentState, Instance, Limits, LoadOp, MemoryHints, Operations, PipelineLayout, PipelineLayoutDescriptor, PowerPreference, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, RequestAdapterError, RequestAdapterOptions, RequestDeviceError, ShaderModuleDescriptor, ShaderSource, StoreOp, TextureFormat, TextureView, Trace, VertexState};
+
+use crate::{camera::{Camera, CameraUniform, MapCamera, OrbitCamera}, document::Document, format::svg::SvgOptions, geo::LatLon, graticule::{self, Extent, Graticule}, light::LightUniform, project::CameraState, projection::{Projection, ProjectionKind}, render::{map_segment_angle, segment_angle, BaseMapRenderer, BaseTexture, ChunkedGlobe, FillRenderer, ProceduralGlobe, Placement, PolylineRenderer, TileAtlas}, sphere::{SphereMesh, GLOBE_RADIUS}, texture::{load_base_image, Texture}, tiles::{TileError, TileId, TilePyramid, TILE_SIZE}, vertex::Vertex};
+
+/// The numbers of samples per pixel that can be picked for multisample anti-aliasing, where `1`
+/// turns it off.
+pub const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// The number of samples per pixel used if the adapter supports it.
+const DEFAULT_SAMPLE_COUNT: u32 = 4;
+
+/// How far the flat map can be zoomed in, as half the visible height in world units.
+const MAP_MIN_HALF_HEIGHT: f32 = 1e-4 * GLOBE_RADIUS;
+
+/// How far the flat map can be zoomed out, as half the visible height in world units.
+const MAP_MAX_HALF_HEIGHT: f32 = 4.0 * GLOBE_RADIUS;
+
+/// The number of rows and columns of pixels picked to find the visible tiles of a tile pyramid.
+const TILE_SAMPLES: u32 = 24;
+
+/// The format of the images rendered by a headless renderer.
+const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+fn create_camera(width: u32, height: u32) -> (OrbitCamera, CameraUniform) {
+    let mut camera = OrbitCamera::new(
+        15.0,
+        0.0,
+        0.0,
+        Vec3::new(0.0, 0.0, 0.0),
+        width as f32 / height as f32,
+    );
+    camera.bounds.min_distance = Some(10.0);
+
+    let mut camera_uniform = CameraUniform::default();
+    camera_uniform.update_view_proj(&camera);
+
+    (camera, camera_uniform)
+}
+
+fn create_map_camera(width: u32, height: u32) -> MapCamera {
+    let mut camera = MapCamera::new(
+        GLOBE_RADIUS,
+        Vec2::new(width as f32, height as f32),
+        GLOBE_RADIUS,
+    );
+    camera.min_half_height = MAP_MIN_HALF_HEIGHT;
+    camera.max_half_height = MAP_MAX_HALF_HEIGHT;
+    camera
+}
+
+fn create_camera_bind_group(device: &Device, camera_uniform: &CameraUniform) -> (BindGroup, BindGroupLayout, Buffer) {
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera Buffer"),
+        contents: bytemuck::cast_slice(&[*camera_uniform]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+
+    let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("camera_bind_group_layout"),
+            });
+
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &camera_bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+        label: Some("camera_bind_group"),
+    });
+
+    (camera_bind_group, camera_bind_group_layout, camera_buffer)
+
+}
+
+fn create_light(device: &Device) -> (BindGroup, BindGroupLayout, Buffer, LightUniform) {
+    let light_uniform = LightUniform {
+        position: [2.0, 6.0, 4.0, 1.0],
+        color: [1.0, 1.0, 1.0, 0.1],
+    };
+    let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Light VB"),
+        contents: bytemuck::cast_slice(&[light_uniform]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let light_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: None,
+        }
+    );
+    let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &light_bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: light_buffer.as_entire_binding(),
+        }],
+        label: None,
+    });
+
+    (light_bind_group, light_bind_group_layout, light_buffer, light_uniform)
+}
+
+fn create_texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
+
+fn create_diffuse_bind_group(device: &Device, texture_bind_group_layout: &BindGroupLayout, diffuse_texture: &Texture) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+            },
+        ],
+        label: Some("diffuse_bind_group"),
+    })
+}
+
+/// Creates the texture drawn into before it is resolved, unless anti-aliasing is turned off.
+fn create_multisampled_framebuffer(device: &Device, format: TextureFormat, width: u32, height: u32, sample_count: u32) -> Option<TextureView> {
+    (sample_count > 1).then(|| Texture::create_multisampled_framebuffer(device, format, width, height, sample_count, "multisampled_framebuffer"))
+}
+
+fn create_globe_mesh(device: &Device, mesh: SphereMesh, sphere_bind_group_layout: &BindGroupLayout) -> GlobeMesh {
+    let Some((vertices, indices)) = mesh.vertices(GLOBE_RADIUS) else {
+        return match mesh {
+            SphereMesh::Procedural => GlobeMesh::Procedural(ProceduralGlobe::new(device, sphere_bind_group_layout)),
+            _ => GlobeMesh::Chunks(ChunkedGlobe::new(device)),
+        };
+    };
+
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    let num_indices = indices.len() as u32;
+
+    GlobeMesh::Buffers { vertex_buffer, index_buffer, num_indices }
+}
+
+/// Creates a pipeline drawing the globe, with the fragment shader taken from the module of the
+/// vertex shader.
+fn create_pipeline(device: &Device, vertex: VertexState, fragment_entry_point: &str, layout: PipelineLayout, swap_chain_format: TextureFormat, sample_count: u32) -> RenderPipeline {
+    let shader = vertex.module;
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(&layout),
+        vertex,
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry_point),
+            targets: &[Some(swap_chain_format.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            // Requires Features::NON_FILL_POLYGON_MODE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Requests the device used for rendering from an adapter.
+pub async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), RequestDeviceError> {
+    adapter
+        .request_device(
+            &DeviceDescriptor {
+                label: None,
+                // Allows the sample counts the adapter supports beyond the 1 and 4 of WebGPU.
+                required_features: adapter.features() & Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                required_limits: Limits::downlevel_webgl2_defaults()
+                    .using_resolution(adapter.limits()),
+                memory_hints: MemoryHints::Performance,
+                trace: Trace::Off
+            }
+        )
+        .await
+}
+
+/// Returns the numbers of samples per pixel out of [SAMPLE_COUNTS] that can be used for
+/// multisample anti-aliasing when drawing into textures of a format.
+///
+/// Arguments:
+///
+/// * `adapter`: The adapter `device` was requested from.
+/// * `device`: The wgpu device used for rendering.
+/// * `format`: The format of the textures that will be drawn into.
+pub fn supported_sample_counts(adapter: &Adapter, device: &Device, format: TextureFormat) -> Vec<u32> {
+    let features = |format: TextureFormat| {
+        if device.features().contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(device.features())
+        }
+    };
+    let (color, depth) = (features(format), features(wgpu::TextureFormat::Depth32Float));
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| {
+            let resolves = count == 1 || color.flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+            color.flags.sample_count_supported(count) && depth.flags.sample_count_supported(count) && resolves
+        })
+        .collect()
+}
+
+/// An error that occurred while setting up or using a headless [Renderer].
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// No adapter is available, such as when a software adapter is required but not installed.
+    Adapter(RequestAdapterError),
+    Device(RequestDeviceError),
+    /// The rendered image could not be read back from the GPU.
+    Readback(String),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Adapter(err) => write!(f, "no graphics adapter: {err}"),
+            HeadlessError::Device(err) => write!(f, "no graphics device: {err}"),
+            HeadlessError::Readback(err) => write!(f, "failed to read the rendered image: {err}"),
+            HeadlessError::Image(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<image::ImageError> for HeadlessError {
+    fn from(err: image::ImageError) -> Self {
+        HeadlessError::Image(err)
+    }
+}
+
+/// The layouts of the bind groups of the pipelines drawing the globe.
+#[derive(Debug)]
+struct GlobeLayouts {
+    texture: BindGroupLayout,
+    tiles: BindGroupLayout,
+    camera: BindGroupLayout,
+    light: BindGroupLayout,
+    // The resolution of the procedural sphere.
+    sphere: BindGroupLayout,
+}
+
+/// The pipelines drawing the globe with either kind of [BaseTexture].
+#[derive(Debug)]
+struct GlobePipelines {
+    // Draw meshes with texture coordinates.
+    image: RenderPipeline,
+    tiled: RenderPipeline,
+    // Draw meshes that compute their texture coordinates from their normals.
+    normal_image: RenderPipeline,
+    normal_tiled: RenderPipeline,
+    // Draw the sphere generated by the vertex shader.
+    procedural_image: RenderPipeline,
+    procedural_tiled: RenderPipeline,
+}
+
+impl GlobePipelines {
+    /// Creates the pipelines for a number of samples per pixel.
+    fn new(device: &Device, layouts: &GlobeLayouts, format: TextureFormat, sample_count: u32) -> Self {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(concat!(include_str!("assets/shader.wgsl"), include_str!("assets/tiles.wgsl")))),
+        });
+        // The procedural sphere has no vertex buffer, but a uniform with its resolution.
+        let create_globe_pipeline = |layout: &BindGroupLayout, procedural: bool, fragment_entry_point| {
+            let mut bind_group_layouts = vec![layout, &layouts.camera, &layouts.light];
+            if procedural {
+                bind_group_layouts.push(&layouts.sphere);
+            }
+            let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+            let (vertex_entry_point, buffers) = if procedural { ("vs_procedural", vec![]) } else { ("vs_main", vec![Vertex::desc()]) };
+            let vertex = VertexState {
+                module: &shader,
+                entry_point: Some(vertex_entry_point),
+                buffers: &buffers,
+                compilation_options: Default::default(),
+            };
+            create_pipeline(device, vertex, fragment_entry_point, render_pipeline_layout, format, sample_count)
+        };
+
+        Self {
+            image: create_globe_pipeline(&layouts.texture, false, "fs_main"),
+            tiled: create_globe_pipeline(&layouts.tiles, false, "fs_tiled"),
+            normal_image: create_globe_pipeline(&layouts.texture, false, "fs_normal"),
+            normal_tiled: create_globe_pipeline(&layouts.tiles, false, "fs_tiled_normal"),
+            procedural_image: create_globe_pipeline(&layouts.texture, true, "fs_main"),
+            procedural_tiled: create_globe_pipeline(&layouts.tiles, true, "fs_tiled"),
+        }
+    }
+
+    /// Returns the pipeline that draws a mesh with a texture.
+    fn get(&self, mesh: SphereMesh, texture: &BaseTexture) -> &RenderPipeline {
+        let tiled = matches!(texture, BaseTexture::Tiles(_));
+        match mesh {
+            SphereMesh::Procedural if tiled => &self.procedural_tiled,
+            SphereMesh::Procedural => &self.procedural_image,
+            _ if mesh.tex_coords_from_normal() && tiled => &self.normal_tiled,
+            _ if mesh.tex_coords_from_normal() => &self.normal_image,
+            _ if tiled => &self.tiled,
+            _ => &self.image,
+        }
+    }
+}
+
+/// The triangles of the globe, see [SphereMesh].
+#[derive(Debug)]
+enum GlobeMesh {
+    /// A mesh that is the same from any view.
+    Buffers {
+        vertex_buffer: Buffer,
+        index_buffer: Buffer,
+        num_indices: u32,
+    },
+    /// Chunks that are picked for the camera in every update.
+    Chunks(ChunkedGlobe),
+    /// A sphere generated by the vertex shader.
+    Procedural(ProceduralGlobe),
+}
+
+/// Draws the globe or the flat map together with the features of a document.
+///
+/// The renderer does not own the texture it draws into, so it can draw into the surface of a
+/// window as well as into an offscreen texture, see [Renderer::headless].
+#[derive(Debug)]
+pub struct Renderer {
+    device: Device,
+    queue: Queue,
+    // The format of the textures drawn into.
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    layouts: GlobeLayouts,
+    globe_pipelines: GlobePipelines,
+    // The mesh the globe is drawn with.
+    sphere_mesh: SphereMesh,
+    globe_mesh: GlobeMesh,
+    // Texture Stuff
+    // The number of samples per pixel used for anti-aliasing.
+    sample_count: u32,
+    // The numbers of samples per pixel the adapter supports.
+    sample_counts: Vec<u32>,
+    // The texture drawn into before it is resolved, or `None` without anti-aliasing.
+    multisampled_framebuffer: Option<TextureView>,
+    depth_texture_view: TextureView,
+    // The image wrapped around the globe.
+    base_texture: BaseTexture,
+    // The camera used for rendering the scene.
+    pub camera: OrbitCamera,
+    // The camera used instead of `camera` while the flat map is shown.
+    pub map_camera: MapCamera,
+    camera_buffer: Buffer,
+    camera_bind_group: BindGroup,
+    camera_uniform: CameraUniform,
+    // Lighting Stuff
+    light_uniform: LightUniform,
+    light_buffer: Buffer,
+    light_bind_group: BindGroup,
+    // The document revision the line buffers were last built from.
+    drawn_revision: Option<u64>,
+    // The number of visible layers of the document that was drawn.
+    drawn_layers: usize,
+    // The arc subdivision the line buffers were last built with.
+    drawn_segment_angle: f64,
+    // Whether the line buffers contain a preview.
+    drawn_preview: bool,
+    // The latitude and longitude grid drawn over the globe or map.
+    pub graticule: Graticule,
+    // The spacing and extent the graticule was last built with, or `None` if it is hidden.
+    drawn_graticule: Option<(f64, Extent)>,
+    polyline_renderer: PolylineRenderer,
+    fill_renderer: FillRenderer,
+    base_map_renderer: BaseMapRenderer,
+    // The projection picked for the flat map.
+    projection_kind: ProjectionKind,
+    // The projection of the flat map while it is shown, or `None` while the globe is shown.
+    map_projection: Option<Box<dyn Projection>>,
+    // The coordinate the projection of the flat map was created around.
+    map_projection_center: LatLon,
+}
+
+impl Renderer {
+    /// Creates a new [Renderer].
+    ///
+    /// Arguments:
+    ///
+    /// * `adapter`: The adapter `device` was requested from.
+    /// * `device`: The wgpu device used for rendering.
+    /// * `queue`: The queue of `device`.
+    /// * `format`: The format of the textures that will be drawn into.
+    /// * `width`: The width of the rendered images in pixels.
+    /// * `height`: The height of the rendered images in pixels.
+    /// * `diffuse_texture`: The image wrapped around the globe.
+    pub fn new(adapter: &Adapter, device: Device, queue: Queue, format: TextureFormat, width: u32, height: u32, diffuse_texture: &Texture) -> Self {
+        // Make the dimensions at least size 1, otherwise wgpu would panic
+        let (width, height) = (width.max(1), height.max(1));
+
+        let sample_counts = supported_sample_counts(adapter, &device, format);
+        let sample_count = if sample_counts.contains(&DEFAULT_SAMPLE_COUNT) { DEFAULT_SAMPLE_COUNT } else { 1 };
+
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let tile_bind_group_layout = TileAtlas::create_bind_group_layout(&device);
+        let diffuse_bind_group = create_diffuse_bind_group(&device, &texture_bind_group_layout, diffuse_texture);
+        let depth_texture_view = Texture::create_depth_texture(&device, width, height, sample_count, "depth_texture");
+
+        // Get camera
+        let (camera, camera_uniform) = create_camera(width, height);
+        let (camera_bind_group, camera_bind_group_layout, camera_buffer) = create_camera_bind_group(&device, &camera_uniform);
+
+        // Get light
+        let (light_bind_group, light_bind_group_layout, light_buffer, light_uniform) = create_light(&device);
+
+        let polyline_renderer = PolylineRenderer::new(&device, &camera_bind_group_layout, format, sample_count);
+        let fill_renderer = FillRenderer::new(&device, &camera_bind_group_layout, format, sample_count);
+        let base_map_renderer = BaseMapRenderer::new(&device, &texture_bind_group_layout, &tile_bind_group_layout, &camera_bind_group_layout, format, sample_count);
+
+        let layouts = GlobeLayouts {
+            texture: texture_bind_group_layout,
+            tiles: tile_bind_group_layout,
+            camera: camera_bind_group_layout,
+            light: light_bind_group_layout,
+            sphere: ProceduralGlobe::create_bind_group_layout(&device),
+        };
+        let globe_pipelines = GlobePipelines::new(&device, &layouts, format, sample_count);
+        let multisampled_framebuffer = create_multisampled_framebuffer(&device, format, width, height, sample_count);
+
+        let sphere_mesh = SphereMesh::default();
+        let globe_mesh = create_globe_mesh(&device, sphere_mesh, &layouts.sphere);
+
+        Self {
+            layouts,
+            globe_pipelines,
+            sphere_mesh,
+            globe_mesh,
+
+            sample_count,
+            sample_counts,
+            multisampled_framebuffer,
+            depth_texture_view,
+            base_texture: BaseTexture::Image(diffuse_bind_group),
+
+            camera,
+            map_camera: create_map_camera(width, height),
+            camera_buffer,
+            camera_bind_group,
+            camera_uniform,
+
+            light_uniform,
+            light_buffer,
+            light_bind_group,
+
+            drawn_revision: None,
+            drawn_layers: 0,
+            drawn_segment_angle: 0.0,
+            graticule: Graticule::default(),
+            drawn_graticule: None,
+            drawn_preview: false,
+            polyline_renderer,
+            fill_renderer,
+            base_map_renderer,
+            projection_kind: ProjectionKind::default(),
+            map_projection: None,
+            map_projection_center: LatLon::new(0.0, 0.0),
+
+            device,
+            queue,
+            format,
+            width,
+            height,
+        }
+    }
+
+    /// Creates a renderer that draws into offscreen textures, without a window.
+    ///
+    /// Arguments:
+    ///
+    /// * `width`: The width of the rendered images in pixels.
+    /// * `height`: The height of the rendered images in pixels.
+    /// * `image`: The image wrapped around the globe.
+    /// * `force_fallback_adapter`: Whether to render in software, which works on machines
+    ///   without a GPU such as CI runners.
+    pub async fn headless(width: u32, height: u32, image: &DynamicImage, force_fallback_adapter: bool) -> Result<Self, HeadlessError> {
+        let instance = Instance::default();
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::default(),
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .map_err(HeadlessError::Adapter)?;
+        let (device, queue) = request_device(&adapter).await.map_err(HeadlessError::Device)?;
+
+        let diffuse_texture = Texture::from_image(&device, &queue, image, Some("diffuse_texture"));
+        Ok(Self::new(&adapter, device, queue, OFFSCREEN_FORMAT, width, height, &diffuse_texture))
+    }
+
+    /// The wgpu device used for rendering.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Replaces the image wrapped around the globe.
+    ///
+    /// Arguments:
+    ///
+    /// * `diffuse_texture`: The new image, which has to be created with the device of the renderer.
+    pub fn set_texture(&mut self, diffuse_texture: &Texture) {
+        self.base_texture = BaseTexture::Image(create_diffuse_bind_group(&self.device, &self.layouts.texture, diffuse_texture));
+    }
+
+    /// Wraps a tile pyramid around the globe, streaming in the tiles needed for the current view.
+    pub fn set_tiles(&mut self, pyramid: TilePyramid) {
+        self.base_texture = BaseTexture::Tiles(Box::new(TileAtlas::new(&self.device, &self.layouts.tiles, pyramid)));
+    }
+
+    /// Loads the image wrapped around the globe.
+    ///
+    /// Images that are too large for a single texture are split into a pyramid of tiles, which
+    /// is stored next to them, so that it only has to be built once.
+    ///
+    /// Arguments:
+    ///
+    /// * `path`: An image file, a folder containing a tile pyramid, or `None` for the built-in texture.
+    pub fn load_texture(&mut self, path: Option<&Path>) -> Result<(), TileError> {
+        if let Some(path) = path
+            && path.is_dir()
+        {
+            self.set_tiles(TilePyramid::open(path)?);
+            return Ok(());
+        }
+
+        let image = load_base_image(path)?;
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        match path {
+            Some(path) if image.width() > max_size || image.height() > max_size => {
+                self.set_tiles(TilePyramid::cached(path, &image)?);
+            },
+            _ => self.set_texture(&Texture::from_image(&self.device, &self.queue, &image, Some("diffuse_texture"))),
+        }
+        Ok(())
+    }
+
+    /// Whether tiles of the texture are still being read, so more frames have to be drawn.
+    pub fn is_loading(&self) -> bool {
+        matches!(&self.base_texture, BaseTexture::Tiles(atlas) if atlas.is_loading())
+    }
+
+    /// Changes the size of the rendered images and adjusts the camera aspect.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(1);
+        self.height = height.max(1);
+
+        self.depth_texture_view = Texture::create_depth_texture(
+            &self.device,
+            self.width,
+            self.height,
+            self.sample_count,
+            "depth_texture",
+        );
+        self.multisampled_framebuffer = create_multisampled_framebuffer(&self.device, self.format, self.width, self.height, self.sample_count);
+
+        self.camera.aspect = self.width as f32 / self.height as f32;
+        self.map_camera.viewport = Vec2::new(self.width as f32, self.height as f32);
+    }
+
+    /// The longest great-circle arc in radians drawn as a single segment at the current zoom level.
+    pub fn segment_angle(&self) -> f64 {
+        match self.map_projection {
+            Some(_) => map_segment_angle(self.map_camera.half_height),
+            None => segment_angle(self.camera.distance),
+        }
+    }
+
+    /// Updates the camera and the buffers of the document.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document that is drawn.
+    /// * `preview`: A line that is still being drawn and is not part of the document yet.
+    pub fn update(&mut self, document: &Document, preview: Option<&[LatLon]>) {
+        match self.map_projection {
+            Some(_) => self.camera_uniform.update_view_proj(&self.map_camera),
+            None => self.camera_uniform.update_view_proj(&self.camera),
+        }
+        if self.map_projection.is_none() {
+            match &mut self.globe_mesh {
+                GlobeMesh::Buffers { .. } => {},
+                GlobeMesh::Chunks(chunks) => chunks.update(&self.device, &self.camera, self.height),
+                GlobeMesh::Procedural(sphere) => sphere.update(&self.queue, &self.camera, self.height),
+            }
+        }
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+
+        // Update the light so that it is transformed with the camera
+        self.light_uniform.position = [
+            self.camera_uniform.view_position[0],
+            self.camera_uniform.view_position[1],
+            self.camera_uniform.view_position[2],
+            1.0,
+        ];
+        self.queue.write_buffer(
+            &self.light_buffer,
+            0,
+            bytemuck::cast_slice(&[self.light_uniform]),
+        );
+
+        let placement = match &self.map_projection {
+            Some(projection) => Placement::Map(projection.as_ref()),
+            None => Placement::Globe,
+        };
+        let document_changed = self.drawn_revision != Some(document.revision());
+        // The placement or the renderers may have changed, see [Renderer::invalidate].
+        let invalidated = self.drawn_revision.is_none();
+        if document_changed {
+            self.fill_renderer.rebuild(&self.device, document, placement);
+        }
+
+        // Rebuild the lines when the document changed, a shape is being previewed or the
+        // zoom level needs a different subdivision of the great-circle arcs
+        let segment_angle = self.segment_angle();
+        let viewport = Vec2::new(self.width as f32, self.height as f32);
+        self.polyline_renderer.update(&self.queue, viewport, (1.0 / (self.pixel_angle() * GLOBE_RADIUS as f64)) as f32);
+        let graticule = self.graticule_view();
+        if invalidated || self.drawn_graticule != graticule || self.drawn_segment_angle != segment_angle {
+            let lines = graticule.map(|(spacing, extent)| graticule::lines(spacing, &extent)).unwrap_or_default();
+            self.polyline_renderer.rebuild_graticule(&self.device, &lines, segment_angle, placement);
+            self.drawn_graticule = graticule;
+        }
+        if document_changed
+            || preview.is_some()
+            || self.drawn_preview
+            || self.drawn_segment_angle != segment_angle
+        {
+            self.polyline_renderer.rebuild(&self.device, document, preview, segment_angle, placement);
+            self.drawn_segment_angle = segment_angle;
+            self.drawn_preview = preview.is_some();
+        }
+        self.drawn_revision = Some(document.revision());
+        self.drawn_layers = document.layers().filter(|layer| layer.visible).count();
+
+        if let BaseTexture::Tiles(atlas) = &self.base_texture {
+            let wanted = self.visible_tiles(atlas.max_level());
+            if let BaseTexture::Tiles(atlas) = &mut self.base_texture {
+                atlas.update(&self.queue, &wanted);
+            }
+        }
+    }
+
+    /// Returns the tiles of a pyramid that are visible, the ones closest to the centre of the image first.
+    ///
+    /// The level of the tiles is picked so that a pixel of a tile is about as large as a pixel of
+    /// the image at the centre of the view. The tiles are found by picking a grid of pixels, which
+    /// works the same on the globe and on any map projection.
+    ///
+    /// Arguments:
+    ///
+    /// * `max_level`: The finest level of the pyramid.
+    fn visible_tiles(&self, max_level: u32) -> Vec<TileId> {
+        // The angle covered by a pixel of a tile of level `l` is `π / (2^l · TILE_SIZE)`.
+        let level = (std::f64::consts::PI / (TILE_SIZE as f64 * self.pixel_angle())).log2().ceil();
+        let level = (level.max(0.0) as u32).min(max_level);
+
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let mut samples: Vec<Vec2> = (0..=TILE_SAMPLES)
+            .flat_map(|i| (0..=TILE_SAMPLES).map(move |j| Vec2::new(i as f32, j as f32) / TILE_SAMPLES as f32))
+            .map(|p| p * size)
+            .collect();
+        samples.sort_by(|a, b| a.distance_squared(size / 2.0).total_cmp(&b.distance_squared(size / 2.0)));
+
+        let mut tiles = vec![];
+        for pixel in samples {
+            if let Some(point) = self.pick(pixel) {
+                let tile = TileId::containing(level, point);
+                if !tiles.contains(&tile) {
+                    tiles.push(tile);
+                }
+            }
+        }
+        tiles
+    }
+
+    /// Draws the scene into a texture.
+    ///
+    /// Arguments:
+    ///
+    /// * `view`: The texture that is drawn into, which must have the size and format of the renderer.
+    pub fn render(&self, view: &TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: Some("Render Encoder") });
+
+        // With anti-aliasing, the samples are resolved into `view` and not needed afterwards.
+        let (rpass_view, resolve_target, store) = match &self.multisampled_framebuffer {
+            Some(framebuffer) => (framebuffer, Some(view), StoreOp::Discard),
+            None => (view, None, StoreOp::Store),
+        };
+
+        {
+            let mut r_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: rpass_view,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
+                        store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if self.map_projection.is_some() {
+                self.base_map_renderer.draw(&mut r_pass, &self.base_texture, &self.camera_bind_group);
+            } else {
+                r_pass.set_pipeline(self.globe_pipelines.get(self.sphere_mesh, &self.base_texture));
+                r_pass.set_bind_group(0, self.base_texture.bind_group(), &[]);
+                r_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                r_pass.set_bind_group(2, &self.light_bind_group, &[]);
+
+                match &self.globe_mesh {
+                    GlobeMesh::Buffers { vertex_buffer, index_buffer, num_indices } => {
+                        r_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                        r_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                        r_pass.draw_indexed(0..*num_indices, 0, 0..1);
+                    },
+                    GlobeMesh::Chunks(chunks) => chunks.draw(&mut r_pass),
+                    GlobeMesh::Procedural(sphere) => sphere.draw(&mut r_pass),
+                }
+            }
+            self.polyline_renderer.draw_graticule(&mut r_pass, &self.camera_bind_group);
+
+            // Each layer is drawn completely before the one above it.
+            for layer in 0..self.drawn_layers {
+                self.fill_renderer.draw(&mut r_pass, &self.camera_bind_group, layer);
+                self.polyline_renderer.draw(&mut r_pass, &self.camera_bind_group, layer);
+            }
+            self.polyline_renderer.draw_preview(&mut r_pass, &self.camera_bind_group);
+
+        } // `r_pass` dropped here
+
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Draws a document into an offscreen texture and reads it back.
+    ///
+    /// The renderer must have been created with [Renderer::headless], or at least for a format
+    /// with four 8-bit channels in RGBA order.
+    pub fn render_to_image(&mut self, document: &Document) -> Result<RgbaImage, HeadlessError> {
+        self.update(document, None);
+        if let BaseTexture::Tiles(atlas) = &mut self.base_texture {
+            atlas.finish_loading(&self.queue);
+        }
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_texture"),
+            size: wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        self.render(&texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        // Rows of a texture copy have to be aligned, so they are padded and cut back afterwards.
+        let row_bytes = 4 * self.width;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: (padded_row_bytes * self.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: Some("Readback Encoder") });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|err| HeadlessError::Readback(err.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|err| HeadlessError::Readback(err.to_string()))?
+            .map_err(|err| HeadlessError::Readback(err.to_string()))?;
+
+        let pixels: Vec<u8> = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes as usize)
+            .flat_map(|row| &row[..row_bytes as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+
+        RgbaImage::from_raw(self.width, self.height, pixels)
+            .ok_or_else(|| HeadlessError::Readback("the image has the wrong size".to_string()))
+    }
+
+    /// Draws a document into a PNG file, see [Renderer::render_to_image].
+    pub fn save_png(&mut self, document: &Document, path: &Path) -> Result<(), HeadlessError> {
+        self.render_to_image(document)?.save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+
+    /// Marks the buffers of the document as outdated, so that they are rebuilt by the next update.
+    pub fn invalidate(&mut self) {
+        self.drawn_revision = None;
+    }
+
+    /// The approximate angle in radians one pixel covers at the centre of the visible globe or map.
+    ///
+    /// Used to scale tolerances of the drawing tools with the zoom level.
+    pub fn pixel_angle(&self) -> f64 {
+        if self.map_projection.is_some() {
+            return (self.map_camera.pixel_size() / GLOBE_RADIUS) as f64;
+        }
+        let height = (self.camera.distance - GLOBE_RADIUS).max(f32::EPSILON);
+        let pixel_size = 2.0 * height * (self.camera.fovy / 2.0).tan() / self.height as f32;
+        (pixel_size / GLOBE_RADIUS) as f64
+    }
+
+    /// Returns the coordinate on the globe or map under a pixel, if it is hit.
+    ///
+    /// Arguments:
+    ///
+    /// * `pixel`: The position in pixels, relative to the top left corner of the image.
+    pub fn pick(&self, pixel: Vec2) -> Option<LatLon> {
+        if let Some(projection) = &self.map_projection {
+            let point = self.map_camera.map_point(pixel) / GLOBE_RADIUS;
+            return projection.inverse(point.as_dvec2());
+        }
+        let viewport = Vec2::new(self.width as f32, self.height as f32);
+        let ray = self.camera.ray_through_pixel(pixel, viewport);
+        let hit = ray.intersect_sphere(Vec3::ZERO, GLOBE_RADIUS)?;
+        Some(LatLon::from_unit_vector(hit.as_dvec3()))
+    }
+
+    /// Returns the pixel a coordinate is shown at, if it is visible.
+    ///
+    /// Coordinates on the far side of the globe, outside the map or outside the image are not
+    /// visible. This is the opposite of [Renderer::pick].
+    ///
+    /// Arguments:
+    ///
+    /// * `p`: The coordinate on the globe.
+    pub fn to_pixel(&self, p: LatLon) -> Option<Vec2> {
+        let position = match &self.map_projection {
+            Some(projection) => (projection.forward(p)?.as_vec2() * GLOBE_RADIUS).extend(0.0),
+            None => {
+                let position = p.to_globe_position(GLOBE_RADIUS);
+                let eye = Vec4::from(self.camera_uniform.view_position).truncate();
+                if position.dot(eye - position) <= 0.0 {
+                    return None;
+                }
+                position
+            },
+        };
+        let clip = Mat4::from_cols_array_2d(&self.camera_uniform.view_proj) * position.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let pixel = Vec2::new(clip.x / clip.w + 1.0, 1.0 - clip.y / clip.w) / 2.0 * size;
+        (pixel.cmpge(Vec2::ZERO).all() && pixel.cmple(size).all()).then_some(pixel)
+    }
+
+    /// The coordinate in the centre of the view, or the centre of the globe view while the centre
+    /// of the map lies off the map.
+    fn view_center(&self) -> LatLon {
+        self.map_center().unwrap_or_else(|| self.camera.center())
+    }
+
+    /// Returns the spacing in degrees and the extent of the graticule at the current zoom level,
+    /// or `None` if it is hidden.
+    ///
+    /// The spacing adapts to the distance of the camera on the globe and to the zoom level of the map.
+    fn graticule_view(&self) -> Option<(f64, Extent)> {
+        if !self.graticule.visible {
+            return None;
+        }
+        let view_angle = (self.pixel_angle() * self.height as f64).to_degrees();
+        let spacing = self.graticule.spacing_for(view_angle);
+        Some((spacing, Extent::around(self.view_center(), view_angle, spacing)))
+    }
+
+    /// Returns the labels of the graticule that are visible, with the pixels they are centred on.
+    pub fn graticule_labels(&self) -> Vec<(Vec2, String)> {
+        let Some((spacing, extent)) = self.graticule_view().filter(|_| self.graticule.labels) else {
+            return vec![];
+        };
+        graticule::labels(spacing, &extent, self.view_center())
+            .into_iter()
+            .filter_map(|label| Some((self.to_pixel(label.position)?, label.text)))
+            .collect()
+    }
+
+    /// Whether the flat map is shown instead of the globe.
+    pub fn is_map_view(&self) -> bool {
+        self.map_projection.is_some()
+    }
+
+    /// Switches between the globe and the flat map, keeping the coordinate in the centre of the view.
+    pub fn toggle_map_view(&mut self) {
+        let zoom = (self.camera.fovy / 2.0).tan();
+        if self.map_projection.is_some() {
+            if let Some(center) = self.map_center() {
+                self.camera.look_at(center);
+            }
+            self.camera.set_distance(GLOBE_RADIUS + self.map_camera.half_height / zoom);
+            self.map_projection = None;
+        } else {
+            self.map_camera.set_half_height((self.camera.distance - GLOBE_RADIUS) * zoom);
+            self.show_map(self.camera.center());
+        }
+        self.drawn_revision = None;
+    }
+
+    /// Switches the flat map to the next projection, see [ProjectionKind::next].
+    pub fn next_projection(&mut self) {
+        self.projection_kind = self.projection_kind.next();
+        log::info!("Using the {} projection", self.projection_kind.name());
+        if self.map_projection.is_some() {
+            let center = self.map_center().unwrap_or_else(|| self.camera.center());
+            self.show_map(center);
+        }
+    }
+
+    /// The mesh the globe is drawn with.
+    pub fn sphere_mesh(&self) -> SphereMesh {
+        self.sphere_mesh
+    }
+
+    /// Draws the globe with another mesh.
+    pub fn set_sphere_mesh(&mut self, mesh: SphereMesh) {
+        if mesh == self.sphere_mesh {
+            return;
+        }
+        self.globe_mesh = create_globe_mesh(&self.device, mesh, &self.layouts.sphere);
+        self.sphere_mesh = mesh;
+        log::info!("Drawing the globe as a {}", mesh.name());
+    }
+
+    /// The number of samples per pixel used for anti-aliasing.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// The numbers of samples per pixel the adapter supports, see [Renderer::set_sample_count].
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
+
+    /// Changes the number of samples per pixel used for anti-aliasing, where `1` turns it off.
+    ///
+    /// Everything drawn into the multisampled textures is created again, so this is slow.
+    ///
+    /// Arguments:
+    ///
+    /// * `sample_count`: One of [Renderer::sample_counts].
+    pub fn set_sample_count(&mut self, sample_count: u32) -> Result<(), String> {
+        if !self.sample_counts.contains(&sample_count) {
+            let supported: Vec<_> = self.sample_counts.iter().map(u32::to_string).collect();
+            return Err(format!("{sample_count} samples per pixel are not supported, only {}", supported.join(", ")));
+        }
+        if sample_count == self.sample_count {
+            return Ok(());
+        }
+
+        let device = &self.device;
+        self.globe_pipelines = GlobePipelines::new(device, &self.layouts, self.format, sample_count);
+        self.polyline_renderer = PolylineRenderer::new(device, &self.layouts.camera, self.format, sample_count);
+        self.fill_renderer = FillRenderer::new(device, &self.layouts.camera, self.format, sample_count);
+        self.base_map_renderer = BaseMapRenderer::new(device, &self.layouts.texture, &self.layouts.tiles, &self.layouts.camera, self.format, sample_count);
+        if let Some(projection) = &self.map_projection {
+            self.base_map_renderer.rebuild(device, projection.as_ref(), self.map_projection_center.lon);
+        }
+        self.sample_count = sample_count;
+        self.drawn_revision = None;
+        // Creates the depth texture and the multisampled framebuffer with the new count.
+        self.resize(self.width, self.height);
+        log::info!("Using {sample_count} samples per pixel");
+        Ok(())
+    }
+
+    /// Shows the flat map in the picked projection, centred on a coordinate.
+    fn show_map(&mut self, center: LatLon) {
+        let projection = self.projection_kind.create(center);
+        self.map_camera.center = projection
+            .forward(center)
+            .map_or(Vec2::ZERO, |p| p.as_vec2() * GLOBE_RADIUS);
+        self.base_map_renderer.rebuild(&self.device, projection.as_ref(), center.lon);
+        self.map_projection = Some(projection);
+        self.map_projection_center = center;
+        self.drawn_revision = None;
+    }
+
+    /// Returns the coordinate in the centre of the flat map, if it is shown and the centre lies on it.
+    fn map_center(&self) -> Option<LatLon> {
+        let projection = self.map_projection.as_ref()?;
+        projection.inverse((self.map_camera.center / GLOBE_RADIUS).as_dvec2())
+    }
+
+    /// Returns the view of the globe that is stored in projects.
+    ///
+    /// While the flat map is shown, this is the globe looking at the centre of the map.
+    pub fn camera_state(&self) -> CameraState {
+        let mut camera = self.camera;
+        if let Some(center) = self.map_center() {
+            camera.look_at(center);
+        }
+        CameraState::from_camera(&camera)
+    }
+
+    /// Moves the view to a state stored in a project, see [Renderer::camera_state].
+    pub fn set_camera_state(&mut self, state: &CameraState) {
+        state.apply(&mut self.camera);
+        if self.map_projection.is_some() {
+            self.show_map(self.camera.center());
+        }
+    }
+
+    /// Returns the settings for exporting what is shown as an SVG map.
+    ///
+    /// While the flat map is shown, this is the part of it that is visible. Otherwise it is the
+    /// whole map in the picked projection, centred on the coordinate in the centre of the globe.
+    pub fn svg_options(&self) -> SvgOptions {
+        let width = self.width as f64;
+        match self.map_projection {
+            Some(_) => {
+                let camera = &self.map_camera;
+                let half_size = Vec2::new(camera.half_height * camera.aspect(), camera.half_height);
+                let extent = ((camera.center - half_size) / GLOBE_RADIUS, (camera.center + half_size) / GLOBE_RADIUS);
+                SvgOptions {
+                    projection: self.projection_kind,
+                    center: self.map_projection_center,
+                    extent: Some((extent.0.as_dvec2(), extent.1.as_dvec2())),
+                    width,
+                    graticule: self.graticule,
+                }
+            },
+            None => SvgOptions {
+                projection: self.projection_kind,
+                center: self.camera.center(),
+                extent: None,
+                width,
+                graticule: self.graticule,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::document::{testing::DocumentExt, Geometry, Polygon, Stroke, StrokeWidth};
+
+    /// The environment variable that lets the rendering tests pass on machines without an adapter.
+    const SKIP_GPU_TESTS: &str = "SITELEN_SKIP_GPU_TESTS";
+
+    /// Creates a software renderer.
+    ///
+    /// Fails the test if this machine has no software adapter, unless [SKIP_GPU_TESTS] is set, in
+    /// which case `None` is returned and the test is skipped.
+    fn headless(width: u32, height: u32) -> Option<Renderer> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 2, Rgba([0, 255, 0, 255])));
+        match pollster::block_on(Renderer::headless(width, height, &image, true)) {
+            Ok(renderer) => Some(renderer),
+            Err(err) if std::env::var_os(SKIP_GPU_TESTS).is_some() => {
+                eprintln!("skipping headless rendering test because {SKIP_GPU_TESTS} is set: {err}");
+                None
+            },
+            Err(err) => panic!("no adapter for headless rendering, set {SKIP_GPU_TESTS} to skip this test: {err}"),
+        }
+    }
+
+    #[test]
+    fn renders_the_globe_offscreen() {
+        let Some(mut renderer) = headless(96, 64) else {
+            return;
+        };
+        let mut document = Document::new();
+        let square = [(-10.0, -100.0), (-10.0, -80.0), (10.0, -80.0), (10.0, -100.0)];
+        let square = square.iter().map(|&(lat, lon)| LatLon::new(lat, lon)).collect();
+        document.add(Geometry::Polygon(Polygon::new(square)));
+        renderer.camera.look_at(LatLon::new(0.0, -90.0));
+
+        let image = renderer.render_to_image(&document).unwrap();
+
+        assert_eq!(image.dimensions(), (96, 64));
+        // The corners show the background and the centre the globe with the red polygon on it.
+        let background = image.get_pixel(0, 0);
+        let center = image.get_pixel(48, 32);
+        assert!(background[2] > background[0], "{background:?}");
+        assert!(center[0] > center[2] && center[0] > 0, "{center:?}");
+    }
+
+    #[test]
+    fn leaves_out_hidden_layers() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        let mut document = Document::new();
+        let square = [(-10.0, -100.0), (-10.0, -80.0), (10.0, -80.0), (10.0, -100.0)];
+        document.add(Geometry::Polygon(Polygon::new(square.iter().map(|&(lat, lon)| LatLon::new(lat, lon)).collect())));
+        renderer.camera.look_at(LatLon::new(0.0, -90.0));
+        let shown = renderer.render_to_image(&document).unwrap()[(32, 32)];
+
+        let mut layer = document.active_layer().clone();
+        layer.visible = false;
+        document.set_layer(layer);
+        let hidden = renderer.render_to_image(&document).unwrap()[(32, 32)];
+
+        assert!(shown[0] > 0, "{shown:?}");
+        assert_eq!(hidden[0], 0, "{hidden:?}");
+    }
+
+    #[test]
+    fn draws_lines_with_their_stroke() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        let mut document = Document::new();
+        document.add(Geometry::Polyline(vec![LatLon::new(0.0, -60.0), LatLon::new(0.0, 60.0)]));
+        let mut layer = document.active_layer().clone();
+        let stroke = Stroke { color: [1.0, 0.0, 0.0, 1.0], width: StrokeWidth::Pixels(9.0), ..Stroke::default() };
+        layer.style.stroke = Some(stroke);
+        document.set_layer(layer.clone());
+        renderer.camera.look_at(LatLon::new(0.0, 0.0));
+
+        // The equator runs through the middle of the image.
+        let solid = renderer.render_to_image(&document).unwrap();
+        assert!(solid[(32, 29)][0] > 200 && solid[(32, 35)][0] > 200, "{:?} {:?}", solid[(32, 29)], solid[(32, 35)]);
+        assert_eq!(solid[(32, 40)][0], 0, "{:?}", solid[(32, 40)]);
+
+        layer.style.stroke.as_mut().unwrap().dash = vec![4.0, 4.0];
+        document.set_layer(layer);
+        let dashed = renderer.render_to_image(&document).unwrap();
+        let row: Vec<_> = (16..48).map(|x| dashed[(x, 32)][0]).collect();
+        assert!(row.iter().any(|&red| red > 200) && row.iter().any(|&red| red < 50), "{row:?}");
+    }
+
+    #[test]
+    fn renders_the_map_offscreen() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        renderer.toggle_map_view();
+
+        let image = renderer.render_to_image(&Document::new()).unwrap();
+
+        // The unlit texture fills the centre of the map.
+        assert_eq!(*image.get_pixel(32, 32), Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn toggles_the_map_view_back_to_the_same_centre() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.camera.look_at(LatLon::new(35.0, 150.0));
+        let center = renderer.camera.center();
+        let distance = renderer.camera.distance;
+
+        renderer.toggle_map_view();
+        assert!(renderer.is_map_view());
+        renderer.toggle_map_view();
+        assert!(!renderer.is_map_view());
+
+        assert!(renderer.camera.center().angular_distance(center) < 1e-4, "{:?}", renderer.camera.center());
+        assert!((renderer.camera.distance - distance).abs() < 1e-3);
+    }
+
+    #[test]
+    fn keeps_the_map_zoom_in_bounds() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.toggle_map_view();
+        renderer.map_camera.zoom(1e9);
+        assert_eq!(renderer.map_camera.half_height, MAP_MAX_HALF_HEIGHT);
+        renderer.map_camera.zoom(1e-9);
+        assert_eq!(renderer.map_camera.half_height, MAP_MIN_HALF_HEIGHT);
+
+        // Switching back and forth starts the map at the zoom of the globe, which is in bounds too.
+        for distance in [0.0, f32::MAX] {
+            renderer.toggle_map_view();
+            renderer.camera.set_distance(distance);
+            renderer.toggle_map_view();
+            assert!((MAP_MIN_HALF_HEIGHT..=MAP_MAX_HALF_HEIGHT).contains(&renderer.map_camera.half_height));
+        }
+    }
+
+    #[test]
+    fn draws_the_graticule_over_the_map() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        renderer.toggle_map_view();
+        renderer.graticule.visible = true;
+
+        let image = renderer.render_to_image(&Document::new()).unwrap();
+
+        // The equator and the prime meridian cross in the centre of the map.
+        let center = image[(32, 32)];
+        assert!(center[0] > 50 && center[2] > 50, "{center:?}");
+        assert!(renderer.graticule_labels().iter().any(|(_, text)| text == "0°"));
+
+        renderer.graticule.visible = false;
+        let image = renderer.render_to_image(&Document::new()).unwrap();
+        assert_eq!(image[(32, 32)], Rgba([0, 255, 0, 255]));
+        assert!(renderer.graticule_labels().is_empty());
+    }
+
+    #[test]
+    fn finds_the_pixels_of_coordinates() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.camera.look_at(LatLon::new(10.0, 20.0));
+        renderer.update(&Document::new(), None);
+
+        let center = renderer.to_pixel(LatLon::new(10.0, 20.0)).unwrap();
+        assert!(center.distance(Vec2::new(32.0, 24.0)) < 0.5, "{center}");
+        let p = LatLon::new(15.0, 25.0);
+        let picked = renderer.pick(renderer.to_pixel(p).unwrap()).unwrap();
+        assert!(picked.angular_distance(p) < 0.1 * renderer.pixel_angle(), "{picked:?}");
+        assert_eq!(renderer.to_pixel(LatLon::new(-10.0, -160.0)), None);
+    }
+
+    #[test]
+    fn renders_every_sphere_mesh() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        // The western hemisphere is red and the eastern one blue.
+        let texture = RgbaImage::from_fn(256, 128, |x, _| if x < 128 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) });
+        let texture = Texture::from_image(renderer.device(), &renderer.queue, &DynamicImage::ImageRgba8(texture), None);
+        renderer.set_texture(&texture);
+        // East is on the right, so the antimeridian has blue on its left and red on its right.
+        renderer.camera.look_at(LatLon::new(0.0, 180.0));
+
+        for mesh in SphereMesh::ALL {
+            renderer.set_sphere_mesh(mesh);
+
+            let image = renderer.render_to_image(&Document::new()).unwrap();
+
+            let (west, east) = (image.get_pixel(26, 32), image.get_pixel(38, 32));
+            assert!(west[2] > west[0], "{mesh:?} {west:?}");
+            assert!(east[0] > east[2], "{mesh:?} {east:?}");
+        }
+    }
+
+    #[test]
+    fn smooths_edges_with_more_samples() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        let background = renderer.render_to_image(&Document::new()).unwrap()[(0, 0)][0];
+
+        assert!(renderer.set_sample_count(3).is_err());
+        for sample_count in renderer.sample_counts().to_vec() {
+            renderer.set_sample_count(sample_count).unwrap();
+
+            let image = renderer.render_to_image(&Document::new()).unwrap();
+
+            // The globe has no red, so only pixels on its outline mix its color with the background.
+            let blended = image.pixels().filter(|pixel| pixel[0] != 0 && pixel[0] != background).count();
+            assert_eq!(blended > 0, sample_count > 1, "{sample_count} samples");
+        }
+    }
+
+    #[test]
+    fn renders_tiles_offscreen() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        // The western hemisphere is red and the eastern one blue.
+        let texture = RgbaImage::from_fn(1024, 512, |x, _| if x < 512 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) });
+        let folder = std::env::temp_dir().join(format!("sitelen-render-tiles-{}", std::process::id()));
+        let pyramid = TilePyramid::build(&DynamicImage::ImageRgba8(texture), &folder).unwrap();
+        renderer.set_tiles(pyramid);
+        renderer.camera.look_at(LatLon::new(0.0, 0.0));
+        renderer.toggle_map_view();
+
+        let image = renderer.render_to_image(&Document::new());
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        let image = image.unwrap();
+        assert_eq!(*image.get_pixel(24, 32), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(40, 32), Rgba([0, 0, 255, 255]));
+    }
+}
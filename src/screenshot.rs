@@ -0,0 +1,41 @@
+use std::error::Error;
+
+use image::DynamicImage;
+
+use crate::{cli::{Options, Screenshot}, document::Document, project::Project, renderer::Renderer};
+
+/// Renders a screenshot without opening a window.
+///
+/// Arguments:
+///
+/// * `options`: The texture and the project that are rendered.
+/// * `screenshot`: Where and how the screenshot is rendered.
+pub fn take(options: &Options, screenshot: &Screenshot) -> Result<(), Box<dyn Error>> {
+    let project = options.project.as_deref().map(Project::load).transpose()?;
+
+    let texture = options.texture.clone().or_else(|| {
+        let project_path = options.project.as_deref()?;
+        project.as_ref()?.texture_path(project_path)
+    });
+    // A placeholder, so that the built-in texture is only decoded if no other texture is given.
+    let placeholder = DynamicImage::new_rgba8(1, 1);
+    let mut renderer = pollster::block_on(Renderer::headless(screenshot.width, screenshot.height, &placeholder, screenshot.software))?;
+    renderer.load_texture(texture.as_deref())?;
+    if let Some(sample_count) = options.sample_count {
+        renderer.set_sample_count(sample_count)?;
+    }
+
+    let document = match project {
+        Some(project) => {
+            renderer.set_camera_state(&project.camera);
+            project.document
+        },
+        None => Document::new(),
+    };
+    if screenshot.map {
+        renderer.toggle_map_view();
+    }
+
+    renderer.save_png(&document, &screenshot.output)?;
+    Ok(())
+}
@@ -1,7 +1,222 @@
+use std::{collections::HashMap, f32::consts::{FRAC_PI_4, PI}};
+
 use glam::Vec3;
 
 use crate::vertex::Vertex;
 
+/// The radius of the rendered globe in world units.
+pub const GLOBE_RADIUS: f32 = 10.0;
+
+/// The number of times the faces of the icosahedron are split into four, which gives about as
+/// many vertices as the UV sphere.
+const ICOSPHERE_SUBDIVISIONS: u32 = 7;
+
+/// The number of cells along each edge of a face of the cube sphere.
+const CUBE_SEGMENTS: u32 = 160;
+
+/// The faces of the cube a cube sphere is made of, as the outward normal of each face with two
+/// axes along it. They satisfy `u × v = normal`, which makes grids that are counter-clockwise in
+/// `(u, v)` counter-clockwise when seen from outside.
+pub const CUBE_FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y, Vec3::Z),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+    (Vec3::Y, Vec3::Z, Vec3::X),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y, Vec3::X),
+];
+
+/// The meshes the globe can be drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SphereMesh {
+    /// A grid of meridians and parallels, see [get_sphere_vertices]. Its triangles get thinner
+    /// towards the poles, where they pinch the texture.
+    Uv,
+    /// A subdivided icosahedron, see [get_icosphere_vertices].
+    Icosphere,
+    /// A subdivided cube pushed out onto the sphere, see [get_cube_sphere_vertices].
+    CubeSphere,
+    /// A cube sphere split into chunks that are refined where the camera is close, see
+    /// [crate::render::ChunkedGlobe].
+    #[default]
+    Chunked,
+    /// A UV sphere generated by the vertex shader, see [crate::render::ProceduralGlobe].
+    Procedural,
+}
+
+impl SphereMesh {
+    /// All meshes, in the order they are cycled through.
+    pub const ALL: [SphereMesh; 5] = [
+        SphereMesh::Uv,
+        SphereMesh::Icosphere,
+        SphereMesh::CubeSphere,
+        SphereMesh::Chunked,
+        SphereMesh::Procedural,
+    ];
+
+    /// The name of the mesh as shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            SphereMesh::Uv => "UV sphere",
+            SphereMesh::Icosphere => "icosphere",
+            SphereMesh::CubeSphere => "cube sphere",
+            SphereMesh::Chunked => "chunked cube sphere",
+            SphereMesh::Procedural => "procedural UV sphere",
+        }
+    }
+
+    /// Returns the mesh following this one in [SphereMesh::ALL], wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mesh| mesh == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the texture coordinates have to be computed from the normal of each fragment.
+    ///
+    /// Only the UV spheres have a seam of duplicated vertices at the antimeridian. Interpolating
+    /// texture coordinates across the triangles of the other meshes that cross it would smear
+    /// the whole texture over them.
+    pub fn tex_coords_from_normal(self) -> bool {
+        !matches!(self, SphereMesh::Uv | SphereMesh::Procedural)
+    }
+
+    /// Creates the vertices and triangles of the mesh.
+    ///
+    /// Returns `None` for [SphereMesh::Chunked] and [SphereMesh::Procedural], whose triangles
+    /// depend on the camera and are not built on the CPU.
+    ///
+    /// Arguments:
+    ///
+    /// * `radius`: The radius of the sphere.
+    pub fn vertices(self, radius: f32) -> Option<(Vec<Vertex>, Vec<u32>)> {
+        match self {
+            SphereMesh::Uv => Some(get_sphere_vertices(radius)),
+            SphereMesh::Icosphere => Some(get_icosphere_vertices(radius, ICOSPHERE_SUBDIVISIONS)),
+            SphereMesh::CubeSphere => Some(get_cube_sphere_vertices(radius, CUBE_SEGMENTS)),
+            SphereMesh::Chunked | SphereMesh::Procedural => None,
+        }
+    }
+}
+
+/// Returns the coordinate of the equirectangular texture at a point of the globe.
+///
+/// This matches the coordinates of [get_sphere_vertices] and `tex_coords_from_normal` in
+/// `shader.wgsl`.
+///
+/// Arguments:
+///
+/// * `normal`: The direction from the centre of the globe to the point, of length `1`.
+pub fn tex_coords_from_normal(normal: Vec3) -> [f32; 2] {
+    let u = (-normal.z).atan2(normal.x) / (2.0 * PI);
+    let v = 0.5 - normal.y.clamp(-1.0, 1.0).asin() / PI;
+    [u.rem_euclid(1.0), v]
+}
+
+/// Returns the direction from the centre of the globe to a point on a face of a cube sphere.
+///
+/// The face is parametrised by equal angles instead of equal distances on the cube, so that
+/// cells of a regular grid in the middle of a face are not much larger than those at its corners.
+///
+/// Arguments:
+///
+/// * `face`: The index of the face in [CUBE_FACES].
+/// * `s`: The coordinate along the `u` axis of the face, from `-1` to `1`.
+/// * `t`: The coordinate along the `v` axis of the face, from `-1` to `1`.
+pub fn cube_sphere_direction(face: usize, s: f32, t: f32) -> Vec3 {
+    let (normal, u, v) = CUBE_FACES[face];
+    (normal + (FRAC_PI_4 * s).tan() * u + (FRAC_PI_4 * t).tan() * v).normalize()
+}
+
+/// Creates a vertex of a mesh whose texture coordinates are computed from its normal.
+pub fn normal_vertex(direction: Vec3, radius: f32) -> Vertex {
+    let normal = direction.normalize();
+    Vertex {
+        position: (normal * radius).to_array(),
+        tex_coords: tex_coords_from_normal(normal),
+        normal: normal.to_array(),
+    }
+}
+
+/// Creates an icosphere, whose triangles have nearly the same size and shape everywhere.
+///
+/// The vertices are shared between triangles, including at the antimeridian, so the texture
+/// coordinates of the vertices cannot be interpolated, see [SphereMesh::tex_coords_from_normal].
+///
+/// Arguments:
+///
+/// * `radius`: The radius of the sphere.
+/// * `subdivisions`: The number of times each triangle is split into four, starting from the
+///   20 faces of an icosahedron.
+pub fn get_icosphere_vertices(radius: f32, subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut directions = vec![
+        Vec3::new(-1.0, t, 0.0), Vec3::new(1.0, t, 0.0), Vec3::new(-1.0, -t, 0.0), Vec3::new(1.0, -t, 0.0),
+        Vec3::new(0.0, -1.0, t), Vec3::new(0.0, 1.0, t), Vec3::new(0.0, -1.0, -t), Vec3::new(0.0, 1.0, -t),
+        Vec3::new(t, 0.0, -1.0), Vec3::new(t, 0.0, 1.0), Vec3::new(-t, 0.0, -1.0), Vec3::new(-t, 0.0, 1.0),
+    ];
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // The midpoint of each edge is shared by the two triangles on either side of it.
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let direction = (directions[a as usize].normalize() + directions[b as usize].normalize()) / 2.0;
+                directions.push(direction);
+                directions.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let vertices = directions.into_iter().map(|direction| normal_vertex(direction, radius)).collect();
+    (vertices, triangles.into_iter().flatten().collect())
+}
+
+/// Creates a cube sphere, which subdivides each face of a cube into a grid and pushes its
+/// vertices out onto the sphere, see [cube_sphere_direction].
+///
+/// Arguments:
+///
+/// * `radius`: The radius of the sphere.
+/// * `segments`: The number of cells along each edge of a face.
+pub fn get_cube_sphere_vertices(radius: f32, segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let side = segments + 1;
+    let mut vertices = Vec::with_capacity((6 * side * side) as usize);
+    let mut indices = Vec::with_capacity((6 * segments * segments * 6) as usize);
+
+    for face in 0..CUBE_FACES.len() {
+        let first = vertices.len() as u32;
+        for j in 0..side {
+            for i in 0..side {
+                let s = 2.0 * i as f32 / segments as f32 - 1.0;
+                let t = 2.0 * j as f32 / segments as f32 - 1.0;
+                vertices.push(normal_vertex(cube_sphere_direction(face, s, t), radius));
+            }
+        }
+        for j in 0..segments {
+            for i in 0..segments {
+                let a = first + j * side + i;
+                let (b, c, d) = (a + 1, a + side + 1, a + side);
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
 // #[cfg(not(feature = "indexed"))]
 pub fn get_sphere_vertices(
     // _index_offset: u32,
@@ -77,8 +292,52 @@ pub fn get_sphere_vertices(
         }
     }
 
-    println!("Verts: {}", vertices.len());
-    println!("Indcs: {}", indices.len());
+    log::debug!("Built a UV sphere with {} vertices and {} indices", vertices.len(), indices.len());
     indices.reverse();
     (vertices, indices)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that the vertices lie on the sphere and the triangles face outwards.
+    fn assert_closed_sphere(vertices: &[Vertex], indices: &[u32], radius: f32) {
+        for vertex in vertices {
+            assert!((Vec3::from(vertex.position).length() - radius).abs() < 1e-4);
+        }
+        for triangle in indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(vertices[triangle[i] as usize].position));
+            assert!((b - a).cross(c - a).dot(a + b + c) > 0.0);
+        }
+    }
+
+    #[test]
+    fn icospheres_are_closed() {
+        let (vertices, indices) = get_icosphere_vertices(2.0, 2);
+
+        assert_eq!(vertices.len(), 10 * 4 * 4 + 2);
+        assert_eq!(indices.len(), 20 * 4 * 4 * 3);
+        assert_closed_sphere(&vertices, &indices, 2.0);
+    }
+
+    #[test]
+    fn cube_spheres_are_closed() {
+        let (vertices, indices) = get_cube_sphere_vertices(2.0, 4);
+
+        assert_eq!(vertices.len(), 6 * 5 * 5);
+        assert_eq!(indices.len(), 6 * 4 * 4 * 6);
+        assert_closed_sphere(&vertices, &indices, 2.0);
+    }
+
+    #[test]
+    fn tex_coords_match_the_uv_sphere() {
+        let (vertices, _) = get_sphere_vertices(1.0);
+
+        // The seam and the poles have several texture coordinates for the same normal.
+        for vertex in vertices.iter().filter(|v| v.normal[1].abs() < 0.999 && v.tex_coords[0] > 0.001 && v.tex_coords[0] < 0.999) {
+            let [u, v] = tex_coords_from_normal(Vec3::from(vertex.normal));
+            assert!((u - vertex.tex_coords[0]).abs() < 1e-3, "{u} {:?}", vertex.tex_coords);
+            assert!((v - vertex.tex_coords[1]).abs() < 1e-3, "{v} {:?}", vertex.tex_coords);
+        }
+    }
+}
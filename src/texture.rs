@@ -1,6 +1,52 @@
-use std::{fs::File, io::BufReader};
+use std::{fs::File, io::{BufRead, BufReader, Cursor, Seek}, path::Path};
 
-use image::{GenericImageView, ImageBuffer, ImageError, ImageReader, Rgb, Rgba};
+use image::{DynamicImage, GenericImageView, ImageError, ImageReader};
+
+use crate::mipmap::{mip_level_count, MipmapGenerator};
+
+/// The largest number of samples taken along the direction a texture is stretched in, which keeps
+/// the globe sharp towards its limb.
+const MAX_ANISOTROPY: u16 = 16;
+
+/// The image wrapped around the globe when no other one is picked.
+pub const EARTH_TEXTURE: &[u8] = include_bytes!("assets/earth_color.jpg");
+
+/// Reads an image file, whatever its size.
+///
+/// Arguments:
+///
+/// * `path`: The path of the image file. Its format is guessed from the content.
+pub fn load_image(path: &Path) -> Result<DynamicImage, ImageError> {
+    decode(BufReader::new(File::open(path)?))
+}
+
+/// Decodes an image held in memory, whatever its size.
+///
+/// Arguments:
+///
+/// * `bytes`: The encoded image. Its format is guessed from the content.
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, ImageError> {
+    decode(Cursor::new(bytes))
+}
+
+/// Decodes the image wrapped around the globe.
+///
+/// Arguments:
+///
+/// * `path`: The image file, or `None` for the built-in [EARTH_TEXTURE].
+pub fn load_base_image(path: Option<&Path>) -> Result<DynamicImage, ImageError> {
+    match path {
+        Some(path) => load_image(path),
+        None => decode_image(EARTH_TEXTURE),
+    }
+}
+
+fn decode(source: impl BufRead + Seek) -> Result<DynamicImage, ImageError> {
+    let mut reader = ImageReader::new(source);
+    // Maps of the whole earth easily exceed the default limits of the decoders.
+    reader.no_limits();
+    reader.with_guessed_format()?.decode()
+}
 
 #[derive(Debug)]
 pub struct Texture {
@@ -10,28 +56,14 @@ pub struct Texture {
 }
 
 impl Texture {
-    /// Creates a new texture from a byte array.
+    /// Creates a texture of a single transparent pixel, used until the actual texture is loaded.
     ///
     /// Arguments:
     ///
     /// * `device`: The wgpu device for which the texture will be generated.
     /// * `queue`: The wgpu queue for which the texture will be generated.
-    /// * `bytes`: The byte array containing the image / texture.
-    /// * `label`: The label of the new texture.
-    pub fn from_bytes(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        bytes: &[u8],
-        label: &str,
-    ) -> Result<Self, ImageError> {
-        let file = File::open("./src/assets/test.png")?;
-        let buf = BufReader::new(file);
-        let mut reader = ImageReader::new(buf);
-        reader.no_limits();
-        let image = reader.with_guessed_format()?.decode()?;
-        // let image = ImageReader::open("./src/assets/test.png")?.decode()?;
-        // let image = image::load_from_memory(bytes)?;
-        Ok(Self::from_image(device, queue, &image, Some(label)))
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::from_image(device, queue, &image::DynamicImage::new_rgba8(1, 1), Some("placeholder_texture"))
     }
 
     // fn rgba8_to_rgb8(input: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
@@ -82,6 +114,10 @@ impl Texture {
 
     /// Creates a new texture from a [image::DynamicImage].
     ///
+    /// The mip chain of the texture is generated on the GPU and sampled trilinearly. The texture
+    /// repeats horizontally, so that the left and right edges of a map of the globe blend into
+    /// each other at the antimeridian.
+    ///
     /// Arguments:
     ///
     /// * `device`: The wgpu device for which the texture will be generated.
@@ -102,14 +138,17 @@ impl Texture {
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count: mip_level_count(dimensions.0, dimensions.1),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         });
 
@@ -129,14 +168,19 @@ impl Texture {
             size,
         );
 
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Mipmap Encoder") });
+        MipmapGenerator::new(device, format).generate(device, &mut encoder, &texture);
+        queue.submit(Some(encoder.finish()));
+
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: wgpu::AddressMode::Repeat,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy_clamp: MAX_ANISOTROPY,
             ..Default::default()
         });
 
@@ -152,18 +196,20 @@ impl Texture {
     /// Arguments:
     ///
     /// * `device`: The wgpu device for which the texture will be generated.
-    /// * `config`: The wgpu surface configuration for which the texture will be generated.
+    /// * `width`: The width of the texture in pixels.
+    /// * `height`: The height of the texture in pixels.
     /// * `sample_count`: This has to be the same as the number of samples used for _MSAA_.
     /// * `label`: The label of the texture.
     pub fn create_depth_texture(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        width: u32,
+        height: u32,
         sample_count: u32,
         label: &str,
     ) -> wgpu::TextureView {
         let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let desc = wgpu::TextureDescriptor {
@@ -186,18 +232,22 @@ impl Texture {
     /// Arguments:
     ///
     /// * `device`: The wgpu device for which the texture will be generated.
-    /// * `config`: The wgpu surface configuration for which the texture will be generated.
-    /// * `sample_count`: The sample count used for _MSAA_. Valid values are `1` (no MSAA) or `4`.
+    /// * `format`: The format of the color target that is resolved into.
+    /// * `width`: The width of the texture in pixels.
+    /// * `height`: The height of the texture in pixels.
+    /// * `sample_count`: The sample count used for _MSAA_. See [crate::renderer::SAMPLE_COUNTS].
     /// * `label`: The label of the texture.
     pub fn create_multisampled_framebuffer(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
         sample_count: u32,
         label: &str,
     ) -> wgpu::TextureView {
         let multisampled_texture_extent = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let multisampled_frame_descriptor = &wgpu::TextureDescriptor {
@@ -205,7 +255,7 @@ impl Texture {
             mip_level_count: 1,
             sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: config.format,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             label: Some(label),
             view_formats: &[],
@@ -216,3 +266,20 @@ impl Texture {
             .create_view(&wgpu::TextureViewDescriptor::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_built_in_texture() {
+        let image = decode_image(EARTH_TEXTURE).unwrap();
+        assert!(image.width() > 0 && image.height() > 0);
+    }
+
+    #[test]
+    fn invalid_images_are_errors() {
+        assert!(decode_image(b"not an image").is_err());
+        assert!(load_image(Path::new("missing/texture.png")).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashSet, sync::mpsc::{self, Receiver, Sender}, thread};
+
+use image::RgbaImage;
+
+use super::{TileError, TileId, TilePyramid};
+
+/// Reads the tiles of a [TilePyramid] on a background thread, so that drawing does not wait for the disk.
+///
+/// The thread stops when the loader is dropped.
+#[derive(Debug)]
+pub struct TileLoader {
+    requests: Sender<TileId>,
+    loaded: Receiver<(TileId, Result<RgbaImage, TileError>)>,
+    // The tiles that were requested but not received yet.
+    pending: HashSet<TileId>,
+}
+
+impl TileLoader {
+    /// Starts loading tiles from a pyramid.
+    pub fn new(pyramid: TilePyramid) -> Self {
+        let (requests, requested) = mpsc::channel::<TileId>();
+        let (send_loaded, loaded) = mpsc::channel();
+        thread::Builder::new()
+            .name("tile loader".to_string())
+            .spawn(move || {
+                for tile in requested {
+                    if send_loaded.send((tile, pyramid.load_tile(tile))).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to start the tile loader");
+
+        Self { requests, loaded, pending: HashSet::new() }
+    }
+
+    /// Queues a tile to be read, unless it is already queued.
+    pub fn request(&mut self, tile: TileId) {
+        if self.pending.insert(tile) {
+            // The thread only stops once the loader is dropped, so sending cannot fail.
+            let _ = self.requests.send(tile);
+        }
+    }
+
+    /// The number of tiles that were requested but not received yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the tiles read since the last call, without waiting for more.
+    pub fn receive(&mut self) -> Vec<(TileId, Result<RgbaImage, TileError>)> {
+        let loaded: Vec<_> = self.loaded.try_iter().collect();
+        for (tile, _) in &loaded {
+            self.pending.remove(tile);
+        }
+        loaded
+    }
+
+    /// Waits until every requested tile has been read and returns them.
+    pub fn receive_all(&mut self) -> Vec<(TileId, Result<RgbaImage, TileError>)> {
+        let mut loaded = vec![];
+        while !self.pending.is_empty() {
+            let Ok((tile, result)) = self.loaded.recv() else {
+                break;
+            };
+            self.pending.remove(&tile);
+            loaded.push((tile, result));
+        }
+        loaded
+    }
+}
@@ -0,0 +1,366 @@
+pub mod loader;
+
+use std::{fmt, fs, io, path::{Path, PathBuf}, time::SystemTime};
+
+use image::{imageops::{self, FilterType}, DynamicImage, ImageError, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+pub use loader::TileLoader;
+
+use crate::{geo::LatLon, texture::load_image};
+
+/// The width and height in pixels of the part of the map covered by one tile.
+pub const TILE_SIZE: u32 = 256;
+
+/// The number of pixels copied from the neighbouring tiles around each tile, so that tiles can be
+/// filtered linearly up to their edges without seams.
+pub const TILE_BORDER: u32 = 1;
+
+/// The finest level a pyramid can have, which keeps the page table within the texture limits.
+pub const MAX_LEVEL: u32 = 10;
+
+/// The name of the file describing a pyramid, stored next to its tiles.
+const MANIFEST_NAME: &str = "pyramid.json";
+
+/// A tile of a [TilePyramid].
+///
+/// Level `l` splits the equirectangular map into `2^(l + 1)` columns and `2^l` rows of tiles,
+/// with `(0, 0)` being the tile at the north pole and the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileId {
+    pub level: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    /// Returns the tile of a level containing a coordinate.
+    ///
+    /// Arguments:
+    ///
+    /// * `level`: The level of the tile.
+    /// * `point`: The coordinate.
+    pub fn containing(level: u32, point: LatLon) -> Self {
+        let (columns, rows) = tile_counts(level);
+        let u = ((point.lon + 180.0) / 360.0).rem_euclid(1.0);
+        let v = ((90.0 - point.lat) / 180.0).clamp(0.0, 1.0);
+        Self {
+            level,
+            x: ((u * columns as f64) as u32).min(columns - 1),
+            y: ((v * rows as f64) as u32).min(rows - 1),
+        }
+    }
+
+    /// Returns the tile of a coarser level that this tile lies in, or the tile itself.
+    ///
+    /// Arguments:
+    ///
+    /// * `level`: The level of the ancestor, at most the level of this tile.
+    pub fn ancestor(self, level: u32) -> Self {
+        let shift = self.level - level;
+        Self { level, x: self.x >> shift, y: self.y >> shift }
+    }
+
+    /// Whether this tile covers all of `other`, which is the case if it is `other` or one of its ancestors.
+    pub fn covers(self, other: TileId) -> bool {
+        self.level <= other.level && other.ancestor(self.level) == self
+    }
+}
+
+/// Returns the number of columns and rows of tiles of a level.
+pub fn tile_counts(level: u32) -> (u32, u32) {
+    (2 << level, 1 << level)
+}
+
+/// An error that occurred while building or reading a [TilePyramid].
+#[derive(Debug)]
+pub enum TileError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Image(ImageError),
+    /// The description of the pyramid is not supported by this build.
+    Unsupported(String),
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileError::Io(err) => write!(f, "{err}"),
+            TileError::Json(err) => write!(f, "invalid tile pyramid: {err}"),
+            TileError::Image(err) => write!(f, "{err}"),
+            TileError::Unsupported(reason) => write!(f, "unsupported tile pyramid: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for TileError {}
+
+impl From<io::Error> for TileError {
+    fn from(err: io::Error) -> Self {
+        TileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for TileError {
+    fn from(err: serde_json::Error) -> Self {
+        TileError::Json(err)
+    }
+}
+
+impl From<ImageError> for TileError {
+    fn from(err: ImageError) -> Self {
+        TileError::Image(err)
+    }
+}
+
+/// The contents of the manifest of a [TilePyramid].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    tile_size: u32,
+    border: u32,
+    max_level: u32,
+    /// The image file a pyramid cached by [TilePyramid::cached] was built from.
+    #[serde(default)]
+    source: Option<SourceFile>,
+}
+
+impl Manifest {
+    fn read(folder: &Path) -> Result<Self, TileError> {
+        Ok(serde_json::from_str(&fs::read_to_string(folder.join(MANIFEST_NAME))?)?)
+    }
+}
+
+/// Identifies the version of an image file, so that a cached pyramid is rebuilt when it changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct SourceFile {
+    /// The size of the file in bytes.
+    size: u64,
+    /// The time the file was last modified, if the platform records it.
+    modified: Option<SystemTime>,
+}
+
+impl SourceFile {
+    fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self { size: metadata.len(), modified: metadata.modified().ok() })
+    }
+}
+
+/// An equirectangular map split into tiles at several levels of detail, stored in a folder.
+///
+/// Each level halves the resolution of the level below it, down to level `0`, which covers the
+/// whole earth with two tiles. Tiles are stored as `<level>/<x>_<y>.png` and include a border of
+/// [TILE_BORDER] pixels taken from their neighbours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilePyramid {
+    folder: PathBuf,
+    max_level: u32,
+}
+
+impl TilePyramid {
+    /// Opens a pyramid written by [TilePyramid::cached].
+    ///
+    /// Arguments:
+    ///
+    /// * `folder`: The folder containing the manifest and the tiles.
+    pub fn open(folder: &Path) -> Result<Self, TileError> {
+        let manifest = Manifest::read(folder)?;
+        if manifest.tile_size != TILE_SIZE || manifest.border != TILE_BORDER {
+            return Err(TileError::Unsupported(format!(
+                "tiles of {} pixels with a border of {}",
+                manifest.tile_size, manifest.border
+            )));
+        }
+        if manifest.max_level > MAX_LEVEL {
+            return Err(TileError::Unsupported(format!("{} levels", manifest.max_level + 1)));
+        }
+        Ok(Self { folder: folder.to_path_buf(), max_level: manifest.max_level })
+    }
+
+    /// Whether a folder contains a pyramid.
+    pub fn exists(folder: &Path) -> bool {
+        folder.join(MANIFEST_NAME).is_file()
+    }
+
+    /// Splits an image into a pyramid like [TilePyramid::build_from], without a source file.
+    #[cfg(test)]
+    pub fn build(image: &DynamicImage, folder: &Path) -> Result<Self, TileError> {
+        Self::build_from(image, folder, None)
+    }
+
+    /// Splits an equirectangular image into a pyramid of tiles on disk.
+    ///
+    /// The finest level is the first one with at least the resolution of the image.
+    ///
+    /// Arguments:
+    ///
+    /// * `image`: The equirectangular map of the whole earth.
+    /// * `folder`: The folder the pyramid is written to. It is created if it does not exist.
+    /// * `source`: The file the image was read from, recorded to notice when it changes.
+    fn build_from(image: &DynamicImage, folder: &Path, source: Option<SourceFile>) -> Result<Self, TileError> {
+        let max_level = (image.height().div_ceil(TILE_SIZE).max(1).next_power_of_two().ilog2()).min(MAX_LEVEL);
+        let (columns, rows) = tile_counts(max_level);
+        let mut level_image = imageops::resize(&image.to_rgba8(), columns * TILE_SIZE, rows * TILE_SIZE, FilterType::Triangle);
+
+        for level in (0..=max_level).rev() {
+            if level < max_level {
+                let (width, height) = (level_image.width() / 2, level_image.height() / 2);
+                level_image = imageops::resize(&level_image, width, height, FilterType::Triangle);
+            }
+            let level_folder = folder.join(level.to_string());
+            fs::create_dir_all(&level_folder)?;
+            let (columns, rows) = tile_counts(level);
+            for y in 0..rows {
+                for x in 0..columns {
+                    let tile = cut_tile(&level_image, x, y);
+                    tile.save_with_format(level_folder.join(format!("{x}_{y}.png")), image::ImageFormat::Png)?;
+                }
+            }
+            log::info!("Wrote level {level} of the tile pyramid {}", folder.display());
+        }
+
+        // The manifest is written last, so that an interrupted build is not mistaken for a pyramid.
+        let manifest = Manifest { tile_size: TILE_SIZE, border: TILE_BORDER, max_level, source };
+        fs::write(folder.join(MANIFEST_NAME), serde_json::to_string_pretty(&manifest)?)?;
+        Ok(Self { folder: folder.to_path_buf(), max_level })
+    }
+
+    /// Opens the pyramid cached next to an image, building it first if it does not exist yet.
+    ///
+    /// The pyramid is rebuilt if the size or modification time of the image differ from those it
+    /// was built from.
+    ///
+    /// Arguments:
+    ///
+    /// * `image_path`: The path of the equirectangular image.
+    /// * `image`: The decoded image, which the pyramid is built from if needed.
+    pub fn cached(image_path: &Path, image: &DynamicImage) -> Result<Self, TileError> {
+        let mut folder = image_path.as_os_str().to_owned();
+        folder.push(".tiles");
+        let folder = PathBuf::from(folder);
+        let source = SourceFile::of(image_path)?;
+        if Self::exists(&folder) {
+            if Manifest::read(&folder)?.source == Some(source) {
+                return Self::open(&folder);
+            }
+            log::info!("The tiles of {} are out of date", image_path.display());
+            // Levels the new image does not need would otherwise be left behind.
+            fs::remove_dir_all(&folder)?;
+        }
+        log::info!("Splitting {} into tiles", image_path.display());
+        Self::build_from(image, &folder, Some(source))
+    }
+
+    /// The finest level of the pyramid.
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    /// Reads a tile, including its border.
+    pub fn load_tile(&self, tile: TileId) -> Result<RgbaImage, TileError> {
+        let path = self.folder.join(tile.level.to_string()).join(format!("{}_{}.png", tile.x, tile.y));
+        let image = load_image(&path)?.to_rgba8();
+        let size = TILE_SIZE + 2 * TILE_BORDER;
+        if image.dimensions() != (size, size) {
+            return Err(TileError::Unsupported(format!("{} is not {size} pixels wide and high", path.display())));
+        }
+        Ok(image)
+    }
+}
+
+/// Cuts a tile with its border out of the image of a level.
+///
+/// The border wraps around horizontally, where the map continues across the antimeridian, and
+/// repeats the outermost pixels at the poles.
+fn cut_tile(level_image: &RgbaImage, x: u32, y: u32) -> RgbaImage {
+    let size = TILE_SIZE + 2 * TILE_BORDER;
+    let (width, height) = level_image.dimensions();
+    RgbaImage::from_fn(size, size, |i, j| {
+        let px = (x * TILE_SIZE + i) as i64 - TILE_BORDER as i64;
+        let py = (y * TILE_SIZE + j) as i64 - TILE_BORDER as i64;
+        let px = px.rem_euclid(width as i64) as u32;
+        let py = py.clamp(0, height as i64 - 1) as u32;
+        *level_image.get_pixel(px, py)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn tiles_containing_coordinates() {
+        assert_eq!(TileId::containing(0, LatLon::new(45.0, -90.0)), TileId { level: 0, x: 0, y: 0 });
+        assert_eq!(TileId::containing(0, LatLon::new(-45.0, 90.0)), TileId { level: 0, x: 1, y: 0 });
+        assert_eq!(TileId::containing(1, LatLon::new(-90.0, 179.9)), TileId { level: 1, x: 3, y: 1 });
+        assert_eq!(TileId::containing(1, LatLon::new(0.0, 180.0)), TileId { level: 1, x: 0, y: 1 });
+        assert_eq!(TileId::containing(2, LatLon::new(10.0, 0.0)), TileId { level: 2, x: 4, y: 1 });
+    }
+
+    #[test]
+    fn tiles_lie_in_their_ancestors() {
+        let tile = TileId { level: 3, x: 13, y: 6 };
+        assert_eq!(tile.ancestor(3), tile);
+        assert_eq!(tile.ancestor(1), TileId { level: 1, x: 3, y: 1 });
+        assert_eq!(tile.ancestor(0), TileId { level: 0, x: 1, y: 0 });
+        assert!(tile.ancestor(2).covers(tile) && tile.covers(tile));
+        assert!(!tile.covers(tile.ancestor(2)));
+        assert!(!TileId { level: 1, x: 2, y: 1 }.covers(tile));
+    }
+
+    #[test]
+    fn borders_wrap_at_the_antimeridian() {
+        // Every column has its own color, so the border shows where it was copied from.
+        let image = RgbaImage::from_fn(2 * TILE_SIZE, TILE_SIZE, |x, _| Rgba([(x % 256) as u8, (x / 256) as u8, 0, 255]));
+
+        let tile = cut_tile(&image, 0, 0);
+
+        assert_eq!(tile.dimensions(), (TILE_SIZE + 2, TILE_SIZE + 2));
+        assert_eq!(*tile.get_pixel(0, 0), *image.get_pixel(2 * TILE_SIZE - 1, 0));
+        assert_eq!(*tile.get_pixel(1, 1), *image.get_pixel(0, 0));
+        assert_eq!(*tile.get_pixel(TILE_SIZE + 1, TILE_SIZE + 1), *image.get_pixel(TILE_SIZE, TILE_SIZE - 1));
+    }
+
+    #[test]
+    fn build_and_read_a_pyramid() {
+        let folder = std::env::temp_dir().join(format!("sitelen-pyramid-{}", std::process::id()));
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1000, 500, Rgba([10, 20, 30, 255])));
+
+        let pyramid = TilePyramid::build(&image, &folder).unwrap();
+        let opened = TilePyramid::open(&folder).unwrap();
+        let tile = opened.load_tile(TileId { level: 1, x: 3, y: 1 });
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(pyramid.max_level(), 1);
+        assert_eq!(opened, pyramid);
+        assert_eq!(*tile.unwrap().get_pixel(5, 5), Rgba([10, 20, 30, 255]));
+    }
+
+    #[test]
+    fn rebuilds_the_cache_when_the_image_changes() {
+        let folder = std::env::temp_dir().join(format!("sitelen-cache-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let image_path = folder.join("earth.png");
+        let load = |width, height, color| {
+            let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)));
+            image.save(&image_path).unwrap();
+            TilePyramid::cached(&image_path, &image).unwrap()
+        };
+
+        let first = load(600, 300, [10, 20, 30, 255]);
+        let tile = TileId { level: 1, x: 3, y: 1 };
+        let reused = TilePyramid::cached(&image_path, &DynamicImage::new_rgba8(1, 1)).unwrap();
+        let first_tile = reused.load_tile(tile).unwrap();
+        let second = load(1200, 600, [40, 50, 60, 255]);
+        let second_tile = second.load_tile(tile).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        // The cached pyramid is opened without looking at the image as long as the file is unchanged.
+        assert_eq!(reused, first);
+        assert_eq!(*first_tile.get_pixel(5, 5), Rgba([10, 20, 30, 255]));
+        assert_eq!((first.max_level(), second.max_level()), (1, 2));
+        assert_eq!(*second_tile.get_pixel(5, 5), Rgba([40, 50, 60, 255]));
+    }
+}
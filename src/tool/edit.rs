@@ -0,0 +1,66 @@
+use crate::{document::{Document, FeatureId}, geo::LatLon};
+
+/// A movable point of a feature, see [crate::document::Geometry::vertices].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexRef {
+    pub feature: FeatureId,
+    pub index: usize,
+}
+
+/// Selects features and drags their vertices around.
+#[derive(Debug, Default)]
+pub struct EditTool {
+    selected: Option<FeatureId>,
+    dragging: Option<VertexRef>,
+}
+
+impl EditTool {
+    /// Grabs the vertex closest to the cursor and selects its feature, ignoring hidden and locked layers.
+    ///
+    /// Arguments:
+    ///
+    /// * `document`: The document containing the features.
+    /// * `point`: The point under the cursor.
+    /// * `snap_angle`: How close in radians the cursor has to be to a vertex to grab it.
+    ///
+    /// Returns `false` if no vertex was close enough, which also clears the selection.
+    pub fn press(&mut self, document: &Document, point: LatLon, snap_angle: f64) -> bool {
+        let closest = document
+            .editable_features()
+            .flat_map(|f| {
+                f.geometry
+                    .vertices()
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(index, v)| (VertexRef { feature: f.id(), index }, v.angular_distance(point)))
+            })
+            .filter(|(_, distance)| *distance < snap_angle)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(vertex, _)| vertex);
+
+        self.dragging = closest;
+        self.selected = closest.map(|v| v.feature);
+        closest.is_some()
+    }
+
+    /// Stops dragging the grabbed vertex, keeping its feature selected.
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+
+    /// The vertex that follows the cursor, if any.
+    pub fn dragging(&self) -> Option<VertexRef> {
+        self.dragging
+    }
+
+    /// The selected feature, if any.
+    pub fn selected(&self) -> Option<FeatureId> {
+        self.selected
+    }
+
+    /// Clears the selection.
+    pub fn deselect(&mut self) {
+        self.selected = None;
+        self.dragging = None;
+    }
+}
@@ -0,0 +1,96 @@
+use crate::geo::{simplify, LatLon};
+
+/// Records a stroke while the left mouse button is dragged across the globe.
+#[derive(Debug, Default)]
+pub struct FreehandTool {
+    stroke: Option<Vec<LatLon>>,
+}
+
+impl FreehandTool {
+    /// Starts a new stroke, discarding any unfinished one.
+    ///
+    /// Arguments:
+    ///
+    /// * `start`: The first point of the stroke.
+    pub fn begin(&mut self, start: LatLon) {
+        self.stroke = Some(vec![start]);
+    }
+
+    /// Appends a point to the current stroke.
+    ///
+    /// Points closer than `min_spacing` radians to the previous one are skipped, so holding the
+    /// cursor still does not pile up duplicates.
+    ///
+    /// Arguments:
+    ///
+    /// * `point`: The point under the cursor.
+    /// * `min_spacing`: The smallest angular distance between two recorded points.
+    ///
+    /// Returns `true` if the point was recorded.
+    pub fn extend(&mut self, point: LatLon, min_spacing: f64) -> bool {
+        let Some(stroke) = &mut self.stroke else {
+            return false;
+        };
+        if stroke.last().is_some_and(|last| last.angular_distance(point) < min_spacing) {
+            return false;
+        }
+        stroke.push(point);
+        true
+    }
+
+    /// Ends the current stroke and returns it simplified.
+    ///
+    /// Arguments:
+    ///
+    /// * `tolerance`: The largest deviation from the recorded stroke in radians.
+    ///
+    /// Returns [None] if no stroke was active or it was too short to form a line.
+    pub fn finish(&mut self, tolerance: f64) -> Option<Vec<LatLon>> {
+        let stroke = simplify(&self.stroke.take()?, tolerance);
+        (stroke.len() >= 2).then_some(stroke)
+    }
+
+    /// The unfinished stroke, used to preview it while drawing.
+    pub fn stroke(&self) -> Option<&[LatLon]> {
+        self.stroke.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_strokes_give_no_line() {
+        let mut tool = FreehandTool::default();
+        assert_eq!(tool.finish(0.0), None);
+
+        tool.begin(LatLon::new(10.0, 20.0));
+        assert_eq!(tool.finish(0.0), None);
+        assert!(tool.stroke().is_none());
+    }
+
+    #[test]
+    fn skips_points_closer_than_the_spacing() {
+        let mut tool = FreehandTool::default();
+        assert!(!tool.extend(LatLon::new(0.0, 1.0), 0.0));
+
+        tool.begin(LatLon::new(0.0, 0.0));
+        let spacing = 1f64.to_radians();
+        assert!(!tool.extend(LatLon::new(0.0, 0.5), spacing));
+        assert!(tool.extend(LatLon::new(0.0, 2.0), spacing));
+        assert!(tool.extend(LatLon::new(0.0, 4.0), spacing));
+        assert_eq!(tool.stroke().map(<[_]>::len), Some(3));
+    }
+
+    #[test]
+    fn finishes_with_the_simplified_stroke() {
+        let mut tool = FreehandTool::default();
+        tool.begin(LatLon::new(0.0, 0.0));
+        for lon in 1..=10 {
+            tool.extend(LatLon::new(0.0, lon as f64), 0.0);
+        }
+        assert_eq!(tool.finish(1e-6), Some(vec![LatLon::new(0.0, 0.0), LatLon::new(0.0, 10.0)]));
+        assert_eq!(tool.finish(1e-6), None);
+    }
+}
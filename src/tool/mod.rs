@@ -0,0 +1,38 @@
+pub mod edit;
+pub mod freehand;
+pub mod path;
+pub mod polygon;
+
+pub use edit::EditTool;
+pub use freehand::FreehandTool;
+pub use path::PathTool;
+pub use polygon::PolygonTool;
+
+/// The tools that can be used to draw on the globe with the left mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolKind {
+    /// Records freehand strokes, see [FreehandTool].
+    #[default]
+    Freehand,
+    /// Places the anchors of Bezier paths, see [PathTool].
+    Path,
+    /// Places the corners of filled areas, see [PolygonTool].
+    Polygon,
+    /// Selects features and moves their vertices, see [EditTool].
+    Edit,
+}
+
+impl ToolKind {
+    /// All tools, in the order they are shown in the tool palette.
+    pub const ALL: [ToolKind; 4] = [ToolKind::Freehand, ToolKind::Path, ToolKind::Polygon, ToolKind::Edit];
+
+    /// The name of the tool as shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            ToolKind::Freehand => "Freehand",
+            ToolKind::Path => "Path",
+            ToolKind::Polygon => "Polygon",
+            ToolKind::Edit => "Edit",
+        }
+    }
+}
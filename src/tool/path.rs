@@ -0,0 +1,133 @@
+use crate::{document::{BezierPath, PathAnchor}, geo::LatLon};
+
+/// Builds a [BezierPath] anchor by anchor.
+///
+/// Clicking places an anchor, dragging before releasing the button pulls out its handles.
+/// Clicking the first anchor again closes the path.
+#[derive(Debug, Default)]
+pub struct PathTool {
+    anchors: Vec<PathAnchor>,
+    /// Whether the handles of the last anchor follow the cursor.
+    dragging: bool,
+}
+
+impl PathTool {
+    /// Places a new anchor or closes the path.
+    ///
+    /// Arguments:
+    ///
+    /// * `point`: The point under the cursor.
+    /// * `snap_angle`: How close in radians the cursor has to be to the first anchor to close the path.
+    ///
+    /// Returns the finished path if this closed it.
+    pub fn press(&mut self, point: LatLon, snap_angle: f64) -> Option<BezierPath> {
+        if self.anchors.len() > 2 && self.anchors[0].point.angular_distance(point) < snap_angle {
+            let anchors = std::mem::take(&mut self.anchors);
+            return Some(BezierPath { anchors, closed: true });
+        }
+
+        self.anchors.push(PathAnchor::corner(point));
+        self.dragging = true;
+        None
+    }
+
+    /// Moves the handles of the anchor placed last, keeping them symmetric.
+    ///
+    /// Arguments:
+    ///
+    /// * `point`: The point under the cursor, which becomes the outgoing handle.
+    pub fn drag(&mut self, point: LatLon) {
+        if !self.dragging {
+            return;
+        }
+        if let Some(anchor) = self.anchors.last_mut() {
+            anchor.out_handle = point;
+            anchor.in_handle = mirror(anchor.point, point);
+        }
+    }
+
+    /// Stops moving the handles of the anchor placed last.
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Ends the path without closing it.
+    ///
+    /// Returns [None] if fewer than two anchors were placed.
+    pub fn finish(&mut self) -> Option<BezierPath> {
+        self.dragging = false;
+        let anchors = std::mem::take(&mut self.anchors);
+        (anchors.len() >= 2).then_some(BezierPath { anchors, closed: false })
+    }
+
+    /// Discards the path under construction.
+    pub fn cancel(&mut self) {
+        self.anchors.clear();
+        self.dragging = false;
+    }
+
+    /// Whether a path is currently being built.
+    pub fn is_active(&self) -> bool {
+        !self.anchors.is_empty()
+    }
+
+    /// The path under construction, used to preview it.
+    pub fn path(&self) -> Option<BezierPath> {
+        self.is_active().then(|| BezierPath { anchors: self.anchors.clone(), closed: false })
+    }
+}
+
+/// Reflects `handle` through `point` along the great circle connecting them.
+fn mirror(point: LatLon, handle: LatLon) -> LatLon {
+    let (p, h) = (point.to_unit_vector(), handle.to_unit_vector());
+    LatLon::from_unit_vector(2.0 * p.dot(h) * p - h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAP: f64 = 0.01;
+
+    #[test]
+    fn pressing_the_first_anchor_closes_the_path() {
+        let mut tool = PathTool::default();
+        let points = [LatLon::new(0.0, 0.0), LatLon::new(0.0, 10.0), LatLon::new(10.0, 5.0)];
+        for point in points {
+            assert_eq!(tool.press(point, SNAP), None);
+            tool.release();
+        }
+
+        let path = tool.press(LatLon::new(0.1, 0.1), SNAP).expect("the path should be closed");
+        assert!(path.closed);
+        assert_eq!(path.anchors, points.map(PathAnchor::corner));
+        assert!(!tool.is_active());
+    }
+
+    #[test]
+    fn needs_three_anchors_to_close() {
+        let mut tool = PathTool::default();
+        tool.press(LatLon::new(0.0, 0.0), SNAP);
+        tool.press(LatLon::new(0.0, 10.0), SNAP);
+        assert_eq!(tool.press(LatLon::new(0.0, 0.0), SNAP), None);
+        assert_eq!(tool.path().map(|path| path.anchors.len()), Some(3));
+
+        let path = tool.finish().expect("the path has enough anchors");
+        assert!(!path.closed);
+        assert_eq!(tool.finish(), None);
+    }
+
+    #[test]
+    fn dragging_mirrors_the_handles() {
+        let mut tool = PathTool::default();
+        tool.press(LatLon::new(0.0, 0.0), SNAP);
+        tool.drag(LatLon::new(0.0, 5.0));
+        tool.release();
+        // Released anchors no longer follow the cursor.
+        tool.drag(LatLon::new(20.0, 20.0));
+
+        let anchor = tool.path().expect("a path is being built").anchors[0];
+        assert_eq!(anchor.out_handle, LatLon::new(0.0, 5.0));
+        assert!(anchor.in_handle.angular_distance(LatLon::new(0.0, -5.0)) < 1e-9);
+    }
+}
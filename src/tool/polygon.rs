@@ -0,0 +1,46 @@
+use crate::{document::Polygon, geo::LatLon};
+
+/// Builds a [Polygon] by clicking its corners.
+///
+/// Clicking the first corner again closes the polygon.
+#[derive(Debug, Default)]
+pub struct PolygonTool {
+    corners: Vec<LatLon>,
+}
+
+impl PolygonTool {
+    /// Places a new corner or closes the polygon.
+    ///
+    /// Arguments:
+    ///
+    /// * `point`: The point under the cursor.
+    /// * `snap_angle`: How close in radians the cursor has to be to the first corner to close the polygon.
+    ///
+    /// Returns the finished polygon if this closed it.
+    pub fn press(&mut self, point: LatLon, snap_angle: f64) -> Option<Polygon> {
+        if self.corners.len() > 2 && self.corners[0].angular_distance(point) < snap_angle {
+            return self.finish();
+        }
+        self.corners.push(point);
+        None
+    }
+
+    /// Closes the polygon after its last corner.
+    ///
+    /// Returns [None] if fewer than three corners were placed.
+    pub fn finish(&mut self) -> Option<Polygon> {
+        let corners = std::mem::take(&mut self.corners);
+        (corners.len() >= 3).then(|| Polygon::new(corners).normalized())
+    }
+
+    /// Discards the polygon under construction.
+    pub fn cancel(&mut self) {
+        self.corners.clear();
+    }
+
+    /// The outline of the polygon under construction, used to preview it.
+    pub fn outline(&self) -> Option<Vec<LatLon>> {
+        let first = self.corners.first()?;
+        Some(self.corners.iter().chain(std::iter::once(first)).copied().collect())
+    }
+}
@@ -0,0 +1,385 @@
+use std::fmt;
+
+use egui::{Context, ViewportId};
+use egui_wgpu::ScreenDescriptor;
+use glam::Vec2;
+use wgpu::{CommandEncoderDescriptor, Device, LoadOp, Operations, Queue, RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TextureFormat, TextureView};
+use winit::{event::WindowEvent, window::Window};
+
+use crate::{document::{FeatureId, Fill, Layer, LayerId, LineCap, LineJoin, Stroke, StrokeWidth, Style}, graticule::{Graticule, SPACINGS}, tool::ToolKind};
+
+/// The size of the labels of the graticule in points.
+const LABEL_SIZE: f32 = 12.0;
+
+/// The panels drawn with egui on top of the globe.
+///
+/// Window events are forwarded to egui before the app handles them, and the panels are drawn in
+/// a render pass of their own after the globe.
+pub struct Ui {
+    context: Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+}
+
+impl Ui {
+    /// Creates the panels of a window.
+    ///
+    /// Arguments:
+    ///
+    /// * `window`: The window the panels are shown in.
+    /// * `device`: The wgpu device used for rendering.
+    /// * `format`: The format of the surface of the window.
+    pub fn new(window: &Window, device: &Device, format: TextureFormat) -> Self {
+        let context = Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            window.theme(),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        // The panels are drawn into the resolved surface, so they need neither depth nor MSAA.
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+        Self { context, state, renderer }
+    }
+
+    /// Forwards a window event to egui and requests a redraw if the panels change.
+    ///
+    /// Returns `true` if egui used the event, like a key typed into a text field, so that the app
+    /// should ignore it.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let response = self.state.on_window_event(window, event);
+        if response.repaint {
+            window.request_redraw();
+        }
+        response.consumed
+    }
+
+    /// Whether the cursor is over a panel or dragging a widget, so that clicks and the mouse wheel
+    /// must not move the camera or draw on the globe.
+    pub fn wants_pointer(&self) -> bool {
+        self.context.is_pointer_over_area() || self.context.is_using_pointer()
+    }
+
+    /// Lays out the panels and draws them on top of what is in a texture.
+    ///
+    /// Arguments:
+    ///
+    /// * `window`: The window the panels are shown in.
+    /// * `device`: The wgpu device used for rendering.
+    /// * `queue`: The queue of `device`.
+    /// * `view`: The surface texture of the window, which already contains the globe.
+    /// * `panels`: The state shown and changed by the panels.
+    pub fn draw(&mut self, window: &Window, device: &Device, queue: &Queue, view: &TextureView, panels: &mut Panels) {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, |context| panels.show(context));
+        self.state.handle_platform_output(window, output.platform_output);
+        if output.viewport_output.get(&ViewportId::ROOT).is_some_and(|viewport| viewport.repaint_delay.is_zero()) {
+            window.request_redraw();
+        }
+
+        let primitives = self.context.tessellate(output.shapes, output.pixels_per_point);
+        let size = window.inner_size();
+        let screen = ScreenDescriptor { size_in_pixels: [size.width, size.height], pixels_per_point: output.pixels_per_point };
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: Some("UI Encoder") });
+        let commands = self.renderer.update_buffers(device, queue, &mut encoder, &primitives, &screen);
+        {
+            let mut r_pass = encoder
+                .begin_render_pass(&RenderPassDescriptor {
+                    label: Some("UI Render Pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: Operations { load: LoadOp::Load, store: StoreOp::Store },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                })
+                .forget_lifetime();
+            self.renderer.render(&mut r_pass, &primitives, &screen);
+        }
+        queue.submit(commands.into_iter().chain(Some(encoder.finish())));
+
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+impl fmt::Debug for Ui {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ui").field("context", &self.context).finish_non_exhaustive()
+    }
+}
+
+/// A change to the layers of the document picked in the layer panel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerAction {
+    /// Adds an empty layer above the active one.
+    Add,
+    /// Makes a layer the one new features are added to.
+    Select(LayerId),
+    /// Replaces the properties of a layer. `merge` is set while the same control keeps changing
+    /// it, so that a whole drag of a slider is undone at once.
+    Set { layer: Layer, merge: bool },
+    /// Moves a layer to a position in the drawing order, with `0` being the bottom.
+    Move(LayerId, usize),
+    MergeDown(LayerId),
+    Duplicate(LayerId),
+    Remove(LayerId),
+}
+
+/// The feature selected with the edit tool, whose style the style panel shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub feature: FeatureId,
+    /// The style the feature is drawn with.
+    pub style: Style,
+    /// Whether the style belongs to the feature instead of its layer.
+    pub own: bool,
+}
+
+/// A new style for a feature picked in the style panel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleChange {
+    pub feature: FeatureId,
+    /// The style of the feature, or `None` to draw it with the style of its layer.
+    pub style: Option<Style>,
+    /// Whether the change continues the previous one, see [LayerAction::Set].
+    pub merge: bool,
+}
+
+/// The state of the app that the panels show and change.
+///
+/// It is copied out of [crate::graphics::Graphics] before the panels are drawn and compared
+/// afterwards, so that changes go through the same methods as keyboard shortcuts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Panels {
+    pub tool: ToolKind,
+    /// The layers of the document from the bottom to the top.
+    pub layers: Vec<Layer>,
+    pub active_layer: LayerId,
+    /// The change picked in the layer panel, if any.
+    pub layer_action: Option<LayerAction>,
+    pub selection: Option<Selection>,
+    /// The change picked in the style panel for the selected feature, if any. Changes to the
+    /// style of the active layer are a [LayerAction::Set] instead.
+    pub style_change: Option<StyleChange>,
+    pub graticule: Graticule,
+    /// The labels of the graticule and the pixels they are centred on.
+    pub graticule_labels: Vec<(Vec2, String)>,
+}
+
+impl Panels {
+    fn show(&mut self, context: &Context) {
+        self.paint_graticule_labels(context);
+
+        egui::Window::new("Tools")
+            .resizable(false)
+            .default_pos([8.0, 8.0])
+            .show(context, |ui| {
+                for tool in ToolKind::ALL {
+                    ui.selectable_value(&mut self.tool, tool, tool.name());
+                }
+            });
+
+        egui::Window::new("Layers")
+            .resizable(false)
+            .default_pos([8.0, 160.0])
+            .show(context, |ui| self.show_layers(ui));
+
+        egui::Window::new("Style")
+            .resizable(false)
+            .default_pos([8.0, 420.0])
+            .show(context, |ui| self.show_style(ui));
+
+        egui::Window::new("Graticule")
+            .resizable(false)
+            .default_pos([140.0, 8.0])
+            .show(context, |ui| self.show_graticule(ui));
+    }
+
+    /// Paints the labels of the graticule behind the panels.
+    fn paint_graticule_labels(&self, context: &Context) {
+        let painter = context.layer_painter(egui::LayerId::background());
+        let font = egui::FontId::proportional(LABEL_SIZE);
+        for (pixel, text) in &self.graticule_labels {
+            let pos = egui::pos2(pixel.x, pixel.y) / context.pixels_per_point();
+            // A shadow keeps the labels readable over bright parts of the globe.
+            painter.text(pos + egui::vec2(1.0, 1.0), egui::Align2::CENTER_CENTER, text, font.clone(), egui::Color32::from_black_alpha(160));
+            painter.text(pos, egui::Align2::CENTER_CENTER, text, font.clone(), egui::Color32::from_gray(230));
+        }
+    }
+
+    /// Shows whether and how the graticule is drawn.
+    fn show_graticule(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.graticule.visible, "Show");
+        ui.add_enabled_ui(self.graticule.visible, |ui| {
+            ui.checkbox(&mut self.graticule.labels, "Labels");
+            let name = |spacing: Option<f64>| spacing.map_or("Auto".to_string(), |spacing| format!("{spacing}°"));
+            egui::ComboBox::from_label("Spacing").selected_text(name(self.graticule.spacing)).show_ui(ui, |ui| {
+                for spacing in std::iter::once(None).chain(SPACINGS.map(Some)) {
+                    ui.selectable_value(&mut self.graticule.spacing, spacing, name(spacing));
+                }
+            });
+        });
+    }
+
+    /// Lists the layers from the top to the bottom, followed by the properties of the active one.
+    fn show_layers(&mut self, ui: &mut egui::Ui) {
+        let mut action = None;
+        for layer in self.layers.iter().rev() {
+            let mut edited = layer.clone();
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut edited.visible, "👁").on_hover_text("Visible");
+                ui.toggle_value(&mut edited.locked, "🔒").on_hover_text("Locked");
+                if ui.selectable_label(layer.id() == self.active_layer, &layer.name).clicked() {
+                    action = Some(LayerAction::Select(layer.id()));
+                }
+            });
+            if edited != *layer {
+                action = Some(LayerAction::Set { layer: edited, merge: false });
+            }
+        }
+
+        let Some(index) = self.layers.iter().position(|layer| layer.id() == self.active_layer) else {
+            self.layer_action = action;
+            return;
+        };
+        let active = &self.layers[index];
+        let id = active.id();
+        ui.separator();
+
+        let mut edited = active.clone();
+        let name = ui.text_edit_singleline(&mut edited.name);
+        let opacity = ui.add(egui::Slider::new(&mut edited.opacity, 0.0..=1.0).text("Opacity"));
+        if edited != *active {
+            // Typing a name and dragging the slider each become a single undo step.
+            let merge = name.changed() || continues_drag(&opacity);
+            action = Some(LayerAction::Set { layer: edited, merge });
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                action = Some(LayerAction::Add);
+            }
+            if ui.button("Duplicate").clicked() {
+                action = Some(LayerAction::Duplicate(id));
+            }
+            if ui.add_enabled(self.layers.len() > 1, egui::Button::new("Remove")).clicked() {
+                action = Some(LayerAction::Remove(id));
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.add_enabled(index + 1 < self.layers.len(), egui::Button::new("Raise")).clicked() {
+                action = Some(LayerAction::Move(id, index + 1));
+            }
+            if ui.add_enabled(index > 0, egui::Button::new("Lower")).clicked() {
+                action = Some(LayerAction::Move(id, index - 1));
+            }
+            if ui.add_enabled(index > 0, egui::Button::new("Merge down")).clicked() {
+                action = Some(LayerAction::MergeDown(id));
+            }
+        });
+        self.layer_action = action;
+    }
+
+    /// Shows the style of the selected feature, or of the active layer if nothing is selected.
+    fn show_style(&mut self, ui: &mut egui::Ui) {
+        if let Some(selection) = &self.selection {
+            let mut own = selection.own;
+            let toggled = ui
+                .checkbox(&mut own, "Own style")
+                .on_hover_text("Draw the selected feature differently from its layer")
+                .changed();
+            let mut style = selection.style.clone();
+            let merge = ui.add_enabled_ui(own, |ui| style_editor(ui, &mut style)).inner;
+            if toggled || style != selection.style {
+                self.style_change = Some(StyleChange { feature: selection.feature, style: own.then_some(style), merge: merge && !toggled });
+            }
+            return;
+        }
+
+        let Some(active) = self.layers.iter().find(|layer| layer.id() == self.active_layer) else {
+            return;
+        };
+        ui.label(format!("Layer \"{}\"", active.name));
+        let mut edited = active.clone();
+        let merge = style_editor(ui, &mut edited.style);
+        if edited != *active {
+            self.layer_action = Some(LayerAction::Set { layer: edited, merge });
+        }
+    }
+}
+
+/// Whether a widget is dragged beyond the first frame, so that its changes form one undo step.
+fn continues_drag(response: &egui::Response) -> bool {
+    response.dragged() && !response.drag_started()
+}
+
+/// Shows the controls of a style.
+///
+/// Returns whether a change continues the previous one, like dragging a value or picking a color.
+fn style_editor(ui: &mut egui::Ui, style: &mut Style) -> bool {
+    let mut merge = false;
+    let mut stroked = style.stroke.is_some();
+    if ui.checkbox(&mut stroked, "Stroke").changed() {
+        style.stroke = stroked.then(Stroke::default);
+    }
+    if let Some(stroke) = &mut style.stroke {
+        ui.horizontal(|ui| {
+            merge |= ui.color_edit_button_rgba_unmultiplied(&mut stroke.color).changed();
+            let (mut width, mut kilometres) = match stroke.width {
+                StrokeWidth::Pixels(width) => (width, false),
+                StrokeWidth::Kilometres(width) => (width, true),
+            };
+            merge |= continues_drag(&ui.add(egui::DragValue::new(&mut width).range(0.0..=1000.0).speed(0.1)));
+            egui::ComboBox::from_id_salt("width_unit")
+                .selected_text(if kilometres { "km" } else { "px" })
+                .width(48.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut kilometres, false, "px");
+                    ui.selectable_value(&mut kilometres, true, "km");
+                });
+            stroke.width = if kilometres { StrokeWidth::Kilometres(width) } else { StrokeWidth::Pixels(width) };
+        });
+
+        // Only a single dash and gap can be edited, a dash of `0` draws a solid line.
+        ui.horizontal(|ui| {
+            let mut dash = stroke.dash.first().copied().unwrap_or(0.0);
+            let mut gap = stroke.dash.get(1).copied().unwrap_or(dash);
+            let dash_response = ui.add(egui::DragValue::new(&mut dash).range(0.0..=1000.0).speed(0.1).prefix("Dash "));
+            let gap_response = ui.add(egui::DragValue::new(&mut gap).range(0.0..=1000.0).speed(0.1).prefix("Gap "));
+            if dash_response.changed() || gap_response.changed() {
+                stroke.dash = if dash > 0.0 { vec![dash, gap] } else { vec![] };
+                merge |= continues_drag(&dash_response) || continues_drag(&gap_response);
+            }
+        });
+
+        egui::ComboBox::from_label("Cap").selected_text(stroke.cap.name()).show_ui(ui, |ui| {
+            for cap in LineCap::ALL {
+                ui.selectable_value(&mut stroke.cap, cap, cap.name());
+            }
+        });
+        egui::ComboBox::from_label("Join").selected_text(stroke.join.name()).show_ui(ui, |ui| {
+            for join in LineJoin::ALL {
+                ui.selectable_value(&mut stroke.join, join, join.name());
+            }
+        });
+    }
+
+    let mut filled = style.fill.is_some();
+    if ui.checkbox(&mut filled, "Fill").changed() {
+        style.fill = filled.then(Fill::default);
+    }
+    if let Some(fill) = &mut style.fill {
+        merge |= ui.color_edit_button_rgba_unmultiplied(&mut fill.color).changed();
+    }
+    merge
+}
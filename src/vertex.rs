@@ -33,3 +33,90 @@ impl Vertex {
         }
     }
 }
+
+/// A vertex of a line or area drawn on top of the globe.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl ColorVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<ColorVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A segment of a line drawn as a quad on screen by `line.wgsl`, one instance per segment.
+///
+/// The neighbouring points shape the corners between segments. Distances are in world units.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineSegment {
+    /// The point before `a`, ignored at the start of a line.
+    pub prev: [f32; 3],
+    pub a: [f32; 3],
+    pub b: [f32; 3],
+    /// The point after `b`, ignored at the end of a line.
+    pub next: [f32; 3],
+    pub color: [f32; 4],
+    /// The lengths of up to two dashes and gaps in the unit of the width, or zeros for a solid line.
+    pub dash: [f32; 4],
+    /// The width in pixels or, with [LineSegment::KILOMETRES], in kilometres.
+    pub width: f32,
+    /// The distance along the line to `a` and to `b`.
+    pub along: [f32; 2],
+    /// The flags below together with the cap shifted by [LineSegment::CAP_SHIFT] and the join
+    /// shifted by [LineSegment::JOIN_SHIFT].
+    pub flags: u32,
+}
+
+impl LineSegment {
+    /// The width is given in kilometres on the ground.
+    pub const KILOMETRES: u32 = 1;
+    /// `a` is the first point of the line.
+    pub const LINE_START: u32 = 2;
+    /// `b` is the last point of the line.
+    pub const LINE_END: u32 = 4;
+    pub const CAP_SHIFT: u32 = 3;
+    pub const JOIN_SHIFT: u32 = 5;
+    /// The segment lies on the globe and is hidden behind its horizon.
+    pub const ON_GLOBE: u32 = 128;
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x3,
+        3 => Float32x3,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32,
+        7 => Float32x2,
+        8 => Uint32,
+    ];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineSegment>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}